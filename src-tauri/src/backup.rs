@@ -0,0 +1,339 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+const BACKUP_DIR_NAME: &str = "backups";
+const DEFAULT_KEEP_COUNT: usize = 10;
+
+/// How the previous contents of a file are kept when a save replaces it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackupMode {
+    #[default]
+    Off,
+    /// One `file~` beside the file, replaced on every save.
+    Single,
+    /// `file.~1~`, `file.~2~`, ... beside the file, as GNU `cp --backup=numbered`.
+    Numbered,
+    /// Time-stamped copies in a central directory.
+    Timestamped,
+}
+
+impl BackupMode {
+    fn from_setting(value: &str) -> Self {
+        match value {
+            "single" => Self::Single,
+            "numbered" => Self::Numbered,
+            "timestamped" => Self::Timestamped,
+            _ => Self::Off,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupPolicy {
+    mode: BackupMode,
+    /// How many numbered or time-stamped backups to keep per file.
+    keep_count: usize,
+    directory: PathBuf,
+}
+
+impl BackupPolicy {
+    /// Reads `backupMode`, `backupKeepCount` and `backupDirectory` from the
+    /// settings store. Anything missing or malformed falls back to the
+    /// default, so a bad setting never blocks a save.
+    pub fn from_settings(app: &tauri::AppHandle) -> Self {
+        use tauri_plugin_store::StoreExt;
+
+        let store = app.store(crate::SETTINGS_STORE_FILE).ok();
+        let setting = |key: &str| store.as_ref().and_then(|store| store.get(key));
+
+        let mode = setting("backupMode")
+            .and_then(|value| value.as_str().map(BackupMode::from_setting))
+            .unwrap_or_default();
+        let keep_count = setting("backupKeepCount")
+            .and_then(|value| value.as_u64())
+            .filter(|count| *count > 0)
+            .map_or(DEFAULT_KEEP_COUNT, |count| count as usize);
+        let directory = setting("backupDirectory")
+            .and_then(|value| value.as_str().map(PathBuf::from))
+            .filter(|path| path.is_absolute())
+            .or_else(|| {
+                app.path()
+                    .app_data_dir()
+                    .ok()
+                    .map(|dir| dir.join(BACKUP_DIR_NAME))
+            })
+            .unwrap_or_default();
+
+        Self {
+            mode,
+            keep_count,
+            directory,
+        }
+    }
+}
+
+fn file_name_of(target: &Path) -> Result<String, String> {
+    target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            format!(
+                "Cannot determine file name for '{}'",
+                target.to_string_lossy()
+            )
+        })
+}
+
+fn list_dir_names(dir: &Path) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(dir).map_err(|error| {
+        format!(
+            "Unable to read backup directory '{}': {error}",
+            dir.to_string_lossy()
+        )
+    })?;
+    Ok(entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect())
+}
+
+/// Number of an existing `name.~N~` backup, if `candidate` is one.
+fn backup_number(candidate: &str, name: &str) -> Option<u64> {
+    candidate
+        .strip_prefix(name)?
+        .strip_prefix(".~")?
+        .strip_suffix('~')?
+        .parse()
+        .ok()
+}
+
+/// UTC time as `YYYYMMDDTHHMMSS.mmmZ`, which sorts in time order.
+fn format_timestamp(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs();
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let shifted = days as i64 + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}.{:03}Z",
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Backups in the central directory are named `name.<dir hash>.<timestamp>`
+/// so files with the same name in different directories keep separate
+/// histories.
+fn timestamped_prefix(target: &Path) -> Result<String, String> {
+    let parent = target
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(format!(
+        "{}.{:08x}.",
+        file_name_of(target)?,
+        crate::stable_path_hash(&parent) as u32
+    ))
+}
+
+/// Links (or, across file systems, copies) `source` to a temp name beside
+/// `backup_path` and renames it into place, so a backup is either complete
+/// or absent. Linking shares the old inode, which the save's rename then
+/// detaches from the target, leaving the backup as its only name; a save
+/// whose rename fails removes the backup again. A save that overwrites the
+/// inode in place must pass `allow_link: false`.
+fn preserve_atomically(source: &Path, backup_path: &Path, allow_link: bool) -> Result<(), String> {
+    let temp_path = crate::build_save_temp_path(backup_path, "backup")?;
+    let linked = if allow_link {
+        fs::hard_link(source, &temp_path)
+    } else {
+        Err(ErrorKind::Unsupported.into())
+    };
+    let result = linked
+        .or_else(|_| {
+            fs::copy(source, &temp_path)?;
+            fs::File::open(&temp_path)?.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, backup_path));
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!(
+            "Unable to write backup '{}': {error}",
+            backup_path.to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+fn remove_backups(dir: &Path, names: &[String]) {
+    for name in names {
+        if let Err(error) = fs::remove_file(dir.join(name)) {
+            log::warn!(
+                target: "wisty::backup",
+                "Unable to remove old backup '{name}': {error}"
+            );
+        }
+    }
+}
+
+/// Preserves the current contents of `target` according to `policy`, just
+/// before a save renames the new version over it. Returns the backup's
+/// path, or None when there was nothing to back up. Pruning old backups is
+/// best effort; failing to create the new one fails the save.
+pub fn back_up_before_replace(
+    target: &Path,
+    policy: &BackupPolicy,
+    now: SystemTime,
+    allow_link: bool,
+) -> Result<Option<PathBuf>, String> {
+    if policy.mode == BackupMode::Off {
+        return Ok(None);
+    }
+    match fs::symlink_metadata(target) {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) => return Ok(None),
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(format!(
+                "Unable to read '{}' for backup: {error}",
+                target.to_string_lossy()
+            ))
+        }
+    }
+
+    let name = file_name_of(target)?;
+    let parent = target.parent().unwrap_or_else(|| Path::new("."));
+
+    match policy.mode {
+        BackupMode::Off => Ok(None),
+        BackupMode::Single => {
+            let backup_path = parent.join(format!("{name}~"));
+            preserve_atomically(target, &backup_path, allow_link)?;
+            Ok(Some(backup_path))
+        }
+        BackupMode::Numbered => {
+            let mut numbers: Vec<u64> = list_dir_names(parent)?
+                .iter()
+                .filter_map(|candidate| backup_number(candidate, &name))
+                .collect();
+            numbers.sort_unstable();
+            let next = numbers.last().map_or(1, |last| last + 1);
+            let backup_path = parent.join(format!("{name}.~{next}~"));
+            preserve_atomically(target, &backup_path, allow_link)?;
+
+            numbers.push(next);
+            let excess = numbers.len().saturating_sub(policy.keep_count);
+            let stale: Vec<String> = numbers[..excess]
+                .iter()
+                .map(|number| format!("{name}.~{number}~"))
+                .collect();
+            remove_backups(parent, &stale);
+            Ok(Some(backup_path))
+        }
+        BackupMode::Timestamped => {
+            fs::create_dir_all(&policy.directory).map_err(|error| {
+                format!(
+                    "Unable to create backup directory '{}': {error}",
+                    policy.directory.to_string_lossy()
+                )
+            })?;
+            let prefix = timestamped_prefix(target)?;
+            let backup_path = policy
+                .directory
+                .join(format!("{prefix}{}", format_timestamp(now)));
+            preserve_atomically(target, &backup_path, allow_link)?;
+
+            let mut existing: Vec<String> = list_dir_names(&policy.directory)?
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(&prefix))
+                .collect();
+            existing.sort_unstable();
+            let excess = existing.len().saturating_sub(policy.keep_count);
+            remove_backups(&policy.directory, &existing[..excess]);
+            Ok(Some(backup_path))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn policy(mode: BackupMode, keep_count: usize, directory: PathBuf) -> BackupPolicy {
+        BackupPolicy {
+            mode,
+            keep_count,
+            directory,
+        }
+    }
+
+    #[test]
+    fn numbered_backups_count_up_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let target = dir.join("notes.txt");
+        let policy = policy(BackupMode::Numbered, 2, dir.to_path_buf());
+
+        for version in 1..=4 {
+            fs::write(&target, format!("v{version}")).unwrap();
+            let backup = back_up_before_replace(&target, &policy, SystemTime::now(), true).unwrap();
+            assert_eq!(backup, Some(dir.join(format!("notes.txt.~{version}~"))));
+            fs::remove_file(&target).unwrap();
+        }
+
+        let mut names = list_dir_names(dir).unwrap();
+        names.sort();
+        assert_eq!(names, ["notes.txt.~3~", "notes.txt.~4~"]);
+        assert_eq!(backup_number("notes.txt.~12~", "notes.txt"), Some(12));
+        assert_eq!(backup_number("notes.txt.~x~", "notes.txt"), None);
+    }
+
+    #[test]
+    fn timestamped_backups_are_pruned_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let central = dir.path().join("central");
+        let target = dir.path().join("notes.txt");
+        let policy = policy(BackupMode::Timestamped, 2, central.clone());
+        fs::write(&target, "text").unwrap();
+
+        for second in 0..3 {
+            let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000 + second);
+            back_up_before_replace(&target, &policy, now, true).unwrap();
+        }
+
+        let mut names = list_dir_names(&central).unwrap();
+        names.sort();
+        let prefix = timestamped_prefix(&target).unwrap();
+        assert_eq!(
+            names,
+            [
+                format!("{prefix}20231114T221321.000Z"),
+                format!("{prefix}20231114T221322.000Z")
+            ]
+        );
+        assert_eq!(format_timestamp(UNIX_EPOCH), "19700101T000000.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(format_timestamp(leap_day), "20240229T123456.789Z");
+    }
+}
@@ -0,0 +1,381 @@
+use crate::launch_position::{self, LaunchPosition};
+use crate::{launch_wait, single_instance, stdio};
+use serde::{Deserialize, Serialize};
+
+/// First line of `--help`, also printed after argument errors.
+const USAGE_LINE: &str = "Usage: wisty [OPTIONS] [[+LINE] FILE[:LINE[:COLUMN]]]...
+       wisty convert [--to html|text] [-o OUTPUT] [FILE]";
+
+const HELP_TEXT: &str = "\
+Open FILEs for editing, each in a window of its own. '-' reads standard input.
+When wisty is already running, the FILEs open there instead.
+
+Options:
+  +LINE                     Put the cursor of the next FILE on LINE
+  -l, --line LINE[:COLUMN]  Put the cursor of every FILE without one there
+  -r, --readonly            Open the FILEs read-only
+  -e, --encoding NAME       Read the FILEs as NAME (e.g. utf-8, latin1,
+                            shift_jis) instead of detecting it
+      --theme light|dark    Use this theme for these windows
+      --format formatted|plain
+                            Use this Markdown view for these windows
+      --stdout              Write the document from '-' to standard output
+                            when it is saved and the window closes
+      --wait                Return only after the FILEs are closed
+      --wait-require-save   Like --wait, but exit with status 1 unless every
+                            FILE was saved
+      --new-instance        Start a separate wisty even if one is running
+  -h, --help                Show this help and exit
+  -V, --version             Show the version and exit
+  --                        Treat every further argument as a FILE
+
+'wisty convert' renders the Markdown of the Formatted view (bold, italic and
+headings) without opening a window. It reads FILE, or standard input when FILE
+is '-' or missing, and writes to standard output unless given -o:
+      --to html|text        Write an HTML fragment (the default) or the text
+                            with its markup removed
+  -o, --output OUTPUT       Write to OUTPUT instead";
+
+/// The first argument that selects `wisty convert`.
+const CONVERT_COMMAND: &str = "convert";
+
+const THEME_MODES: &[&str] = &["light", "dark"];
+const FORMAT_VIEW_MODES: &[&str] = &["formatted", "plain"];
+const CONVERT_FORMATS: &[&str] = &["html", "text"];
+
+/// How a launch file should be shown. Sent to the frontend with the file, and
+/// to a running instance along with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOptions {
+    pub read_only: bool,
+    /// A WHATWG label that replaces encoding detection.
+    pub encoding: Option<String>,
+    pub theme_mode: Option<String>,
+    pub format_view_mode: Option<String>,
+}
+
+/// A path argument, with the position of a `+N` argument right before it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PathArg {
+    pub raw: String,
+    pub line_arg: Option<LaunchPosition>,
+}
+
+/// Everything a normal launch was asked for.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    pub paths: Vec<PathArg>,
+    pub file_options: FileOptions,
+    /// From `--line`: for files without a position of their own.
+    pub default_position: Option<LaunchPosition>,
+    pub stdout: bool,
+    pub wait: bool,
+    pub wait_require_save: bool,
+    pub new_instance: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConvertFormat {
+    #[default]
+    Html,
+    Text,
+}
+
+/// What `wisty convert` was asked for. A missing input, or `-`, is standard
+/// input; a missing output is standard output.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConvertOptions {
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub format: ConvertFormat,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Cli {
+    Launch(LaunchOptions),
+    Convert(ConvertOptions),
+    Help,
+    Version,
+}
+
+pub fn help_text() -> String {
+    format!("{USAGE_LINE}\n{HELP_TEXT}")
+}
+
+pub fn version_text() -> String {
+    format!("wisty {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Printed under an argument error instead of the whole help.
+pub fn error_hint() -> String {
+    format!("{USAGE_LINE}\nTry 'wisty --help' for more information.")
+}
+
+fn parse_choice(flag: &str, value: String, choices: &[&str]) -> Result<String, String> {
+    if choices.contains(&value.as_str()) {
+        Ok(value)
+    } else {
+        Err(format!(
+            "invalid value '{value}' for '{flag}' (expected {})",
+            choices.join(" or ")
+        ))
+    }
+}
+
+/// Splits `--name=value` into the flag and its inline value.
+fn split_inline_value(arg: &str) -> (String, Option<&str>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+        _ => (arg.to_string(), None),
+    }
+}
+
+/// Parses the arguments after the program name. Options may come before,
+/// between or after the paths; `--` ends them.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+    let mut options = LaunchOptions::default();
+    let mut line_arg: Option<(String, LaunchPosition)> = None;
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == CONVERT_COMMAND) {
+        args.next();
+        return parse_convert(args);
+    }
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            for raw in args.by_ref() {
+                let line_arg = line_arg.take().map(|(_, position)| position);
+                options.paths.push(PathArg { raw, line_arg });
+            }
+            break;
+        }
+
+        if let Some(position) = launch_position::parse_line_arg(&arg) {
+            line_arg = Some((arg, position));
+            continue;
+        }
+        if arg == stdio::STDIO_ARG || !arg.starts_with('-') {
+            let line_arg = line_arg.take().map(|(_, position)| position);
+            options.paths.push(PathArg { raw: arg, line_arg });
+            continue;
+        }
+
+        // `--name=value` and `--name value` are both accepted.
+        let (flag, inline_value) = split_inline_value(&arg);
+        let takes_value = matches!(
+            flag.as_str(),
+            "-l" | "--line" | "-e" | "--encoding" | "--theme" | "--format"
+        );
+        if inline_value.is_some() && !takes_value {
+            return Err(format!("'{flag}' doesn't take a value"));
+        }
+        let mut value = || -> Result<String, String> {
+            match inline_value {
+                Some(value) => Ok(value.to_string()),
+                None => args.next().ok_or_else(|| format!("'{flag}' needs a value")),
+            }
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Cli::Help),
+            "-V" | "--version" => return Ok(Cli::Version),
+            "-l" | "--line" => {
+                let raw = value()?;
+                options.default_position = Some(
+                    launch_position::parse_position(&raw)
+                        .ok_or_else(|| format!("invalid value '{raw}' for '{flag}'"))?,
+                );
+            }
+            "-r" | "--readonly" => options.file_options.read_only = true,
+            "-e" | "--encoding" => options.file_options.encoding = Some(value()?),
+            "--theme" => {
+                options.file_options.theme_mode = Some(parse_choice(&flag, value()?, THEME_MODES)?)
+            }
+            "--format" => {
+                options.file_options.format_view_mode =
+                    Some(parse_choice(&flag, value()?, FORMAT_VIEW_MODES)?)
+            }
+            stdio::STDOUT_FLAG => options.stdout = true,
+            launch_wait::WAIT_FLAG => options.wait = true,
+            launch_wait::WAIT_REQUIRE_SAVE_FLAG => {
+                options.wait = true;
+                options.wait_require_save = true;
+            }
+            single_instance::NEW_INSTANCE_FLAG => options.new_instance = true,
+            _ => return Err(format!("unknown option '{flag}'")),
+        }
+    }
+
+    if let Some((arg, _)) = line_arg {
+        return Err(format!("'{arg}' must be followed by a file path"));
+    }
+    Ok(Cli::Launch(options))
+}
+
+/// Parses the arguments after `convert`.
+fn parse_convert(mut args: impl Iterator<Item = String>) -> Result<Cli, String> {
+    let mut options = ConvertOptions::default();
+    let mut only_paths = false;
+
+    while let Some(arg) = args.next() {
+        if only_paths || arg == stdio::STDIO_ARG || !arg.starts_with('-') {
+            if options.input.is_some() {
+                return Err(format!("'{CONVERT_COMMAND}' takes a single input file"));
+            }
+            options.input = Some(arg);
+            continue;
+        }
+        if arg == "--" {
+            only_paths = true;
+            continue;
+        }
+
+        let (flag, inline_value) = split_inline_value(&arg);
+        let takes_value = matches!(flag.as_str(), "--to" | "-o" | "--output");
+        if inline_value.is_some() && !takes_value {
+            return Err(format!("'{flag}' doesn't take a value"));
+        }
+        let mut value = || -> Result<String, String> {
+            match inline_value {
+                Some(value) => Ok(value.to_string()),
+                None => args.next().ok_or_else(|| format!("'{flag}' needs a value")),
+            }
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Cli::Help),
+            "--to" => {
+                options.format = match parse_choice(&flag, value()?, CONVERT_FORMATS)?.as_str() {
+                    "text" => ConvertFormat::Text,
+                    _ => ConvertFormat::Html,
+                }
+            }
+            "-o" | "--output" => options.output = Some(value()?),
+            _ => return Err(format!("unknown option '{flag}'")),
+        }
+    }
+    Ok(Cli::Convert(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Cli, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn launch(args: &[&str]) -> LaunchOptions {
+        match parse_args(args) {
+            Ok(Cli::Launch(options)) => options,
+            other => panic!("expected a launch, got {other:?}"),
+        }
+    }
+
+    fn raw_paths(options: &LaunchOptions) -> Vec<&str> {
+        options.paths.iter().map(|path| path.raw.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_help_and_version_anywhere() {
+        assert_eq!(parse_args(&["notes.txt", "--help"]), Ok(Cli::Help));
+        assert_eq!(parse_args(&["-V"]), Ok(Cli::Version));
+        assert!(help_text().contains("--wait-require-save"));
+    }
+
+    #[test]
+    fn collects_paths_and_file_options() {
+        let options = launch(&[
+            "-r",
+            "--encoding=latin1",
+            "a.txt",
+            "+12",
+            "b.txt",
+            "--theme",
+            "dark",
+            "--line",
+            "3:4",
+            "-",
+            "--",
+            "--wait",
+        ]);
+        assert_eq!(raw_paths(&options), ["a.txt", "b.txt", "-", "--wait"]);
+        assert_eq!(
+            options.paths[1].line_arg,
+            Some(LaunchPosition {
+                line: 12,
+                column: None
+            })
+        );
+        assert_eq!(
+            options.file_options,
+            FileOptions {
+                read_only: true,
+                encoding: Some("latin1".to_string()),
+                theme_mode: Some("dark".to_string()),
+                format_view_mode: None,
+            }
+        );
+        assert_eq!(
+            options.default_position,
+            Some(LaunchPosition {
+                line: 3,
+                column: Some(4)
+            })
+        );
+        assert!(!options.wait);
+    }
+
+    #[test]
+    fn sets_launch_flags() {
+        let options = launch(&["--wait-require-save", "--new-instance", "--stdout"]);
+        assert!(options.wait && options.wait_require_save);
+        assert!(options.new_instance && options.stdout);
+        assert!(options.paths.is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            parse_args(&["--wiat", "notes.txt"]),
+            Err("unknown option '--wiat'".to_string())
+        );
+        assert_eq!(parse_args(&["-x"]), Err("unknown option '-x'".to_string()));
+        assert_eq!(
+            parse_args(&["--encoding"]),
+            Err("'--encoding' needs a value".to_string())
+        );
+        assert!(parse_args(&["--line", "0"]).is_err());
+        assert!(parse_args(&["--line=4:x"]).is_err());
+        assert!(parse_args(&["--theme", "blue"]).is_err());
+        assert!(parse_args(&["--wait=yes"]).is_err());
+        assert!(parse_args(&["notes.txt", "+4"]).is_err());
+    }
+
+    #[test]
+    fn parses_the_convert_command() {
+        assert_eq!(
+            parse_args(&["convert", "--to=text", "notes.md", "-o", "notes.txt"]),
+            Ok(Cli::Convert(ConvertOptions {
+                input: Some("notes.md".to_string()),
+                output: Some("notes.txt".to_string()),
+                format: ConvertFormat::Text,
+            }))
+        );
+        assert_eq!(
+            parse_args(&["convert"]),
+            Ok(Cli::Convert(ConvertOptions::default()))
+        );
+        assert_eq!(parse_args(&["convert", "--help"]), Ok(Cli::Help));
+        assert!(parse_args(&["convert", "--to", "pdf"]).is_err());
+        assert!(parse_args(&["convert", "a.md", "b.md"]).is_err());
+        assert!(parse_args(&["convert", "--wait"]).is_err());
+
+        // Only the first argument selects the command.
+        assert_eq!(
+            raw_paths(&launch(&["notes.md", "convert"])),
+            ["notes.md", "convert"]
+        );
+    }
+}
@@ -0,0 +1,687 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Where an OpenDocument extension lists its parts.
+const MANIFEST_PATH: &str = "META-INF/manifest.xml";
+
+/// Media type of the configuration files that describe an extension's
+/// dictionaries, usually `dictionaries.xcu`.
+const CONFIGURATION_MEDIA_TYPE: &str = "application/vnd.sun.star.configuration-data";
+
+/// Locations in a configuration file are relative to the file's directory,
+/// which they spell as this.
+const ORIGIN: &str = "%origin%";
+
+/// Largest file taken from a package. Thesauri are the biggest dictionary
+/// files at a few tens of megabytes; anything far past that isn't one.
+const MAX_FILE_BYTES: u64 = 128 * 1024 * 1024;
+
+/// What one package put into the dictionary directory, kept so removing its
+/// last dictionary can also remove the files that came with it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPackage {
+    /// Codes of its spelling dictionaries, each a `<code>.aff`/`<code>.dic`
+    /// pair.
+    pub codes: Vec<String>,
+    /// Names of the thesaurus and hyphenation files installed with them.
+    pub extra_files: Vec<String>,
+}
+
+/// A dictionary as a configuration file describes it.
+#[derive(Default)]
+struct ConfiguredDictionary {
+    /// `DICT_SPELL`, `DICT_HYPH` or `DICT_THES`.
+    format: String,
+    /// Paths of its files within the package.
+    locations: Vec<String>,
+}
+
+/// A spelling dictionary's files within a package.
+struct SpellingFiles {
+    code: String,
+    aff: String,
+    dic: String,
+}
+
+/// What to take from a package.
+#[derive(Default)]
+struct PackageContents {
+    spelling: Vec<SpellingFiles>,
+    /// Paths of thesaurus and hyphenation files within the package.
+    extras: Vec<String>,
+}
+
+/// The last part of a path within a package, if it makes a safe file name:
+/// not empty and not hidden, so nothing lands outside the dictionary
+/// directory or over a file of ours.
+fn file_name(path: &str) -> Option<&str> {
+    let name = path.rsplit('/').next()?;
+    (!name.is_empty() && !name.starts_with('.') && !name.contains('\\')).then_some(name)
+}
+
+/// Resolves a configuration file's `%origin%/...` location against the
+/// directory the configuration file is in.
+fn resolve_location(origin: &str, location: &str) -> Option<String> {
+    let relative = location.strip_prefix(ORIGIN)?;
+    let mut segments: Vec<&str> = origin
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    for segment in relative.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// The configuration files listed in an extension's manifest.
+fn manifest_configurations(xml: &[u8]) -> Result<Vec<String>, String> {
+    let mut reader = Reader::from_reader(xml);
+    let mut configurations = Vec::new();
+    loop {
+        match reader
+            .read_event()
+            .map_err(|error| format!("Unable to read package manifest: {error}"))?
+        {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"file-entry" =>
+            {
+                let media_type = attribute(&element, b"media-type");
+                if media_type.as_deref() == Some(CONFIGURATION_MEDIA_TYPE) {
+                    configurations.extend(attribute(&element, b"full-path"));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(configurations)
+}
+
+/// The dictionaries a configuration file describes: every `node` with a
+/// `Format` property, with the files in its `Locations`.
+fn configured_dictionaries(xml: &[u8]) -> Result<Vec<ConfiguredDictionary>, String> {
+    let mut reader = Reader::from_reader(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut nodes: Vec<ConfiguredDictionary> = Vec::new();
+    let mut dictionaries = Vec::new();
+    let mut property: Option<String> = None;
+    // Set inside a `value`, to the separator of its list.
+    let mut value_separator: Option<Option<String>> = None;
+    loop {
+        match reader
+            .read_event()
+            .map_err(|error| format!("Unable to read dictionary configuration: {error}"))?
+        {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"node" => nodes.push(ConfiguredDictionary::default()),
+                b"prop" => property = attribute(&element, b"name"),
+                b"value" => value_separator = Some(attribute(&element, b"separator")),
+                _ => {}
+            },
+            Event::Text(text) => {
+                let (Some(node), Some(name), Some(separator)) = (
+                    nodes.last_mut(),
+                    property.as_deref(),
+                    value_separator.as_ref(),
+                ) else {
+                    continue;
+                };
+                let text = text
+                    .unescape()
+                    .map_err(|error| format!("Unable to read dictionary configuration: {error}"))?;
+                match name {
+                    "Format" => node.format = text.trim().to_string(),
+                    "Locations" => {
+                        let items: Vec<&str> = match separator {
+                            Some(separator) => text.split(separator.as_str()).collect(),
+                            None => text.split_whitespace().collect(),
+                        };
+                        node.locations.extend(
+                            items
+                                .into_iter()
+                                .map(str::trim)
+                                .filter(|item| !item.is_empty())
+                                .map(str::to_string),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"node" => {
+                    if let Some(node) = nodes.pop().filter(|node| !node.format.is_empty()) {
+                        dictionaries.push(node);
+                    }
+                }
+                b"prop" => property = None,
+                b"value" => value_separator = None,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(dictionaries)
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|error| format!("Unable to read '{name}' from dictionary package: {error}"))?;
+    let mut bytes = Vec::new();
+    entry
+        .take(MAX_FILE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|error| format!("Unable to read '{name}' from dictionary package: {error}"))?;
+    if bytes.len() as u64 > MAX_FILE_BYTES {
+        return Err(format!("'{name}' in the dictionary package is too large"));
+    }
+    Ok(bytes)
+}
+
+/// Works out what to install from the package's manifest and configuration
+/// files. A zip without them is taken to hold `.aff`/`.dic` pairs.
+fn package_contents<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<PackageContents, String> {
+    let mut configured = Vec::new();
+    if archive.index_for_name(MANIFEST_PATH).is_some() {
+        let manifest = read_entry(archive, MANIFEST_PATH)?;
+        for configuration in manifest_configurations(&manifest)? {
+            let Some(name) = resolve_location("", &format!("{ORIGIN}/{configuration}")) else {
+                continue;
+            };
+            let origin = name.rsplit_once('/').map_or("", |(directory, _)| directory);
+            let xml = read_entry(archive, &name)?;
+            for dictionary in configured_dictionaries(&xml)? {
+                let locations = dictionary
+                    .locations
+                    .iter()
+                    .filter_map(|location| resolve_location(origin, location))
+                    .collect();
+                configured.push((dictionary.format, locations));
+            }
+        }
+    }
+
+    let mut contents = PackageContents::default();
+    if configured.is_empty() {
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        for aff in &names {
+            let Some(stem) = aff.strip_suffix(".aff") else {
+                continue;
+            };
+            let dic = format!("{stem}.dic");
+            if names.contains(&dic) {
+                configured.push(("DICT_SPELL".to_string(), vec![aff.clone(), dic]));
+            }
+        }
+    }
+
+    for (format, locations) in configured {
+        if format == "DICT_SPELL" {
+            let find = |extension: &str| {
+                locations
+                    .iter()
+                    .find(|location| location.ends_with(extension))
+                    .cloned()
+            };
+            let (Some(aff), Some(dic)) = (find(".aff"), find(".dic")) else {
+                continue;
+            };
+            let Some(code) = file_name(&aff).and_then(|name| name.strip_suffix(".aff")) else {
+                continue;
+            };
+            let code = code.to_string();
+            if !code.is_empty() && !contents.spelling.iter().any(|files| files.code == code) {
+                contents.spelling.push(SpellingFiles { code, aff, dic });
+            }
+        } else {
+            for location in locations {
+                if file_name(&location).is_some() && !contents.extras.contains(&location) {
+                    contents.extras.push(location);
+                }
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Copies a package entry to `path` without holding it in memory.
+fn extract_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    path: &Path,
+) -> Result<(), String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|error| format!("Unable to read '{name}' from dictionary package: {error}"))?;
+    let mut file =
+        fs::File::create(path).map_err(|error| format!("Unable to install dictionary: {error}"))?;
+    let copied = io::copy(&mut entry.take(MAX_FILE_BYTES + 1), &mut file)
+        .map_err(|error| format!("Unable to extract '{name}' from dictionary package: {error}"))?;
+    if copied > MAX_FILE_BYTES {
+        return Err(format!("'{name}' in the dictionary package is too large"));
+    }
+    Ok(())
+}
+
+/// Why an extra file named `name` can't be installed into `dir`, if it
+/// would replace a spelling dictionary's file, a file of another package,
+/// or another file of this one.
+fn extra_conflict(
+    dir: &Path,
+    name: &str,
+    installing: &InstalledPackage,
+    packages: &[InstalledPackage],
+) -> Option<String> {
+    let stem = name
+        .strip_suffix(".aff")
+        .or_else(|| name.strip_suffix(".dic"));
+    if let Some(stem) = stem {
+        let installed =
+            dir.join(format!("{stem}.aff")).exists() && dir.join(format!("{stem}.dic")).exists();
+        if installed || installing.codes.iter().any(|code| code == stem) {
+            return Some(format!(
+                "'{name}' in the dictionary package would replace dictionary '{stem}'"
+            ));
+        }
+    }
+    if installing
+        .extra_files
+        .iter()
+        .any(|existing| existing == name)
+    {
+        return Some(format!(
+            "The dictionary package has more than one file named '{name}'"
+        ));
+    }
+    // A package sharing a dictionary with this one is an earlier install of
+    // it, whose files this one replaces.
+    let other = packages.iter().any(|package| {
+        package.extra_files.iter().any(|existing| existing == name)
+            && !package
+                .codes
+                .iter()
+                .any(|code| installing.codes.contains(code))
+    });
+    other
+        .then(|| format!("'{name}' in the dictionary package belongs to another installed package"))
+}
+
+/// Moves each staged `.<name>.partial` file in `dir` to `name`. A file it
+/// replaces is set aside until every move has succeeded, so a failed move
+/// can put back what was there before.
+fn move_into_place(dir: &Path, names: &[String]) -> Result<(), String> {
+    let mut moved: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+    let mut result = Ok(());
+    for name in names {
+        let target = dir.join(name);
+        let previous = dir.join(format!(".{name}.previous"));
+        let set_aside = match fs::rename(&target, &previous) {
+            Ok(()) => Ok(Some(previous)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        };
+        result = set_aside.and_then(|previous| {
+            moved.push((target.clone(), previous));
+            fs::rename(dir.join(format!(".{name}.partial")), &target)
+        });
+        if result.is_err() {
+            break;
+        }
+    }
+
+    for (target, previous) in &moved {
+        match (previous, &result) {
+            (Some(previous), Ok(())) => {
+                let _ = fs::remove_file(previous);
+            }
+            (Some(previous), Err(_)) => {
+                let _ = fs::rename(previous, target);
+            }
+            (None, Ok(())) => {}
+            (None, Err(_)) => {
+                let _ = fs::remove_file(target);
+            }
+        }
+    }
+    if result.is_err() {
+        for name in names {
+            let _ = fs::remove_file(dir.join(format!(".{name}.partial")));
+        }
+    }
+    result.map_err(|error| format!("Unable to install dictionary: {error}"))
+}
+
+/// Installs the hunspell dictionaries in a LibreOffice extension (`.oxt`)
+/// or a zip of `.aff`/`.dic` files into `dir`, along with any thesaurus and
+/// hyphenation files the extension lists. Each dictionary is named after
+/// its `.aff` file, replacing one already installed under that code; an
+/// extra file that would replace anything but an earlier install of the
+/// same package fails the install. `packages` is the record of what is
+/// installed already. Files are extracted next to where they go and only
+/// moved into place once all of them are, so a package that fails part way
+/// leaves the directory as it was.
+pub fn install_package(
+    package: &Path,
+    dir: &Path,
+    packages: &[InstalledPackage],
+) -> Result<InstalledPackage, String> {
+    let file = fs::File::open(package)
+        .map_err(|error| format!("Unable to open dictionary package: {error}"))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|error| format!("Unable to read dictionary package: {error}"))?;
+
+    let contents = package_contents(&mut archive)?;
+    if contents.spelling.is_empty() {
+        return Err("The package has no hunspell spelling dictionary".to_string());
+    }
+
+    // Entries to take from the package, each with the name it's installed as.
+    let mut files: Vec<(&str, String)> = Vec::new();
+    let mut installed = InstalledPackage::default();
+    for spelling in &contents.spelling {
+        files.push((&spelling.aff, format!("{}.aff", spelling.code)));
+        files.push((&spelling.dic, format!("{}.dic", spelling.code)));
+        installed.codes.push(spelling.code.clone());
+    }
+    for extra in &contents.extras {
+        let Some(name) = file_name(extra) else {
+            continue;
+        };
+        if let Some(conflict) = extra_conflict(dir, name, &installed, packages) {
+            return Err(conflict);
+        }
+        files.push((extra, name.to_string()));
+        installed.extra_files.push(name.to_string());
+    }
+
+    let names: Vec<String> = files.iter().map(|(_, name)| name.clone()).collect();
+    for (index, (entry, name)) in files.iter().enumerate() {
+        let partial = dir.join(format!(".{name}.partial"));
+        if let Err(error) = extract_entry(&mut archive, entry, &partial) {
+            for name in &names[..=index] {
+                let _ = fs::remove_file(dir.join(format!(".{name}.partial")));
+            }
+            return Err(error);
+        }
+    }
+    move_into_place(dir, &names)?;
+    Ok(installed)
+}
+
+/// Drops packages with no dictionaries left from `packages`, removing their
+/// extra files from `dir` unless a remaining package installed them too.
+fn remove_emptied(dir: &Path, packages: &mut Vec<InstalledPackage>) {
+    let (emptied, kept): (Vec<InstalledPackage>, Vec<InstalledPackage>) = packages
+        .drain(..)
+        .partition(|package| package.codes.is_empty());
+    for name in emptied.iter().flat_map(|package| &package.extra_files) {
+        if !kept
+            .iter()
+            .any(|package| package.extra_files.contains(name))
+        {
+            let _ = fs::remove_file(dir.join(name));
+        }
+    }
+    *packages = kept;
+}
+
+/// Adds a newly installed package to `packages`. Dictionaries it replaced
+/// no longer belong to the packages they came from.
+pub fn record_install(
+    dir: &Path,
+    packages: &mut Vec<InstalledPackage>,
+    installed: InstalledPackage,
+) {
+    for package in packages.iter_mut() {
+        package.codes.retain(|code| !installed.codes.contains(code));
+    }
+    packages.push(installed);
+    remove_emptied(dir, packages);
+}
+
+/// Removes dictionary `code` from `dir`, and with it the thesaurus and
+/// hyphenation files of its package once none of the package's dictionaries
+/// are left. Dictionaries copied in by hand have no package and only lose
+/// their `.aff`/`.dic` pair.
+pub fn uninstall(
+    dir: &Path,
+    code: &str,
+    packages: &mut Vec<InstalledPackage>,
+) -> Result<(), String> {
+    let aff = file_name(code).map(|code| dir.join(format!("{code}.aff")));
+    let dic = file_name(code).map(|code| dir.join(format!("{code}.dic")));
+    let (Some(aff), Some(dic)) = (aff, dic) else {
+        return Err(format!("'{code}' is not a dictionary code"));
+    };
+    if !aff.exists() && !dic.exists() {
+        return Err(format!(
+            "Dictionary '{code}' is not installed in {}",
+            dir.display()
+        ));
+    }
+    for path in [aff, dic] {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(format!("Unable to remove dictionary: {error}")),
+        }
+    }
+
+    for package in packages.iter_mut() {
+        package.codes.retain(|existing| existing != code);
+    }
+    remove_emptied(dir, packages);
+    Ok(())
+}
+
+/// Reads the record of installed packages. A missing or unreadable record
+/// is empty, which only means removals keep the extra files.
+pub fn read_records(path: &Path) -> Vec<InstalledPackage> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_records(path: &Path, packages: &[InstalledPackage]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(packages)
+        .map_err(|error| format!("Unable to save installed dictionaries: {error}"))?;
+    fs::write(path, json).map_err(|error| format!("Unable to save installed dictionaries: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="http://openoffice.org/2001/manifest">
+  <manifest:file-entry manifest:media-type="application/vnd.sun.star.configuration-data"
+                       manifest:full-path="dictionaries.xcu"/>
+  <manifest:file-entry manifest:media-type="application/vnd.sun.star.package-bundle-description"
+                       manifest:full-path="description.xml"/>
+</manifest:manifest>"#;
+
+    const DICTIONARIES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<oor:component-data xmlns:oor="http://openoffice.org/2001/registry"
+    xmlns:xs="http://www.w3.org/2001/XMLSchema" oor:name="Linguistic" oor:package="org.openoffice.Office">
+  <node oor:name="ServiceManager">
+    <node oor:name="Dictionaries">
+      <node oor:name="HunSpellDic_en_US" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value>%origin%/dicts/en_US.dic %origin%/dicts/en_US.aff</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_SPELL</value></prop>
+        <prop oor:name="Locales" oor:type="oor:string-list"><value>en-US</value></prop>
+      </node>
+      <node oor:name="HunSpellDic_en_GB" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value oor:separator=";">%origin%/dicts/en_GB.aff;%origin%/dicts/en_GB.dic</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_SPELL</value></prop>
+      </node>
+      <node oor:name="HyphDic_en_US" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value>%origin%/hyph/../hyph_en_US.dic</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_HYPH</value></prop>
+      </node>
+      <node oor:name="ThesDic_en_US" oor:op="fuse">
+        <prop oor:name="Locations" oor:type="oor:string-list">
+          <value>%origin%/th_en_US.dat %origin%/th_en_US.idx</value>
+        </prop>
+        <prop oor:name="Format" oor:type="xs:string"><value>DICT_THES</value></prop>
+      </node>
+    </node>
+  </node>
+</oor:component-data>"#;
+
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn installs_an_extension_from_its_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = dir.path().join("en.oxt");
+        fs::write(
+            &package,
+            zip(&[
+                (MANIFEST_PATH, MANIFEST),
+                ("dictionaries.xcu", DICTIONARIES),
+                ("dicts/en_US.aff", "SET UTF-8\n"),
+                ("dicts/en_US.dic", "1\ncolor\n"),
+                ("dicts/en_GB.aff", "SET UTF-8\n"),
+                ("dicts/en_GB.dic", "1\ncolour\n"),
+                ("hyph_en_US.dic", "UTF-8\n"),
+                ("th_en_US.dat", "UTF-8\n"),
+                ("th_en_US.idx", "UTF-8\n"),
+                // Not listed anywhere, so left out.
+                ("dicts/fr_FR.aff", "SET UTF-8\n"),
+                ("dicts/fr_FR.dic", "1\nmot\n"),
+            ]),
+        )
+        .unwrap();
+        let installed_dir = dir.path().join("hunspell");
+        fs::create_dir_all(&installed_dir).unwrap();
+
+        let installed = install_package(&package, &installed_dir, &[]).unwrap();
+        assert_eq!(installed.codes, ["en_US", "en_GB"]);
+        assert_eq!(
+            installed.extra_files,
+            ["hyph_en_US.dic", "th_en_US.dat", "th_en_US.idx"]
+        );
+        assert_eq!(
+            file_names(&installed_dir),
+            [
+                "en_GB.aff",
+                "en_GB.dic",
+                "en_US.aff",
+                "en_US.dic",
+                "hyph_en_US.dic",
+                "th_en_US.dat",
+                "th_en_US.idx",
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(installed_dir.join("en_GB.dic")).unwrap(),
+            "1\ncolour\n"
+        );
+        let mut packages = Vec::new();
+        record_install(&installed_dir, &mut packages, installed);
+
+        // Reinstalling replaces the package's own files, but nothing else's.
+        assert!(install_package(&package, &installed_dir, &packages).is_ok());
+        let other = dir.path().join("other.oxt");
+        let clashing = DICTIONARIES
+            .replace("en_US", "en_CA")
+            .replace("hyph_en_CA", "hyph_en_US")
+            .replace("en_GB", "en_AU");
+        fs::write(
+            &other,
+            zip(&[
+                (MANIFEST_PATH, MANIFEST),
+                ("dictionaries.xcu", &clashing),
+                ("dicts/en_CA.aff", "SET UTF-8\n"),
+                ("dicts/en_CA.dic", "1\ncolour\n"),
+                ("dicts/en_AU.aff", "SET UTF-8\n"),
+                ("dicts/en_AU.dic", "1\ncolour\n"),
+                ("hyph_en_US.dic", "UTF-8\n"),
+                ("th_en_CA.dat", "UTF-8\n"),
+                ("th_en_CA.idx", "UTF-8\n"),
+            ]),
+        )
+        .unwrap();
+        let error = install_package(&other, &installed_dir, &packages).unwrap_err();
+        assert!(error.contains("another installed package"));
+        assert_eq!(file_names(&installed_dir).len(), 7);
+
+        // The extra files stay until the package's last dictionary goes.
+        uninstall(&installed_dir, "en_US", &mut packages).unwrap();
+        assert!(installed_dir.join("th_en_US.dat").exists());
+        assert!(uninstall(&installed_dir, "en_US", &mut packages).is_err());
+        uninstall(&installed_dir, "en_GB", &mut packages).unwrap();
+        assert!(file_names(&installed_dir).is_empty());
+        assert!(packages.is_empty());
+    }
+
+    #[test]
+    fn keeps_locations_inside_the_package() {
+        assert_eq!(
+            resolve_location("dicts", "%origin%/../en.aff"),
+            Some("en.aff".to_string())
+        );
+        assert_eq!(resolve_location("", "%origin%/../../etc/passwd"), None);
+        assert_eq!(
+            resolve_location("", "vnd.sun.star.expand:$HOME/en.aff"),
+            None
+        );
+        assert_eq!(file_name("dicts/.hidden.dic"), None);
+        assert_eq!(file_name("dicts/"), None);
+        assert_eq!(file_name("dicts/en_US.dic"), Some("en_US.dic"));
+    }
+}
@@ -0,0 +1,377 @@
+//! Word, sentence and readability counts, computed chunk by chunk so that a
+//! document of hundreds of megabytes never has to be scanned in the webview
+//! or held whole in memory.
+
+use crate::markdown;
+use crate::text_encoding::{self, StreamDecoder};
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Average silent reading speed for prose, in words per minute.
+const READING_WORDS_PER_MINUTE: f64 = 230.0;
+
+/// A line longer than this is counted in pieces split at whitespace, or
+/// anywhere if it has none, so a file without line breaks is never buffered
+/// whole.
+const MAX_PENDING_LINE_BYTES: usize = 64 * 1024;
+
+const FILE_CHUNK_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStats {
+    pub words: u64,
+    /// Every character but line breaks.
+    pub characters: u64,
+    pub characters_excluding_spaces: u64,
+    pub sentences: u64,
+    /// Runs of non-blank lines between blank lines or headings.
+    pub paragraphs: u64,
+    pub lines: u64,
+    pub reading_time_seconds: u64,
+    /// Flesch reading ease: 60 to 70 is plain English, lower is harder.
+    /// `None` for a document without words.
+    pub readability: Option<f64>,
+    /// Number of ATX headings at each level, `#` to `######`.
+    pub headings: [u64; 6],
+}
+
+/// Accumulates [`DocumentStats`] over text pushed in arbitrary chunks. A
+/// chunk may end anywhere, even mid-word, as long as it is valid UTF-8.
+#[derive(Default)]
+pub struct StatsCounter {
+    stats: DocumentStats,
+    syllables: u64,
+    /// The current line so far, or its tail after the last whitespace once
+    /// the line has grown long.
+    pending: String,
+    /// Part of the current line has already been counted.
+    line_started: bool,
+    line_heading: Option<usize>,
+    line_has_content: bool,
+    in_paragraph: bool,
+    /// Words were seen since the last sentence ended.
+    sentence_open: bool,
+}
+
+fn is_sentence_end(character: char) -> bool {
+    matches!(
+        character,
+        '.' | '!' | '?' | '…' | '‼' | '⁇' | '⁈' | '⁉' | '。' | '！' | '？'
+    )
+}
+
+/// Estimates syllables the way Flesch scores usually are for English: one
+/// per group of vowels, less a silent final `e`, and at least one a word.
+fn syllables(word: &str) -> u64 {
+    let word = word.to_lowercase();
+    let mut count = 0;
+    let mut previous_was_vowel = false;
+    for character in word.chars() {
+        let is_vowel = matches!(
+            character,
+            'a' | 'e' | 'i' | 'o' | 'u' | 'y'
+                | 'à'..='å' | 'è'..='ë' | 'ì'..='ï' | 'ò'..='ö' | 'ù'..='ü' | 'ý' | 'ÿ'
+        );
+        if is_vowel && !previous_was_vowel {
+            count += 1;
+        }
+        previous_was_vowel = is_vowel;
+    }
+    if count > 1 && word.ends_with('e') && !word.ends_with("le") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+impl StatsCounter {
+    pub fn push(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(index) = rest.find('\n') {
+            self.pending.push_str(&rest[..index]);
+            let line = std::mem::take(&mut self.pending);
+            self.count_fragment(&line);
+            self.end_line();
+            rest = &rest[index + 1..];
+        }
+        self.pending.push_str(rest);
+
+        // The word after the last whitespace may go on in the next chunk.
+        // Without whitespace close enough to the end, the line is split
+        // where the text ends, at the cost of counting the word there twice.
+        if self.pending.len() > MAX_PENDING_LINE_BYTES {
+            let split = self
+                .pending
+                .rfind(char::is_whitespace)
+                .filter(|split| self.pending.len() - split <= MAX_PENDING_LINE_BYTES)
+                .unwrap_or(self.pending.len());
+            let tail = self.pending.split_off(split);
+            let head = std::mem::replace(&mut self.pending, tail);
+            self.count_fragment(&head);
+        }
+    }
+
+    fn count_fragment(&mut self, fragment: &str) {
+        if !self.line_started {
+            self.line_heading = markdown::heading_prefix(fragment).map(|(level, _)| level);
+            self.line_started = true;
+        }
+
+        for piece in fragment.split_word_bounds() {
+            let characters = piece.chars().filter(|character| *character != '\r').count() as u64;
+            self.stats.characters += characters;
+            if piece.chars().all(char::is_whitespace) {
+                continue;
+            }
+            self.stats.characters_excluding_spaces += characters;
+            self.line_has_content = true;
+
+            if piece.chars().any(char::is_alphanumeric) {
+                self.stats.words += 1;
+                self.syllables += syllables(piece);
+                self.sentence_open = true;
+            } else if piece.chars().any(is_sentence_end) {
+                self.end_sentence();
+            }
+        }
+    }
+
+    fn end_sentence(&mut self) {
+        if self.sentence_open {
+            self.stats.sentences += 1;
+            self.sentence_open = false;
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.stats.lines += 1;
+        // A heading or blank line also ends a sentence left without a full
+        // stop, such as the heading's own text.
+        if let Some(level) = self.line_heading {
+            self.stats.headings[level - 1] += 1;
+            self.in_paragraph = false;
+            self.end_sentence();
+        } else if !self.line_has_content {
+            self.in_paragraph = false;
+            self.end_sentence();
+        } else if !self.in_paragraph {
+            self.stats.paragraphs += 1;
+            self.in_paragraph = true;
+        }
+        self.line_started = false;
+        self.line_heading = None;
+        self.line_has_content = false;
+    }
+
+    pub fn finish(mut self) -> DocumentStats {
+        let line = std::mem::take(&mut self.pending);
+        self.count_fragment(&line);
+        self.end_line();
+        self.end_sentence();
+
+        let words = self.stats.words as f64;
+        self.stats.reading_time_seconds = (words * 60.0 / READING_WORDS_PER_MINUTE).ceil() as u64;
+        self.stats.readability = (self.stats.words > 0).then(|| {
+            let sentences = self.stats.sentences.max(1) as f64;
+            206.835 - 1.015 * (words / sentences) - 84.6 * (self.syllables as f64 / words)
+        });
+        self.stats
+    }
+}
+
+/// Counts what `reader` holds, decoded the way the open stream would decode
+/// it, `chunk_bytes` at a time.
+fn count_reader(
+    reader: impl Read,
+    forced_encoding: Option<&'static Encoding>,
+    chunk_bytes: usize,
+) -> Result<DocumentStats, String> {
+    let mut document = text_encoding::sample_document(reader, forced_encoding)
+        .map_err(|error| format!("Unable to read file: {error}"))?;
+
+    let mut decoder = StreamDecoder::new(document.encoding);
+    let mut counter = StatsCounter::default();
+    let mut buffer = vec![0; chunk_bytes];
+    loop {
+        let read = document
+            .reader
+            .read(&mut buffer)
+            .map_err(|error| format!("Unable to read file: {error}"))?;
+        counter.push(&decoder.decode(&buffer[..read], read == 0)?);
+        if read == 0 {
+            return Ok(counter.finish());
+        }
+    }
+}
+
+/// Counts the file at `path` in `encoding`, or the one it is detected to be in.
+pub fn stats_for_file(
+    path: &Path,
+    forced_encoding: Option<&'static Encoding>,
+) -> Result<DocumentStats, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("Unable to open file '{}': {error}", path.display()))?;
+    count_reader(file, forced_encoding, FILE_CHUNK_BYTES)
+}
+
+/// Counters for text streamed from the editor, for documents with unsaved
+/// changes.
+#[derive(Default)]
+pub struct DocumentStatsState {
+    stream_counter: Mutex<u64>,
+    active_streams: Mutex<HashMap<String, StatsCounter>>,
+}
+
+// The stream commands are async so that counting runs off the main thread.
+
+#[tauri::command]
+pub async fn start_document_stats_stream(
+    state: tauri::State<'_, DocumentStatsState>,
+) -> Result<String, String> {
+    let stream_id = {
+        let mut counter = state
+            .stream_counter
+            .lock()
+            .map_err(|error| format!("Unable to allocate stats stream id: {error}"))?;
+        *counter += 1;
+        format!("stats-{}", *counter)
+    };
+    state
+        .active_streams
+        .lock()
+        .map_err(|error| format!("Unable to store stats stream state: {error}"))?
+        .insert(stream_id.clone(), StatsCounter::default());
+    Ok(stream_id)
+}
+
+#[tauri::command]
+pub async fn write_document_stats_chunk(
+    state: tauri::State<'_, DocumentStatsState>,
+    stream_id: String,
+    text_chunk: String,
+) -> Result<(), String> {
+    let mut streams = state
+        .active_streams
+        .lock()
+        .map_err(|error| format!("Unable to read stats stream state: {error}"))?;
+    streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| format!("Stats stream '{}' not found", stream_id))?
+        .push(&text_chunk);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn finish_document_stats_stream(
+    state: tauri::State<'_, DocumentStatsState>,
+    stream_id: String,
+) -> Result<DocumentStats, String> {
+    let counter = state
+        .active_streams
+        .lock()
+        .map_err(|error| format!("Unable to read stats stream state: {error}"))?
+        .remove(&stream_id)
+        .ok_or_else(|| format!("Stats stream '{}' not found", stream_id))?;
+    Ok(counter.finish())
+}
+
+#[tauri::command]
+pub async fn cancel_document_stats_stream(
+    state: tauri::State<'_, DocumentStatsState>,
+    stream_id: String,
+) -> Result<(), String> {
+    state
+        .active_streams
+        .lock()
+        .map_err(|error| format!("Unable to read stats stream state: {error}"))?
+        .remove(&stream_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(text: &str) -> DocumentStats {
+        let mut counter = StatsCounter::default();
+        counter.push(text);
+        counter.finish()
+    }
+
+    #[test]
+    fn counts_words_sentences_paragraphs_and_headings() {
+        let text = "# Title\n\nThe cat sat. It purred!\nThen slept\n\n## Part\nDone?\n";
+        let stats = stats(text);
+        assert_eq!(stats.words, 10);
+        assert_eq!(stats.sentences, 6);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.lines, 8);
+        assert_eq!(stats.headings, [1, 1, 0, 0, 0, 0]);
+        assert_eq!(stats.characters, text.chars().count() as u64 - 7);
+        assert_eq!(stats.reading_time_seconds, 3);
+        assert!(stats.readability.is_some_and(|score| score > 80.0));
+    }
+
+    #[test]
+    fn segments_words_by_unicode_rules() {
+        assert_eq!(stats("naïve café, don't 3.14").words, 4);
+        // Ideographs have no spaces between words and count one by one.
+        assert_eq!(stats("我喜欢猫").words, 4);
+        assert_eq!(stats("  \n\t").words, 0);
+        assert_eq!(stats("").readability, None);
+        assert_eq!(stats("").lines, 1);
+    }
+
+    #[test]
+    fn chunk_boundaries_do_not_change_the_counts() {
+        let text = "# Über\r\n\r\nZwölf Boxkämpfer jagen Viktor quer über den Sylter Deich.\r\n"
+            .repeat(50)
+            + &"long ".repeat(30_000);
+        let whole = stats(&text);
+        for size in [1, 7, 4096] {
+            let mut counter = StatsCounter::default();
+            let mut start = 0;
+            while start < text.len() {
+                let mut end = (start + size).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end += 1;
+                }
+                counter.push(&text[start..end]);
+                start = end;
+            }
+            assert_eq!(counter.finish(), whole, "chunks of {size}");
+        }
+        assert_eq!(whole.words, 50 * 10 + 30_000);
+        assert_eq!(whole.headings[0], 50);
+
+        // A line with no whitespace is split rather than held whole.
+        let mut counter = StatsCounter::default();
+        for _ in 0..100 {
+            counter.push(&"é".repeat(1000));
+            assert!(counter.pending.len() <= MAX_PENDING_LINE_BYTES);
+        }
+        assert_eq!(counter.finish().characters, 100_000);
+    }
+
+    #[test]
+    fn reads_files_in_any_encoding_across_chunk_boundaries() {
+        let text = "Ça marche très bien. Déjà vu!\n";
+        let utf8 = [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat();
+        let expected = stats(text);
+        // Three-byte chunks split the accented letters, which the UTF-8
+        // decoder has to carry over to the next chunk.
+        assert_eq!(count_reader(utf8.as_slice(), None, 3), Ok(expected.clone()));
+
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        let forced = Some(encoding_rs::WINDOWS_1252);
+        assert_eq!(count_reader(&latin1[..], forced, 5), Ok(expected));
+
+        assert!(count_reader(&b"ok \xFF"[..], Some(encoding_rs::UTF_8), 2).is_err());
+    }
+}
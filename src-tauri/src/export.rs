@@ -0,0 +1,173 @@
+use crate::markdown;
+use serde::Deserialize;
+
+/// How the editor looked when the document was exported: the font settings
+/// and theme the Formatted view was drawn with.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStyle {
+    pub font_family: String,
+    pub font_size: f64,
+    pub font_style: String,
+    pub font_weight: i32,
+    pub theme_mode: String,
+}
+
+impl ExportStyle {
+    fn is_dark(&self) -> bool {
+        self.theme_mode == "dark"
+    }
+
+    /// The body font size in CSS pixels, falling back to the editor's default
+    /// for a setting that makes no sense.
+    pub fn font_size_px(&self) -> f64 {
+        if self.font_size.is_finite() && self.font_size > 0.0 {
+            self.font_size
+        } else {
+            14.0
+        }
+    }
+
+    /// Text and background colours, as the editor's style extension sets them.
+    pub fn colors(&self) -> (&'static str, &'static str) {
+        if self.is_dark() {
+            ("#d7dfef", "#111925")
+        } else {
+            ("#1c2736", "#ffffff")
+        }
+    }
+}
+
+/// Line height of body text in the editor, as a multiple of the font size.
+pub const LINE_HEIGHT: f64 = 1.55;
+
+/// Font size and line height of a heading level relative to body text, as
+/// the Formatted view's `.cm-fmt-hN` set them.
+pub fn heading_metrics(level: usize) -> (f64, f64) {
+    match level {
+        1 => (1.9, 1.3),
+        2 => (1.6, 1.3),
+        3 => (1.35, 1.35),
+        4 => (1.2, LINE_HEIGHT),
+        5 => (1.1, LINE_HEIGHT),
+        _ => (1.0, LINE_HEIGHT),
+    }
+}
+
+/// Heading sizes and line heights of the Formatted view's `.cm-fmt-hN`.
+const HEADING_CSS: &str = "\
+h1, h2, h3, h4, h5, h6 { margin: 0; font-weight: 700; }
+h1 { font-size: 1.9em; line-height: 1.3; }
+h2 { font-size: 1.6em; line-height: 1.3; }
+h3 { font-size: 1.35em; line-height: 1.35; }
+h4 { font-size: 1.2em; }
+h5 { font-size: 1.1em; }
+h6 { font-size: 1em; opacity: 0.85; }";
+
+/// Keeps a setting from closing the declaration, rule or `<style>` element
+/// it is written into.
+fn css_value(value: &str) -> String {
+    value
+        .chars()
+        .filter(|character| !matches!(character, ';' | '{' | '}' | '<' | '>' | '\\'))
+        .collect()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn stylesheet(style: &ExportStyle) -> String {
+    let (color, background) = style.colors();
+    let font_size = style.font_size_px();
+    format!(
+        "body {{
+  margin: 0;
+  padding: 12px 14px;
+  color: {color};
+  background-color: {background};
+  font-family: {};
+  font-size: {font_size}px;
+  font-style: {};
+  font-weight: {};
+  line-height: 1.55;
+  overflow-wrap: break-word;
+}}
+p {{ margin: 0 0 1.55em; }}
+strong {{ font-weight: 700; }}
+em {{ font-style: italic; }}
+{HEADING_CSS}",
+        css_value(&style.font_family),
+        css_value(&style.font_style),
+        style.font_weight.clamp(1, 1000),
+    )
+}
+
+/// A standalone HTML page for `text`, rendered as the Formatted view shows
+/// it, with the stylesheet embedded so the file can be shared on its own.
+pub fn html_document(text: &str, title: &str, style: &ExportStyle) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<meta name=\"generator\" content=\"wisty {}\">
+<title>{}</title>
+<style>
+{}
+</style>
+</head>
+<body>
+{}</body>
+</html>
+",
+        env!("CARGO_PKG_VERSION"),
+        escape_text(title),
+        stylesheet(style),
+        markdown::to_html(text),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(theme_mode: &str) -> ExportStyle {
+        ExportStyle {
+            font_family: "\"Noto Serif\", serif".to_string(),
+            font_size: 16.0,
+            font_style: "normal".to_string(),
+            font_weight: 400,
+            theme_mode: theme_mode.to_string(),
+        }
+    }
+
+    #[test]
+    fn embeds_the_font_and_theme() {
+        let html = html_document("# Notes\n**done**", "notes", &style("dark"));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>notes</title>"));
+        assert!(html.contains("font-family: \"Noto Serif\", serif;"));
+        assert!(html.contains("font-size: 16px;"));
+        assert!(html.contains("background-color: #111925;"));
+        assert!(html.contains("<h1>Notes</h1>\n<p><strong>done</strong></p>\n</body>"));
+
+        let light = html_document("", "", &style("light"));
+        assert!(light.contains("background-color: #ffffff;"));
+    }
+
+    #[test]
+    fn settings_cannot_escape_the_stylesheet() {
+        let mut style = style("light");
+        style.font_family = "x; } </style><script>alert(1)</script>".to_string();
+        style.font_size = f64::NAN;
+        let html = html_document("", "<b>", &style);
+        assert!(!html.contains("<script>"));
+        assert_eq!(html.matches("</style>").count(), 1);
+        assert!(html.contains("font-size: 14px;"));
+        assert!(html.contains("<title>&lt;b&gt;</title>"));
+    }
+}
@@ -0,0 +1,252 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Symlink chains longer than this are treated as loops, as the kernel does.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Follows symlinks from `path` to the file a save should replace, so the
+/// link itself survives. Works for dangling links too: saving through one
+/// creates the file it points at.
+pub fn resolve_save_target(path: &Path) -> Result<PathBuf, String> {
+    let mut current = path.to_path_buf();
+    for _ in 0..MAX_SYMLINK_HOPS {
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {}
+            Ok(_) => return Ok(current),
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(current),
+            Err(error) => {
+                return Err(format!(
+                    "Unable to inspect '{}': {error}",
+                    current.to_string_lossy()
+                ))
+            }
+        }
+
+        let link = fs::read_link(&current).map_err(|error| {
+            format!(
+                "Unable to read symlink '{}': {error}",
+                current.to_string_lossy()
+            )
+        })?;
+        current = match current.parent() {
+            Some(parent) if link.is_relative() => parent.join(link),
+            _ => link,
+        };
+    }
+    Err(format!(
+        "Too many levels of symbolic links in '{}'",
+        path.to_string_lossy()
+    ))
+}
+
+/// True when `target` has other hard links. Renaming a new file over it
+/// would leave those names pointing at the old contents, so the save has to
+/// overwrite the existing inode instead.
+pub fn has_other_links(target: &Path) -> Result<bool, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        match fs::metadata(target) {
+            Ok(metadata) => Ok(metadata.is_file() && metadata.nlink() > 1),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(format!(
+                "Unable to read metadata for '{}': {error}",
+                target.to_string_lossy()
+            )),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = target;
+        Ok(false)
+    }
+}
+
+/// Gives the temp file `temp_path` the owner, mode and extended attributes
+/// of `target` before it is renamed over it. POSIX ACLs and SELinux labels
+/// are extended attributes (`system.posix_acl_*`, `security.selinux`) and
+/// are carried with the rest.
+///
+/// Only the mode is required: changing the owner needs privileges wisty
+/// usually lacks, and some attributes can't be set by unprivileged users or
+/// on every file system, so those failures are logged and the save goes on.
+pub fn copy_metadata(target: &Path, temp_path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let metadata = match fs::metadata(target) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => {
+                return Err(format!(
+                    "Unable to read permissions for '{}': {error}",
+                    target.to_string_lossy()
+                ))
+            }
+        };
+
+        // Owner first: chown clears set-id bits, which the chmod restores.
+        copy_owner(temp_path, metadata.uid(), metadata.gid());
+
+        fs::set_permissions(temp_path, fs::Permissions::from_mode(metadata.mode())).map_err(
+            |error| {
+                format!(
+                    "Unable to copy permissions to temporary save file '{}': {error}",
+                    temp_path.to_string_lossy()
+                )
+            },
+        )?;
+
+        // After the mode: setting an access ACL also rewrites the group bits.
+        copy_xattrs(target, temp_path);
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (target, temp_path);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_owner(temp_path: &Path, uid: u32, gid: u32) {
+    use std::os::unix::fs::{chown, MetadataExt};
+
+    let Ok(current) = fs::metadata(temp_path) else {
+        return;
+    };
+    if current.uid() == uid && current.gid() == gid {
+        return;
+    }
+    // Without privileges the owner can't change, but the group can if the
+    // user belongs to it.
+    if chown(temp_path, Some(uid), Some(gid)).is_ok() {
+        return;
+    }
+    if let Err(error) = chown(temp_path, None, Some(gid)) {
+        log::warn!(
+            target: "wisty::file_metadata",
+            "Unable to keep owner {uid}:{gid} for '{}': {error}",
+            temp_path.to_string_lossy()
+        );
+    }
+}
+
+#[cfg(unix)]
+fn copy_xattrs(target: &Path, temp_path: &Path) {
+    let names = match xattr::list(target) {
+        Ok(names) => names,
+        Err(error) => {
+            // File systems without xattr support have nothing to copy.
+            if error.kind() != ErrorKind::Unsupported {
+                log::warn!(
+                    target: "wisty::file_metadata",
+                    "Unable to list extended attributes of '{}': {error}",
+                    target.to_string_lossy()
+                );
+            }
+            return;
+        }
+    };
+
+    for name in names {
+        let result = xattr::get(target, &name).and_then(|value| match value {
+            Some(value) => xattr::set(temp_path, &name, &value),
+            None => Ok(()),
+        });
+        if let Err(error) = result {
+            log::warn!(
+                target: "wisty::file_metadata",
+                "Unable to keep extended attribute '{}' for '{}': {error}",
+                name.to_string_lossy(),
+                target.to_string_lossy()
+            );
+        }
+    }
+}
+
+/// Copies the finished temp file into `target` without replacing its inode,
+/// then removes the temp file. Used for hard-linked files; unlike the rename
+/// this isn't atomic, which is why it is only the fallback. If the copy
+/// fails the temp file is kept, as it may be the only complete copy of the
+/// text, and the error names it. The target takes the temp file's mtime so
+/// the file watcher's fingerprint of it holds.
+pub fn write_in_place(temp_path: &Path, target: &Path) -> Result<(), String> {
+    let result = (|| -> std::io::Result<()> {
+        let mut source = File::open(temp_path)?;
+        let modified = source.metadata()?.modified()?;
+        let mut destination = OpenOptions::new().write(true).open(target)?;
+        let written = std::io::copy(&mut source, &mut destination)?;
+        destination.set_len(written)?;
+        destination.set_modified(modified)?;
+        destination.sync_all()
+    })();
+
+    match result {
+        Ok(()) => {
+            let _ = fs::remove_file(temp_path);
+            Ok(())
+        }
+        Err(error) => Err(format!(
+            "Unable to write '{}' in place: {error}. The saved text is in '{}'.",
+            target.to_string_lossy(),
+            temp_path.to_string_lossy()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_symlink_chains_including_dangling_ones() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let real = dir.join("real.txt");
+        fs::write(&real, "text").unwrap();
+        symlink("real.txt", dir.join("first")).unwrap();
+        symlink(dir.join("first"), dir.join("second")).unwrap();
+        symlink("missing.txt", dir.join("dangling")).unwrap();
+        symlink("loop", dir.join("loop")).unwrap();
+
+        assert_eq!(resolve_save_target(&dir.join("second")).unwrap(), real);
+        assert_eq!(
+            resolve_save_target(&dir.join("dangling")).unwrap(),
+            dir.join("missing.txt")
+        );
+        assert_eq!(resolve_save_target(&real).unwrap(), real);
+        assert!(resolve_save_target(&dir.join("loop")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn in_place_write_keeps_hard_links_together() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let target = dir.join("notes.txt");
+        let other = dir.join("other-name.txt");
+        let temp = dir.join("temp");
+        fs::write(&target, "a longer original text").unwrap();
+        fs::hard_link(&target, &other).unwrap();
+        fs::write(&temp, "short").unwrap();
+
+        assert!(has_other_links(&target).unwrap());
+        write_in_place(&temp, &target).unwrap();
+
+        assert_eq!(fs::read_to_string(&other).unwrap(), "short");
+        assert!(!temp.exists());
+        assert!(!has_other_links(&dir.join("missing")).unwrap());
+
+        // A failed copy leaves the only complete text where it was.
+        fs::write(&temp, "kept").unwrap();
+        let error = write_in_place(&temp, &dir.join("missing")).unwrap_err();
+        assert!(error.contains(&*temp.to_string_lossy()));
+        assert_eq!(fs::read_to_string(&temp).unwrap(), "kept");
+    }
+}
@@ -0,0 +1,579 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tauri::Emitter;
+
+/// Event emitted to the frontend when a tracked file changes on disk.
+pub const FILE_CHANGED_EVENT: &str = "file-changed-on-disk";
+
+/// What a file looked like when wisty last read or wrote it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileFingerprint {
+    modified: Option<SystemTime>,
+    size_bytes: u64,
+    content_hash: u64,
+}
+
+impl FileFingerprint {
+    pub fn new(metadata: &std::fs::Metadata, content_hash: u64) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            size_bytes: metadata.len(),
+            content_hash,
+        }
+    }
+
+    /// Cheap check against current metadata. A match means the file almost
+    /// certainly hasn't been touched; a mismatch still needs a content
+    /// comparison, since tools like `touch` change the mtime alone.
+    fn matches_metadata(&self, metadata: &std::fs::Metadata) -> bool {
+        self.size_bytes == metadata.len() && self.modified == metadata.modified().ok()
+    }
+}
+
+/// Incremental content hash, fed with the bytes as the open and save streams
+/// pass them through so no extra read of the file is needed. The hash is only
+/// ever compared within one process, so std's SipHash is sufficient.
+#[derive(Default)]
+pub struct ContentHasher(DefaultHasher);
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Self(DefaultHasher::new())
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = ContentHasher::new();
+    let mut buffer = vec![0_u8; 256 * 1024];
+    loop {
+        let read_count = file.read(&mut buffer)?;
+        if read_count == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.update(&buffer[..read_count]);
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileDiskStatus {
+    /// The file is not tracked, so nothing is known about it.
+    Untracked,
+    Unchanged,
+    Modified,
+    Missing,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum FileChangeKind {
+    Modified,
+    Removed,
+    Renamed,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FileChangedPayload {
+    path: String,
+    kind: FileChangeKind,
+    new_path: Option<String>,
+}
+
+/// The windows showing a tracked file, by label, each with the path as that
+/// window spells it, which is what its change events carry.
+type Subscribers = HashMap<String, String>;
+
+/// The one watcher shared by every tracked file, with the files it watches
+/// for and how many of them are in each directory it watches.
+#[derive(Default)]
+struct Watching {
+    watcher: Option<RecommendedWatcher>,
+    files: HashSet<PathBuf>,
+    directories: HashMap<PathBuf, usize>,
+}
+
+/// Tracks files open in the editor and watches their parent directories, so
+/// editors that save by renaming a temp file over the original (as wisty
+/// itself does) are noticed too; a watch on the file's inode would be lost.
+/// A file open in several windows is watched until the last one lets go.
+/// One watcher serves every file, so opening many files doesn't use up the
+/// user's inotify instances; its events are routed to files by path.
+#[derive(Default)]
+pub struct FileWatchState {
+    // Kept apart from `watching` so the notify callback thread never waits
+    // on a lock held while a directory is being watched or unwatched.
+    fingerprints: Arc<Mutex<HashMap<PathBuf, FileFingerprint>>>,
+    subscribers: Arc<Mutex<HashMap<PathBuf, Subscribers>>>,
+    watching: Mutex<Watching>,
+}
+
+/// Normalizes `path` for use as a tracking key. Files that don't exist yet
+/// (a first save) are keyed by their canonical parent plus file name.
+pub fn tracking_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => std::fs::canonicalize(parent)
+            .map(|parent| parent.join(file_name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl FileWatchState {
+    /// Records `fingerprint` for `path`, open in `window`, and starts
+    /// watching it if it isn't watched already. Watch failures are logged
+    /// rather than returned: the divergence check on save still works
+    /// without live events.
+    pub fn track(
+        &self,
+        app: &tauri::AppHandle,
+        window: &str,
+        path: &Path,
+        fingerprint: FileFingerprint,
+    ) {
+        let key = tracking_key(path);
+        if let Ok(mut fingerprints) = self.fingerprints.lock() {
+            fingerprints.insert(key.clone(), fingerprint);
+        }
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers
+                .entry(key.clone())
+                .or_default()
+                .insert(window.to_string(), path.to_string_lossy().to_string());
+        }
+
+        let Ok(mut watching) = self.watching.lock() else {
+            return;
+        };
+        if watching.files.contains(&key) {
+            return;
+        }
+        if let Err(error) = self.watch_directory(app, &mut watching, &key) {
+            log::warn!(
+                target: "wisty::file_watcher",
+                "Unable to watch '{}': {error}",
+                key.to_string_lossy()
+            );
+            return;
+        }
+        watching.files.insert(key);
+    }
+
+    /// Replaces the recorded fingerprint without touching the watcher. Used
+    /// by the save stream just before it renames over the tracked file.
+    pub fn update_fingerprint(&self, path: &Path, fingerprint: FileFingerprint) {
+        if let Ok(mut fingerprints) = self.fingerprints.lock() {
+            fingerprints.insert(tracking_key(path), fingerprint);
+        }
+    }
+
+    pub fn fingerprint(&self, path: &Path) -> Option<FileFingerprint> {
+        self.fingerprints
+            .lock()
+            .ok()
+            .and_then(|fingerprints| fingerprints.get(&tracking_key(path)).copied())
+    }
+
+    /// Drops the recorded fingerprint but keeps watching, so changes to the
+    /// file go unreported until it is tracked again.
+    pub fn forget_fingerprint(&self, path: &Path) {
+        if let Ok(mut fingerprints) = self.fingerprints.lock() {
+            fingerprints.remove(&tracking_key(path));
+        }
+    }
+
+    /// Stops reporting changes to `path` to `window`, and stops watching it
+    /// once no window has it open.
+    pub fn release(&self, window: &str, path: &Path) {
+        let key = tracking_key(path);
+        let unwatched = self.subscribers.lock().is_ok_and(|mut subscribers| {
+            let Some(windows) = subscribers.get_mut(&key) else {
+                return false;
+            };
+            windows.remove(window);
+            let empty = windows.is_empty();
+            if empty {
+                subscribers.remove(&key);
+            }
+            empty
+        });
+        if unwatched {
+            self.stop_watching(&[key]);
+        }
+    }
+
+    /// Releases every file open in a window that has closed.
+    pub fn release_window(&self, window: &str) {
+        let mut unwatched = Vec::new();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|key, windows| {
+                windows.remove(window);
+                if windows.is_empty() {
+                    unwatched.push(key.clone());
+                }
+                !windows.is_empty()
+            });
+        }
+        self.stop_watching(&unwatched);
+    }
+
+    fn stop_watching(&self, keys: &[PathBuf]) {
+        if let Ok(mut fingerprints) = self.fingerprints.lock() {
+            for key in keys {
+                fingerprints.remove(key);
+            }
+        }
+        let Ok(mut watching) = self.watching.lock() else {
+            return;
+        };
+        for key in keys {
+            if !watching.files.remove(key) {
+                continue;
+            }
+            let Some(parent) = key.parent() else {
+                continue;
+            };
+            let Some(count) = watching.directories.get_mut(parent) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 {
+                watching.directories.remove(parent);
+                if let Some(watcher) = watching.watcher.as_mut() {
+                    let _ = watcher.unwatch(parent);
+                }
+            }
+        }
+    }
+
+    /// Whether `path` is open in any window.
+    pub fn is_open(&self, path: &Path) -> bool {
+        let key = tracking_key(path);
+        self.subscribers
+            .lock()
+            .is_ok_and(|subscribers| subscribers.contains_key(&key))
+    }
+
+    /// Compares the file on disk with what was recorded when it was opened
+    /// or last saved.
+    pub fn disk_status(&self, path: &Path) -> Result<FileDiskStatus, String> {
+        let Some(fingerprint) = self.fingerprint(path) else {
+            return Ok(FileDiskStatus::Untracked);
+        };
+
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Ok(FileDiskStatus::Missing)
+            }
+            Err(error) => {
+                return Err(format!(
+                    "Unable to read metadata for '{}': {error}",
+                    path.to_string_lossy()
+                ))
+            }
+        };
+
+        let unchanged = content_unchanged(
+            &self.fingerprints,
+            &tracking_key(path),
+            path,
+            fingerprint,
+            &metadata,
+        )
+        .map_err(|error| {
+            format!(
+                "Unable to read '{}' to compare it with the open copy: {error}",
+                path.to_string_lossy()
+            )
+        })?;
+        Ok(if unchanged {
+            FileDiskStatus::Unchanged
+        } else {
+            FileDiskStatus::Modified
+        })
+    }
+
+    /// Watches the directory `key` is in, counting the files it is watched
+    /// for, and creates the shared watcher on first use.
+    fn watch_directory(
+        &self,
+        app: &tauri::AppHandle,
+        watching: &mut Watching,
+        key: &Path,
+    ) -> Result<(), String> {
+        let parent = key
+            .parent()
+            .ok_or_else(|| "File has no parent directory".to_string())?;
+        if let Some(count) = watching.directories.get_mut(parent) {
+            *count += 1;
+            return Ok(());
+        }
+
+        let watcher = match watching.watcher.as_mut() {
+            Some(watcher) => watcher,
+            None => watching.watcher.insert(self.start_watcher(app)?),
+        };
+        watcher
+            .watch(parent, RecursiveMode::NonRecursive)
+            .map_err(|error| error.to_string())?;
+        watching.directories.insert(parent.to_path_buf(), 1);
+        Ok(())
+    }
+
+    fn start_watcher(&self, app: &tauri::AppHandle) -> Result<RecommendedWatcher, String> {
+        let fingerprints = Arc::clone(&self.fingerprints);
+        let subscribers = Arc::clone(&self.subscribers);
+        let app = app.clone();
+
+        notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            // An event for a watched directory concerns whichever tracked
+            // files it names; a rename can name two.
+            let targets: Vec<PathBuf> = subscribers
+                .lock()
+                .map(|subscribers| {
+                    event
+                        .paths
+                        .iter()
+                        .filter(|path| subscribers.contains_key(path.as_path()))
+                        .cloned()
+                        .collect::<HashSet<PathBuf>>()
+                        .into_iter()
+                        .collect()
+                })
+                .unwrap_or_default();
+            for target in targets {
+                report_event(&app, &fingerprints, &subscribers, &target, &event);
+            }
+        })
+        .map_err(|error| error.to_string())
+    }
+}
+
+/// Whether the file at `path`, tracked as `key`, still has the content
+/// `fingerprint` recorded. Metadata settles most cases; an equal size with a
+/// new mtime, as after `touch`, needs the content hashed. Same content
+/// under a new mtime is remembered so the next check takes the cheap path.
+fn content_unchanged(
+    fingerprints: &Mutex<HashMap<PathBuf, FileFingerprint>>,
+    key: &Path,
+    path: &Path,
+    fingerprint: FileFingerprint,
+    metadata: &std::fs::Metadata,
+) -> std::io::Result<bool> {
+    if fingerprint.matches_metadata(metadata) {
+        return Ok(true);
+    }
+    if fingerprint.size_bytes != metadata.len() {
+        return Ok(false);
+    }
+    let content_hash = hash_file(path)?;
+    if content_hash != fingerprint.content_hash {
+        return Ok(false);
+    }
+    if let Ok(mut fingerprints) = fingerprints.lock() {
+        fingerprints.insert(
+            key.to_path_buf(),
+            FileFingerprint::new(metadata, content_hash),
+        );
+    }
+    Ok(true)
+}
+
+fn report_event(
+    app: &tauri::AppHandle,
+    fingerprints: &Mutex<HashMap<PathBuf, FileFingerprint>>,
+    subscribers: &Mutex<HashMap<PathBuf, Subscribers>>,
+    target: &Path,
+    event: &notify::Event,
+) {
+    let Some(payload) = classify_event(event, target) else {
+        return;
+    };
+
+    if payload.kind == FileChangeKind::Modified {
+        // Skip events that leave the recorded content in place, which is how
+        // wisty's own saves and a `touch` show up.
+        let recorded = fingerprints
+            .lock()
+            .ok()
+            .and_then(|fingerprints| fingerprints.get(target).copied());
+        let unchanged = match (recorded, std::fs::metadata(target)) {
+            (Some(fingerprint), Ok(metadata)) => {
+                content_unchanged(fingerprints, target, target, fingerprint, &metadata)
+                    .unwrap_or(false)
+            }
+            (None, _) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+    }
+
+    // Each window is told about the file under the path it opened it by,
+    // which may be relative or through a symlink.
+    let windows = subscribers
+        .lock()
+        .ok()
+        .and_then(|subscribers| subscribers.get(target).cloned())
+        .unwrap_or_default();
+    for (window, path) in windows {
+        let _ = app.emit_to(
+            window.as_str(),
+            FILE_CHANGED_EVENT,
+            FileChangedPayload {
+                path,
+                ..payload.clone()
+            },
+        );
+    }
+}
+
+fn classify_event(event: &notify::Event, target: &Path) -> Option<FileChangedPayload> {
+    let index = event.paths.iter().position(|path| path == target)?;
+    let path = target.to_string_lossy().to_string();
+
+    let kind = match event.kind {
+        EventKind::Modify(ModifyKind::Name(mode)) => {
+            if target.exists() {
+                // Something was renamed onto the file: an atomic save.
+                FileChangeKind::Modified
+            } else {
+                let new_path = match mode {
+                    RenameMode::Both if index == 0 => event
+                        .paths
+                        .get(1)
+                        .map(|path| path.to_string_lossy().to_string()),
+                    _ => None,
+                };
+                return Some(FileChangedPayload {
+                    path,
+                    kind: FileChangeKind::Renamed,
+                    new_path,
+                });
+            }
+        }
+        EventKind::Modify(_) | EventKind::Create(_) => FileChangeKind::Modified,
+        EventKind::Remove(_) => FileChangeKind::Removed,
+        _ => return None,
+    };
+
+    Some(FileChangedPayload {
+        path,
+        kind,
+        new_path: None,
+    })
+}
+
+/// Stops tracking a file for the calling window, e.g. when it moves on to
+/// another document.
+#[tauri::command]
+pub fn unwatch_file(
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, FileWatchState>,
+    file_path: String,
+) {
+    state.release(window.label(), Path::new(&file_path));
+}
+
+/// Reports whether a tracked file has diverged from the copy in the editor.
+#[tauri::command]
+pub fn check_file_on_disk(
+    state: tauri::State<'_, FileWatchState>,
+    file_path: String,
+) -> Result<FileDiskStatus, String> {
+    state.disk_status(Path::new(&file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint_of(path: &Path) -> FileFingerprint {
+        let metadata = std::fs::metadata(path).unwrap();
+        FileFingerprint::new(&metadata, hash_file(path).unwrap())
+    }
+
+    #[test]
+    fn detects_divergence_from_recorded_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, b"original").unwrap();
+        let state = FileWatchState::default();
+        assert_eq!(state.disk_status(&path).unwrap(), FileDiskStatus::Untracked);
+
+        // The same content with a new modification time is unchanged.
+        let mut fingerprint = fingerprint_of(&path);
+        fingerprint.modified = Some(SystemTime::UNIX_EPOCH);
+        state.update_fingerprint(&path, fingerprint);
+        assert_eq!(state.disk_status(&path).unwrap(), FileDiskStatus::Unchanged);
+        assert_eq!(state.fingerprint(&path), Some(fingerprint_of(&path)));
+
+        std::fs::write(&path, b"changed by another tool").unwrap();
+        assert_eq!(state.disk_status(&path).unwrap(), FileDiskStatus::Modified);
+
+        // Watching goes on until the last window lets go.
+        let spellings = [("main", "notes.txt"), ("document-1", "./notes.txt")]
+            .map(|(window, spelling)| (window.to_string(), spelling.to_string()));
+        state
+            .subscribers
+            .lock()
+            .unwrap()
+            .insert(tracking_key(&path), HashMap::from(spellings));
+        state.release("main", &path);
+        assert!(state.fingerprint(&path).is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(state.disk_status(&path).unwrap(), FileDiskStatus::Missing);
+        state.release_window("document-1");
+        assert_eq!(state.fingerprint(&path), None);
+    }
+
+    #[test]
+    fn classifies_rename_and_remove_events() {
+        let target = PathBuf::from("/tmp/wisty-missing-dir/notes.txt");
+        let moved = PathBuf::from("/tmp/wisty-missing-dir/notes-old.txt");
+
+        let rename = notify::Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(target.clone())
+            .add_path(moved.clone());
+        let payload = classify_event(&rename, &target).unwrap();
+        assert_eq!(payload.kind, FileChangeKind::Renamed);
+        assert_eq!(payload.new_path.as_deref(), moved.to_str());
+
+        let remove = notify::Event::new(EventKind::Remove(notify::event::RemoveKind::File))
+            .add_path(target.clone());
+        assert_eq!(
+            classify_event(&remove, &target).unwrap().kind,
+            FileChangeKind::Removed
+        );
+
+        let unrelated =
+            notify::Event::new(EventKind::Remove(notify::event::RemoveKind::File)).add_path(moved);
+        assert!(classify_event(&unrelated, &target).is_none());
+    }
+}
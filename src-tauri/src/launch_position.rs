@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the editor should place the cursor once a launch file has finished
+/// streaming. Both numbers are 1-based, as compilers and grep print them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchPosition {
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+fn parse_number(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().filter(|number| *number > 0)
+}
+
+/// Parses the `+N` argument that puts the following file at line N.
+pub fn parse_line_arg(arg: &str) -> Option<LaunchPosition> {
+    let line = parse_number(arg.strip_prefix('+')?)?;
+    Some(LaunchPosition { line, column: None })
+}
+
+/// Parses the `LINE[:COLUMN]` value of `--line`.
+pub fn parse_position(value: &str) -> Option<LaunchPosition> {
+    let (line, column) = match value.split_once(':') {
+        Some((line, column)) => (line, Some(parse_number(column)?)),
+        None => (value, None),
+    };
+    Some(LaunchPosition {
+        line: parse_number(line)?,
+        column,
+    })
+}
+
+/// Splits `path:line[:col]` into the path and position. `exists` decides
+/// between readings: a file whose name really ends in `:12` is taken whole,
+/// and `a:1:2` prefers `a` at 1:2 over `a:1` at line 2 only if `a` exists.
+/// When no reading names an existing file, the position is split off anyway,
+/// since a new file named `notes.txt:12` is rarely what was meant.
+pub fn split_position(raw: &str, exists: impl Fn(&str) -> bool) -> (&str, Option<LaunchPosition>) {
+    if exists(raw) {
+        return (raw, None);
+    }
+
+    let mut readings = Vec::new();
+    if let Some((rest, last)) = raw.rsplit_once(':') {
+        if let Some(last) = parse_number(last) {
+            if let Some((path, line)) = rest.rsplit_once(':') {
+                if let Some(line) = parse_number(line) {
+                    let position = LaunchPosition {
+                        line,
+                        column: Some(last),
+                    };
+                    readings.push((path, position));
+                }
+            }
+            let position = LaunchPosition {
+                line: last,
+                column: None,
+            };
+            readings.push((rest, position));
+        }
+    }
+
+    let chosen = readings
+        .iter()
+        .find(|(path, _)| exists(path))
+        .or(readings.first())
+        .filter(|(path, _)| !path.is_empty());
+    match chosen {
+        Some((path, position)) => (path, Some(*position)),
+        None => (raw, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, column: Option<u32>) -> Option<LaunchPosition> {
+        Some(LaunchPosition { line, column })
+    }
+
+    #[test]
+    fn parses_plus_line_arguments() {
+        assert_eq!(parse_line_arg("+120"), at(120, None));
+        assert_eq!(parse_line_arg("+0"), None);
+        assert_eq!(parse_line_arg("+x"), None);
+        assert_eq!(parse_line_arg("120"), None);
+    }
+
+    #[test]
+    fn parses_line_option_values() {
+        assert_eq!(parse_position("120"), at(120, None));
+        assert_eq!(parse_position("120:5"), at(120, Some(5)));
+        assert_eq!(parse_position("120:"), None);
+        assert_eq!(parse_position(":5"), None);
+    }
+
+    #[test]
+    fn splits_line_and_column_suffixes() {
+        let none = |_: &str| false;
+        assert_eq!(split_position("notes.txt", none), ("notes.txt", None));
+        assert_eq!(
+            split_position("notes.txt:120", none),
+            ("notes.txt", at(120, None))
+        );
+        assert_eq!(
+            split_position("src/notes.txt:120:5", none),
+            ("src/notes.txt", at(120, Some(5)))
+        );
+        assert_eq!(split_position("notes.txt:", none), ("notes.txt:", None));
+        assert_eq!(split_position(":12", none), (":12", None));
+        assert_eq!(split_position("notes.txt:0", none), ("notes.txt:0", None));
+    }
+
+    #[test]
+    fn prefers_readings_that_name_existing_files() {
+        assert_eq!(
+            split_position("log:2024:7", |path| path == "log:2024:7"),
+            ("log:2024:7", None)
+        );
+        assert_eq!(
+            split_position("log:2024:7", |path| path == "log:2024"),
+            ("log:2024", at(7, None))
+        );
+        assert_eq!(
+            split_position("log:2024:7", |path| path == "log"),
+            ("log", at(2024, Some(7)))
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+/// Keeps the invoking process alive until its documents are closed, for use
+/// as `$EDITOR` or `GIT_EDITOR`.
+pub const WAIT_FLAG: &str = "--wait";
+
+/// Like `--wait`, but exits non-zero when a document was closed without ever
+/// being saved, so the caller can abort.
+pub const WAIT_REQUIRE_SAVE_FLAG: &str = "--wait-require-save";
+
+/// Exit status of a `--wait-require-save` launch whose document wasn't saved.
+pub const UNSAVED_EXIT_CODE: i32 = 1;
+
+struct WaitedDocument {
+    group: u64,
+    path: PathBuf,
+    window_label: Option<String>,
+    saved: bool,
+}
+
+/// The documents of one `--wait` invocation. It is answered once all of them
+/// are closed.
+struct WaitGroup {
+    remaining: usize,
+    all_saved: bool,
+    done: Sender<bool>,
+}
+
+#[derive(Default)]
+struct LaunchWaitsInner {
+    next_id: u64,
+    documents: HashMap<u64, WaitedDocument>,
+    groups: HashMap<u64, WaitGroup>,
+}
+
+/// Documents that a waiting `wisty --wait` process is blocked on, keyed by
+/// the wait id their launch file arg carries to the frontend.
+#[derive(Default)]
+pub struct LaunchWaits {
+    inner: Mutex<LaunchWaitsInner>,
+}
+
+impl LaunchWaits {
+    /// Starts waiting on `paths`. Returns an id per path, in order, and the
+    /// receiver that gets whether all of them were saved once the last one
+    /// is closed.
+    pub fn begin(&self, paths: &[PathBuf]) -> (Vec<u64>, Receiver<bool>) {
+        let (done, receiver) = mpsc::channel();
+        let Ok(mut inner) = self.inner.lock() else {
+            let _ = done.send(false);
+            return (Vec::new(), receiver);
+        };
+        if paths.is_empty() {
+            let _ = done.send(false);
+            return (Vec::new(), receiver);
+        }
+
+        inner.next_id += 1;
+        let group = inner.next_id;
+        inner.groups.insert(
+            group,
+            WaitGroup {
+                remaining: paths.len(),
+                all_saved: true,
+                done,
+            },
+        );
+        let ids = paths
+            .iter()
+            .map(|path| {
+                inner.next_id += 1;
+                let id = inner.next_id;
+                inner.documents.insert(
+                    id,
+                    WaitedDocument {
+                        group,
+                        path: path.clone(),
+                        window_label: None,
+                        saved: false,
+                    },
+                );
+                id
+            })
+            .collect();
+        (ids, receiver)
+    }
+
+    /// Records the window a waited document was opened in, so closing that
+    /// window releases it.
+    pub fn bind_window(&self, id: u64, window_label: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if let Some(document) = inner.documents.get_mut(&id) {
+                document.window_label = Some(window_label.to_string());
+            }
+        }
+    }
+
+    /// Marks the waited documents at `path` as saved.
+    pub fn mark_saved(&self, path: &Path) {
+        if let Ok(mut inner) = self.inner.lock() {
+            for document in inner.documents.values_mut() {
+                if document.path == path {
+                    document.saved = true;
+                }
+            }
+        }
+    }
+
+    /// Stops waiting on one document: its window moved on to another one.
+    pub fn release(&self, id: u64) {
+        if let Ok(mut inner) = self.inner.lock() {
+            release_document(&mut inner, id);
+        }
+    }
+
+    /// Stops waiting on every document shown in a window that was closed.
+    pub fn release_window(&self, window_label: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let ids: Vec<u64> = inner
+                .documents
+                .iter()
+                .filter(|(_, document)| document.window_label.as_deref() == Some(window_label))
+                .map(|(id, _)| *id)
+                .collect();
+            for id in ids {
+                release_document(&mut inner, id);
+            }
+        }
+    }
+
+    /// Stops waiting on everything, as the app exits.
+    pub fn release_all(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let ids: Vec<u64> = inner.documents.keys().copied().collect();
+            for id in ids {
+                release_document(&mut inner, id);
+            }
+        }
+    }
+}
+
+fn release_document(inner: &mut LaunchWaitsInner, id: u64) {
+    let Some(document) = inner.documents.remove(&id) else {
+        return;
+    };
+    let Some(group) = inner.groups.get_mut(&document.group) else {
+        return;
+    };
+    group.remaining -= 1;
+    group.all_saved &= document.saved;
+    if group.remaining == 0 {
+        if let Some(group) = inner.groups.remove(&document.group) {
+            let _ = group.done.send(group.all_saved);
+        }
+    }
+}
+
+/// The wait of a launch that runs its own instance rather than forwarding,
+/// reported through the exit status when the app exits.
+pub struct LocalWait {
+    pub receiver: Mutex<Receiver<bool>>,
+    pub require_save: bool,
+}
+
+/// The exit status for a `--wait` launch whose documents were closed, saved
+/// or not.
+pub fn exit_code(all_saved: bool, require_save: bool) -> i32 {
+    if require_save && !all_saved {
+        UNSAVED_EXIT_CODE
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_once_every_document_is_closed() {
+        let waits = LaunchWaits::default();
+        let (ids, receiver) = waits.begin(&[PathBuf::from("/a"), PathBuf::from("/b")]);
+        waits.bind_window(ids[1], "document-1");
+
+        waits.mark_saved(Path::new("/a"));
+        waits.release(ids[0]);
+        assert!(receiver.try_recv().is_err());
+
+        waits.release_window("document-1");
+        assert_eq!(receiver.try_recv(), Ok(false));
+        assert_eq!(exit_code(false, true), UNSAVED_EXIT_CODE);
+        assert_eq!(exit_code(false, false), 0);
+    }
+
+    #[test]
+    fn reports_saved_documents() {
+        let waits = LaunchWaits::default();
+        let (ids, receiver) = waits.begin(&[PathBuf::from("/repo/.git/COMMIT_EDITMSG")]);
+        waits.mark_saved(Path::new("/repo/.git/COMMIT_EDITMSG"));
+        waits.release(ids[0]);
+        waits.release(ids[0]);
+        assert_eq!(receiver.try_recv(), Ok(true));
+
+        let (_, receiver) = waits.begin(&[]);
+        assert_eq!(receiver.try_recv(), Ok(false));
+
+        let (_, receiver) = waits.begin(&[PathBuf::from("/left/open")]);
+        waits.release_all();
+        assert_eq!(receiver.try_recv(), Ok(false));
+    }
+}
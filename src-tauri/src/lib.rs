@@ -1,18 +1,25 @@
 use log::LevelFilter;
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::BufWriter;
 use std::io::ErrorKind;
 use std::io::{IsTerminal, Read, Write};
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
 use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tauri_plugin_log::{Target, TargetKind};
 
 const CLI_SOFT_LIMIT_BYTES: u64 = 50 * 1024 * 1024;
 const CLI_HARD_LIMIT_BYTES: u64 = 1024 * 1024 * 1024;
+const SETTINGS_STORE_FILE: &str = "settings.json";
+/// The window from `tauri.conf.json`.
+const MAIN_WINDOW_LABEL: &str = "main";
+/// Windows opened beyond the main one are labelled `document-1`, `document-2`
+/// and so on, which is what the default capability matches.
+const DOCUMENT_WINDOW_LABEL_PREFIX: &str = "document-";
 
 #[derive(serde::Serialize)]
 struct EditorFontSelection {
@@ -35,34 +42,66 @@ struct EditorFontInput {
     font_weight: i32,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct LaunchFileArg {
     path: String,
     exists: bool,
     #[serde(rename = "fileSizeBytes")]
     file_size_bytes: Option<u64>,
+    /// Set for `-`: the content is streamed with `start_stdin_stream`.
+    stdin: bool,
+    /// From `+N`, a `path:line[:col]` suffix or `--line`.
+    position: Option<launch_position::LaunchPosition>,
+    #[serde(flatten)]
+    options: cli::FileOptions,
+    /// Set when a `--wait` process is blocked until this document closes;
+    /// passed to `release_launch_wait` if the window moves on to another.
+    wait_id: Option<u64>,
+}
+
+/// Everything the command line asked to open, resolved before the window
+/// exists.
+struct LaunchArgs {
+    files: VecDeque<LaunchFileArg>,
+    stdin_spool: Option<stdio::StdinSpool>,
+    stdout_spool: Option<PathBuf>,
 }
 
-struct LaunchFileStream {
-    file: File,
+/// What a second `wisty` invocation sends to the running one.
+#[derive(Serialize, Deserialize)]
+struct ForwardedLaunch {
+    files: Vec<LaunchFileArg>,
+    /// Launched with `--wait`: keep the connection until the files close.
+    wait: bool,
+}
+
+struct OpenFileStream {
+    reader: Box<dyn Read + Send>,
     file_path: String,
     file_size_bytes: u64,
     bytes_read_total: u64,
-    utf8_carry: Vec<u8>,
+    decoder: text_encoding::StreamDecoder,
+    content_hash: file_watcher::ContentHasher,
+    /// Metadata the file watcher's fingerprint starts from. None for
+    /// recovery snapshots and standard input, which no other program edits.
+    watched_metadata: Option<std::fs::Metadata>,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct LaunchFileStreamStartResult {
+struct OpenFileStreamStartResult {
     stream_id: String,
     file_path: String,
     file_size_bytes: u64,
+    encoding: String,
+    has_bom: bool,
+    line_ending: line_endings::LineEnding,
 }
 
 #[derive(Serialize)]
 #[serde(tag = "kind", rename_all = "camelCase")]
-enum LaunchFileStreamChunkResult {
+enum OpenFileStreamChunkResult {
     Chunk {
         text: String,
         #[serde(rename = "bytesReadTotal")]
@@ -78,11 +117,52 @@ enum LaunchFileStreamChunkResult {
     },
 }
 
+/// Paths the frontend may stream with `start_open_file_stream`. Seeded with
+/// the launch file and filled by the Open dialog in `pick_open_file`. Entries
+/// are canonical so relative or symlinked spellings of a path compare equal.
+#[derive(Default)]
+struct PathApprovalRegistry {
+    paths: Mutex<HashSet<PathBuf>>,
+}
+
+impl PathApprovalRegistry {
+    fn approve(&self, path: &Path) -> Result<(), String> {
+        let canonical_path = std::fs::canonicalize(path).map_err(|error| {
+            format!(
+                "Unable to normalize file path '{}': {error}",
+                path.to_string_lossy()
+            )
+        })?;
+        self.paths
+            .lock()
+            .map_err(|error| format!("Unable to update approved paths: {error}"))?
+            .insert(canonical_path);
+        Ok(())
+    }
+
+    fn is_approved(&self, canonical_path: &Path) -> Result<bool, String> {
+        Ok(self
+            .paths
+            .lock()
+            .map_err(|error| format!("Unable to read approved paths: {error}"))?
+            .contains(canonical_path))
+    }
+}
+
 struct LaunchArgState {
-    pending_file: Mutex<Option<LaunchFileArg>>,
-    approved_launch_file_path: Option<String>,
-    launch_stream_counter: Mutex<u64>,
-    active_launch_streams: Mutex<HashMap<String, LaunchFileStream>>,
+    /// Files from the command line not yet taken by a window. Each window
+    /// takes the next one, in command-line order.
+    pending_files: Mutex<VecDeque<LaunchFileArg>>,
+    /// Windows that haven't loaded yet. Each takes a pending file once it
+    /// has, so they count against the queue when more windows are opened.
+    loading_windows: Mutex<HashSet<String>>,
+    pending_stdin: Mutex<Option<stdio::StdinSpool>>,
+    /// Where `--stdout` saves go until the app exits.
+    stdout_spool: Option<PathBuf>,
+    document_window_counter: Mutex<u64>,
+    approved_open_paths: PathApprovalRegistry,
+    open_stream_counter: Mutex<u64>,
+    active_open_streams: Mutex<HashMap<String, OpenFileStream>>,
     save_stream_counter: Mutex<u64>,
     active_save_streams: Mutex<HashMap<String, SaveFileStream>>,
 }
@@ -91,7 +171,22 @@ struct SaveFileStream {
     target_path: PathBuf,
     temp_path: PathBuf,
     writer: BufWriter<File>,
+    encoder: text_encoding::StreamEncoder,
+    line_endings: line_endings::LineEndingNormalizer,
     bytes_written_total: u64,
+    content_hash: file_watcher::ContentHasher,
+    /// Set when the target has other hard links: the finished temp file is
+    /// copied into it instead of renamed over it.
+    write_in_place: bool,
+    /// Set when the user can't write the target: `temp_path` is a private
+    /// staging file that the privileged helper installs on finish.
+    requires_elevation: bool,
+    /// Set for the `--stdout` document: the target is the stdout spool, which
+    /// is neither backed up nor watched.
+    to_stdout: bool,
+    /// Set for exports: the target isn't the open document, so it is neither
+    /// backed up nor watched, and writing it doesn't count as saving.
+    export: bool,
 }
 
 #[derive(Serialize)]
@@ -99,6 +194,9 @@ struct SaveFileStream {
 struct SaveFileStreamStartResult {
     stream_id: String,
     file_path: String,
+    encoding: String,
+    has_bom: bool,
+    requires_elevation: bool,
 }
 
 #[derive(Serialize)]
@@ -114,48 +212,34 @@ struct SaveFileStreamFinishResult {
 }
 
 impl LaunchArgState {
-    fn new(pending_file: Option<LaunchFileArg>) -> Self {
-        let approved_launch_file_path = pending_file.as_ref().and_then(|file| {
-            if file.exists {
-                Some(file.path.clone())
-            } else {
-                None
+    fn new(launch: LaunchArgs) -> Self {
+        let approved_open_paths = PathApprovalRegistry::default();
+        if let Ok(mut paths) = approved_open_paths.paths.lock() {
+            // The paths were canonicalized when the arguments were validated.
+            for file in launch
+                .files
+                .iter()
+                .filter(|file| file.exists && !file.stdin)
+            {
+                paths.insert(PathBuf::from(&file.path));
             }
-        });
+        }
 
         Self {
-            pending_file: Mutex::new(pending_file),
-            approved_launch_file_path,
-            launch_stream_counter: Mutex::new(0),
-            active_launch_streams: Mutex::new(HashMap::new()),
+            pending_files: Mutex::new(launch.files),
+            loading_windows: Mutex::new(HashSet::from([MAIN_WINDOW_LABEL.to_string()])),
+            pending_stdin: Mutex::new(launch.stdin_spool),
+            stdout_spool: launch.stdout_spool,
+            document_window_counter: Mutex::new(0),
+            approved_open_paths,
+            open_stream_counter: Mutex::new(0),
+            active_open_streams: Mutex::new(HashMap::new()),
             save_stream_counter: Mutex::new(0),
             active_save_streams: Mutex::new(HashMap::new()),
         }
     }
 }
 
-fn parse_positional_launch_args() -> Result<Option<String>, String> {
-    let mut positional: Vec<String> = Vec::new();
-    let mut passthrough_mode = false;
-
-    for arg in std::env::args().skip(1) {
-        if !passthrough_mode && arg == "--" {
-            passthrough_mode = true;
-            continue;
-        }
-        if !passthrough_mode && arg.starts_with('-') {
-            continue;
-        }
-        positional.push(arg);
-    }
-
-    if positional.len() > 1 {
-        return Err("Only one file path argument is supported".to_string());
-    }
-
-    Ok(positional.into_iter().next())
-}
-
 /// Decodes `%XX` escapes in a `file://` URI path (e.g. `%20` for a space).
 fn percent_decode(input: &str) -> Result<String, String> {
     let bytes = input.as_bytes();
@@ -222,34 +306,71 @@ fn format_size_mb(size_bytes: u64) -> String {
     format!("{:.1}", size_bytes as f64 / (1024.0 * 1024.0))
 }
 
-fn confirm_large_cli_open(path: &Path, size_bytes: u64) -> Result<(), String> {
-    if size_bytes < CLI_SOFT_LIMIT_BYTES {
+/// Asks once about every large file on the command line, so `wisty *.log`
+/// doesn't prompt file by file. Files over the hard limit are refused
+/// outright.
+fn confirm_large_cli_open(files: &[LaunchFileArg]) -> Result<(), String> {
+    let large_files: Vec<(&str, u64)> = files
+        .iter()
+        .filter(|file| !file.stdin)
+        .filter_map(|file| Some((file.path.as_str(), file.file_size_bytes?)))
+        .filter(|(_, size_bytes)| *size_bytes >= CLI_SOFT_LIMIT_BYTES)
+        .collect();
+    if large_files.is_empty() {
         return Ok(());
     }
 
-    if size_bytes >= CLI_HARD_LIMIT_BYTES {
+    if let Some((path, size_bytes)) = large_files
+        .iter()
+        .find(|(_, size_bytes)| *size_bytes >= CLI_HARD_LIMIT_BYTES)
+    {
         return Err(format!(
             "File is too large to open safely ({} MB, hard limit is {} MB): {}",
-            format_size_mb(size_bytes),
+            format_size_mb(*size_bytes),
             format_size_mb(CLI_HARD_LIMIT_BYTES),
-            path.to_string_lossy()
+            path
         ));
     }
 
+    let total_bytes: u64 = large_files.iter().map(|(_, size_bytes)| size_bytes).sum();
+    let paths = large_files
+        .iter()
+        .map(|(path, _)| *path)
+        .collect::<Vec<_>>()
+        .join(", ");
     if !is_interactive_tty() {
-        return Err(format!(
-            "File is large ({} MB). Run from an interactive terminal to confirm opening: {}",
-            format_size_mb(size_bytes),
-            path.to_string_lossy()
-        ));
+        return Err(if large_files.len() == 1 {
+            format!(
+                "File is large ({} MB). Run from an interactive terminal to confirm opening: {}",
+                format_size_mb(total_bytes),
+                paths
+            )
+        } else {
+            format!(
+                "{} files are large ({} MB in total). Run from an interactive terminal to confirm opening: {}",
+                large_files.len(),
+                format_size_mb(total_bytes),
+                paths
+            )
+        });
     }
 
-    eprintln!(
-        "wisty: warning: '{}' is a large file ({} MB).",
-        path.to_string_lossy(),
-        format_size_mb(size_bytes)
-    );
-    eprint!("Open anyway? [y/N] ");
+    for (path, size_bytes) in &large_files {
+        eprintln!(
+            "wisty: warning: '{}' is a large file ({} MB).",
+            path,
+            format_size_mb(*size_bytes)
+        );
+    }
+    if large_files.len() == 1 {
+        eprint!("Open anyway? [y/N] ");
+    } else {
+        eprint!(
+            "Open all {} ({} MB in total) anyway? [y/N] ",
+            large_files.len(),
+            format_size_mb(total_bytes)
+        );
+    }
     std::io::stderr()
         .flush()
         .map_err(|error| format!("Unable to prompt for confirmation: {error}"))?;
@@ -264,10 +385,7 @@ fn confirm_large_cli_open(path: &Path, size_bytes: u64) -> Result<(), String> {
         return Ok(());
     }
 
-    Err(format!(
-        "Opening cancelled for large file: {}",
-        path.to_string_lossy()
-    ))
+    Err(format!("Opening cancelled for large file: {}", paths))
 }
 
 fn validate_launch_file_arg(path: &Path) -> Result<LaunchFileArg, String> {
@@ -279,7 +397,6 @@ fn validate_launch_file_arg(path: &Path) -> Result<LaunchFileArg, String> {
                     path.to_string_lossy()
                 ));
             }
-            confirm_large_cli_open(path, metadata.len())?;
             let canonical_path = std::fs::canonicalize(path).map_err(|error| {
                 format!(
                     "Unable to normalize file path '{}': {error}",
@@ -291,6 +408,10 @@ fn validate_launch_file_arg(path: &Path) -> Result<LaunchFileArg, String> {
                 path: canonical_path.to_string_lossy().to_string(),
                 exists: true,
                 file_size_bytes: Some(metadata.len()),
+                stdin: false,
+                position: None,
+                options: cli::FileOptions::default(),
+                wait_id: None,
             })
         }
         Err(error) if error.kind() == ErrorKind::NotFound => {
@@ -333,6 +454,10 @@ fn validate_launch_file_arg(path: &Path) -> Result<LaunchFileArg, String> {
                 path: normalized_path.to_string_lossy().to_string(),
                 exists: false,
                 file_size_bytes: None,
+                stdin: false,
+                position: None,
+                options: cli::FileOptions::default(),
+                wait_id: None,
             })
         }
         Err(error) => Err(format!(
@@ -342,14 +467,92 @@ fn validate_launch_file_arg(path: &Path) -> Result<LaunchFileArg, String> {
     }
 }
 
-fn resolve_launch_file_arg() -> Result<Option<LaunchFileArg>, String> {
-    let raw = parse_positional_launch_args()?;
-    let Some(raw_path) = raw else {
-        return Ok(None);
+fn resolve_launch_args(options: &cli::LaunchOptions) -> Result<LaunchArgs, String> {
+    let write_to_stdout = options.stdout;
+    if let Some(label) = &options.file_options.encoding {
+        text_encoding::resolve_encoding(label)?;
+    }
+
+    let mut files = VecDeque::new();
+    let mut stdin_spool = None;
+    for path_arg in &options.paths {
+        let raw_arg = path_arg.raw.as_str();
+        let line_arg = path_arg.line_arg.or(options.default_position);
+        if raw_arg != stdio::STDIO_ARG {
+            // `notes.txt:12:5` as printed by compilers and grep, unless a
+            // file by that exact name exists.
+            let (raw_path, suffix) = launch_position::split_position(raw_arg, |candidate| {
+                normalize_cli_path(candidate).is_ok_and(|path| path.exists())
+            });
+            let mut file = validate_launch_file_arg(&normalize_cli_path(raw_path)?)?;
+            if options.file_options.read_only && !file.exists {
+                return Err(format!(
+                    "Cannot open '{}' read-only: it does not exist",
+                    file.path
+                ));
+            }
+            file.position = suffix.or(line_arg);
+            file.options = options.file_options.clone();
+            files.push_back(file);
+            continue;
+        }
+        if stdin_spool.is_some() {
+            return Err(format!(
+                "Standard input ('{}') can only be opened once",
+                stdio::STDIO_ARG
+            ));
+        }
+        let spool = stdio::spool_stdin(CLI_HARD_LIMIT_BYTES)?;
+        files.push_back(LaunchFileArg {
+            path: stdio::STDIO_ARG.to_string(),
+            exists: true,
+            file_size_bytes: Some(spool.size_bytes()),
+            stdin: true,
+            position: line_arg,
+            options: options.file_options.clone(),
+            wait_id: None,
+        });
+        stdin_spool = Some(spool);
+    }
+    confirm_large_cli_open(files.make_contiguous())?;
+
+    // The `--stdout` document is untitled, so a file argument would have
+    // nowhere to go.
+    if write_to_stdout && files.iter().any(|file| !file.stdin) {
+        return Err(format!(
+            "{} can only be combined with '{}'",
+            stdio::STDOUT_FLAG,
+            stdio::STDIO_ARG
+        ));
+    }
+    // A waiting caller reads the result back from the file it passed.
+    if options.wait {
+        if files.is_empty() {
+            return Err(format!("{} needs a file to edit", launch_wait::WAIT_FLAG));
+        }
+        if stdin_spool.is_some() || write_to_stdout {
+            return Err(format!(
+                "{} can't be combined with standard input or output",
+                launch_wait::WAIT_FLAG
+            ));
+        }
+    }
+
+    let stdout_spool = if write_to_stdout {
+        let spool_path = match &stdin_spool {
+            Some(spool) => stdio::create_stdout_spool(&std::env::temp_dir(), &mut spool.open()?)?,
+            None => stdio::create_stdout_spool(&std::env::temp_dir(), &mut std::io::empty())?,
+        };
+        Some(spool_path)
+    } else {
+        None
     };
 
-    let normalized = normalize_cli_path(&raw_path)?;
-    validate_launch_file_arg(&normalized).map(Some)
+    Ok(LaunchArgs {
+        files,
+        stdin_spool,
+        stdout_spool,
+    })
 }
 
 fn to_pango_style(value: &str) -> gtk::pango::Style {
@@ -473,129 +676,335 @@ fn choose_editor_font(
 
 #[tauri::command]
 fn take_launch_file_arg(
+    window: tauri::WebviewWindow,
     state: tauri::State<'_, LaunchArgState>,
+    waits: tauri::State<'_, launch_wait::LaunchWaits>,
 ) -> Result<Option<LaunchFileArg>, String> {
     let mut guard = state
-        .pending_file
+        .pending_files
         .lock()
         .map_err(|error| format!("Unable to read launch args state: {error}"))?;
 
-    Ok(guard.take())
+    let file = guard.pop_front();
+    set_window_loading(&state, window.label(), false);
+    if let Some(wait_id) = file.as_ref().and_then(|file| file.wait_id) {
+        // Closing this window now closes the waited document.
+        waits.bind_window(wait_id, window.label());
+    }
+    Ok(file)
 }
 
-fn split_utf8_prefix(input: &[u8]) -> Result<(String, Vec<u8>), String> {
-    match std::str::from_utf8(input) {
-        Ok(text) => Ok((text.to_string(), Vec::new())),
-        Err(error) => {
-            let valid_up_to = error.valid_up_to();
-            if let Some(_error_len) = error.error_len() {
-                return Err("Launch stream contains invalid UTF-8 data".to_string());
-            }
+/// Called when the window showing a waited document opens another one in
+/// its place, so the waiting process is told it was closed.
+#[tauri::command]
+fn release_launch_wait(waits: tauri::State<'_, launch_wait::LaunchWaits>, wait_id: u64) {
+    waits.release(wait_id);
+}
 
-            let valid = std::str::from_utf8(&input[..valid_up_to])
-                .map_err(|_| "Failed to decode launch stream UTF-8 prefix".to_string())?;
+#[tauri::command]
+fn launch_writes_to_stdout(state: tauri::State<'_, LaunchArgState>) -> bool {
+    state.stdout_spool.is_some()
+}
 
-            Ok((valid.to_string(), input[valid_up_to..].to_vec()))
-        }
+/// Whether the frontend may read `canonical_path`: it was chosen in the Open
+/// dialog or on the command line this session, or opened or saved recently.
+fn is_approved_for_reading(
+    app: &tauri::AppHandle,
+    state: &LaunchArgState,
+    canonical_path: &Path,
+) -> Result<bool, String> {
+    Ok(state.approved_open_paths.is_approved(canonical_path)?
+        || app
+            .state::<recent_files::RecentFiles>()
+            .contains(app, canonical_path))
+}
+
+/// Remembers a file the user opened or saved, so the Recent menu can open
+/// it again next session. Failing only costs the menu entry.
+fn record_recent_file(app: &tauri::AppHandle, path: &Path) {
+    let recorded = std::fs::canonicalize(path)
+        .map_err(|error| {
+            format!(
+                "Unable to normalize file path '{}': {error}",
+                path.display()
+            )
+        })
+        .and_then(|canonical_path| {
+            app.state::<recent_files::RecentFiles>()
+                .record(app, &canonical_path)
+        });
+    if let Err(error) = recorded {
+        log::warn!(target: "wisty::recent_files", "{error}");
     }
 }
 
+/// Shows the native Open dialog and approves the chosen file for streaming.
+/// Runs as an async command because the blocking dialog call must not run on
+/// the main thread.
 #[tauri::command]
-fn start_launch_file_stream(
+async fn pick_open_file(
+    app: tauri::AppHandle,
     state: tauri::State<'_, LaunchArgState>,
-    file_path: String,
-) -> Result<LaunchFileStreamStartResult, String> {
-    let approved_path = state
-        .approved_launch_file_path
-        .as_ref()
-        .ok_or_else(|| "No launch file is available for streaming".to_string())?;
+    default_directory: Option<String>,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
 
-    if &file_path != approved_path {
-        return Err("Requested path is not an approved launch file".to_string());
+    let mut dialog = app.dialog().file();
+    if let Some(directory) = default_directory.filter(|value| !value.trim().is_empty()) {
+        dialog = dialog.set_directory(directory);
     }
 
-    let metadata = std::fs::metadata(&file_path).map_err(|error| {
-        format!(
-            "Unable to read launch file metadata '{}': {error}",
-            file_path
-        )
-    })?;
+    let Some(selected) = dialog.blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = selected
+        .into_path()
+        .map_err(|error| format!("Unable to resolve selected file: {error}"))?;
+
+    state.approved_open_paths.approve(&path)?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+fn start_open_file_stream(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    encoding: Option<String>,
+) -> Result<OpenFileStreamStartResult, String> {
+    let forced_encoding = encoding
+        .as_deref()
+        .map(text_encoding::resolve_encoding)
+        .transpose()?;
+    let canonical_path = std::fs::canonicalize(&file_path)
+        .map_err(|error| format!("Unable to open file '{}': {error}", file_path))?;
+
+    let is_recovery_snapshot = recovery::is_recovery_snapshot(
+        &app,
+        &app.state::<recovery::RecoveryState>(),
+        &canonical_path,
+    );
+    if !is_recovery_snapshot && !is_approved_for_reading(&app, &state, &canonical_path)? {
+        return Err("Requested path has not been approved for opening".to_string());
+    }
+
+    let metadata = std::fs::metadata(&file_path)
+        .map_err(|error| format!("Unable to read file metadata '{}': {error}", file_path))?;
     if !metadata.is_file() {
-        return Err(format!("Launch path is not a regular file: {}", file_path));
+        return Err(format!("Path is not a regular file: {}", file_path));
     }
 
     let file = File::open(&file_path)
-        .map_err(|error| format!("Unable to open launch file '{}': {error}", file_path))?;
+        .map_err(|error| format!("Unable to open file '{}': {error}", file_path))?;
+
+    let file_size_bytes = metadata.len();
+    if !is_recovery_snapshot {
+        record_recent_file(&app, &canonical_path);
+    }
+    let watched_metadata = (!is_recovery_snapshot).then_some(metadata);
+    begin_open_stream(
+        &state,
+        Box::new(file),
+        file_path,
+        forced_encoding,
+        file_size_bytes,
+        watched_metadata,
+    )
+}
+
+/// Counts words, sentences, headings and so on in a file the user opened.
+/// The file is read on a blocking thread, so a large one holds up neither
+/// the main thread nor the async runtime.
+#[tauri::command]
+async fn document_stats(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    encoding: Option<String>,
+) -> Result<document_stats::DocumentStats, String> {
+    let forced_encoding = encoding
+        .as_deref()
+        .map(text_encoding::resolve_encoding)
+        .transpose()?;
+    let canonical_path = std::fs::canonicalize(&file_path)
+        .map_err(|error| format!("Unable to open file '{}': {error}", file_path))?;
+    if !is_approved_for_reading(&app, &state, &canonical_path)? {
+        return Err("Requested path has not been approved for opening".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        document_stats::stats_for_file(&canonical_path, forced_encoding)
+    })
+    .await
+    .map_err(|error| format!("Unable to count document: {error}"))?
+}
+
+/// Lists every misspelled word in a file the user opened, with where each
+/// one is. Checked on a blocking thread, like `document_stats`; progress is
+/// sent to the calling window.
+#[tauri::command]
+async fn spell_report_for_file(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    encoding: Option<String>,
+) -> Result<spell_report::SpellReport, String> {
+    let forced_encoding = encoding
+        .as_deref()
+        .map(text_encoding::resolve_encoding)
+        .transpose()?;
+    let canonical_path = std::fs::canonicalize(&file_path)
+        .map_err(|error| format!("Unable to open file '{}': {error}", file_path))?;
+    if !is_approved_for_reading(&app, &state, &canonical_path)? {
+        return Err("Requested path has not been approved for opening".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let spell = app.state::<spellcheck::SpellState>();
+        spell_report::report_for_file(&window, &spell, &canonical_path, forced_encoding)
+    })
+    .await
+    .map_err(|error| format!("Unable to check spelling: {error}"))?
+}
+
+/// Streams the standard input read for the `-` launch argument. It can only
+/// be read once.
+#[tauri::command]
+fn start_stdin_stream(
+    state: tauri::State<'_, LaunchArgState>,
+    encoding: Option<String>,
+) -> Result<OpenFileStreamStartResult, String> {
+    let forced_encoding = encoding
+        .as_deref()
+        .map(text_encoding::resolve_encoding)
+        .transpose()?;
+    let spool = state
+        .pending_stdin
+        .lock()
+        .map_err(|error| format!("Unable to read launch args state: {error}"))?
+        .take()
+        .ok_or_else(|| "Standard input is not available".to_string())?;
+
+    let size_bytes = spool.size_bytes();
+    begin_open_stream(
+        &state,
+        Box::new(spool.open()?),
+        stdio::STDIO_ARG.to_string(),
+        forced_encoding,
+        size_bytes,
+        None,
+    )
+}
+
+/// Detects the encoding (unless `forced_encoding` is given) and line endings
+/// from the start of `source` and registers a stream that decodes the rest
+/// chunk by chunk.
+fn begin_open_stream(
+    state: &LaunchArgState,
+    source: Box<dyn Read + Send>,
+    file_path: String,
+    forced_encoding: Option<&'static encoding_rs::Encoding>,
+    file_size_bytes: u64,
+    watched_metadata: Option<std::fs::Metadata>,
+) -> Result<OpenFileStreamStartResult, String> {
+    let document = text_encoding::sample_document(source, forced_encoding)
+        .map_err(|error| format!("Unable to read file '{}': {error}", file_path))?;
+    let encoding = document.encoding;
+    let bom_length = document.bom.len();
+    // The rest of the sample is streamed again ahead of the source below, so
+    // only the skipped byte-order mark is hashed here.
+    let mut content_hash = file_watcher::ContentHasher::new();
+    content_hash.update(&document.bom);
+
+    // The byte-order mark is reported rather than streamed, so the editor
+    // never sees U+FEFF and the save stream can write it back on request.
+    let sample_text = text_encoding::StreamDecoder::new(encoding)
+        .decode(document.sample(), document.sample_is_complete)?;
+    let line_ending = line_endings::detect_line_ending(&sample_text, document.sample_is_complete);
+
+    let reader = Box::new(document.reader);
 
     let stream_id = {
         let mut counter = state
-            .launch_stream_counter
+            .open_stream_counter
             .lock()
-            .map_err(|error| format!("Unable to allocate launch stream id: {error}"))?;
+            .map_err(|error| format!("Unable to allocate open stream id: {error}"))?;
         *counter += 1;
-        format!("launch-{}", *counter)
+        format!("open-{}", *counter)
     };
 
     {
         let mut streams = state
-            .active_launch_streams
+            .active_open_streams
             .lock()
-            .map_err(|error| format!("Unable to store launch stream state: {error}"))?;
+            .map_err(|error| format!("Unable to store open stream state: {error}"))?;
         streams.insert(
             stream_id.clone(),
-            LaunchFileStream {
-                file,
+            OpenFileStream {
+                reader,
                 file_path: file_path.clone(),
-                file_size_bytes: metadata.len(),
-                bytes_read_total: 0,
-                utf8_carry: Vec::new(),
+                file_size_bytes,
+                bytes_read_total: bom_length as u64,
+                decoder: text_encoding::StreamDecoder::new(encoding),
+                content_hash,
+                watched_metadata,
             },
         );
     }
 
-    Ok(LaunchFileStreamStartResult {
+    Ok(OpenFileStreamStartResult {
         stream_id,
         file_path,
-        file_size_bytes: metadata.len(),
+        file_size_bytes,
+        encoding: encoding.name().to_string(),
+        has_bom: bom_length > 0,
+        line_ending,
     })
 }
 
 #[tauri::command]
-fn read_launch_file_chunk(
+fn read_open_file_chunk(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
     state: tauri::State<'_, LaunchArgState>,
+    watch_state: tauri::State<'_, file_watcher::FileWatchState>,
     stream_id: String,
     max_bytes: usize,
-) -> Result<LaunchFileStreamChunkResult, String> {
+) -> Result<OpenFileStreamChunkResult, String> {
     let mut streams = state
-        .active_launch_streams
+        .active_open_streams
         .lock()
-        .map_err(|error| format!("Unable to read launch stream state: {error}"))?;
+        .map_err(|error| format!("Unable to read open stream state: {error}"))?;
 
     let stream = streams
         .get_mut(&stream_id)
-        .ok_or_else(|| format!("Launch stream '{}' not found", stream_id))?;
+        .ok_or_else(|| format!("Open stream '{}' not found", stream_id))?;
 
     let read_size = max_bytes.clamp(4 * 1024, 1024 * 1024);
     let mut buffer = vec![0_u8; read_size];
     let read_count = stream
-        .file
+        .reader
         .read(&mut buffer)
-        .map_err(|error| format!("Unable to read launch file '{}': {error}", stream.file_path))?;
+        .map_err(|error| format!("Unable to read file '{}': {error}", stream.file_path))?;
 
     if read_count == 0 {
-        if stream.utf8_carry.is_empty() {
-            return Ok(LaunchFileStreamChunkResult::Eof {
+        let trailing = stream.decoder.decode(&[], true)?;
+        // Fully read: from here on, changes by other programs are reported.
+        if let Some(metadata) = &stream.watched_metadata {
+            watch_state.track(
+                &app,
+                window.label(),
+                Path::new(&stream.file_path),
+                file_watcher::FileFingerprint::new(metadata, stream.content_hash.finish()),
+            );
+        }
+        if trailing.is_empty() {
+            return Ok(OpenFileStreamChunkResult::Eof {
                 bytes_read_total: stream.bytes_read_total,
                 file_size_bytes: stream.file_size_bytes,
             });
         }
 
-        let trailing = std::str::from_utf8(&stream.utf8_carry)
-            .map_err(|_| "Launch stream ended with invalid UTF-8 sequence".to_string())?
-            .to_string();
-        stream.utf8_carry.clear();
-
-        return Ok(LaunchFileStreamChunkResult::Chunk {
+        return Ok(OpenFileStreamChunkResult::Chunk {
             text: trailing,
             bytes_read_total: stream.bytes_read_total,
             file_size_bytes: stream.file_size_bytes,
@@ -603,17 +1012,11 @@ fn read_launch_file_chunk(
     }
 
     stream.bytes_read_total += read_count as u64;
+    stream.content_hash.update(&buffer[..read_count]);
 
-    let mut combined = Vec::with_capacity(stream.utf8_carry.len() + read_count);
-    if !stream.utf8_carry.is_empty() {
-        combined.extend_from_slice(&stream.utf8_carry);
-    }
-    combined.extend_from_slice(&buffer[..read_count]);
+    let decoded = stream.decoder.decode(&buffer[..read_count], false)?;
 
-    let (decoded, carry) = split_utf8_prefix(&combined)?;
-    stream.utf8_carry = carry;
-
-    Ok(LaunchFileStreamChunkResult::Chunk {
+    Ok(OpenFileStreamChunkResult::Chunk {
         text: decoded,
         bytes_read_total: stream.bytes_read_total,
         file_size_bytes: stream.file_size_bytes,
@@ -621,27 +1024,27 @@ fn read_launch_file_chunk(
 }
 
 #[tauri::command]
-fn cancel_launch_file_stream(
+fn cancel_open_file_stream(
     state: tauri::State<'_, LaunchArgState>,
     stream_id: String,
 ) -> Result<(), String> {
     let mut streams = state
-        .active_launch_streams
+        .active_open_streams
         .lock()
-        .map_err(|error| format!("Unable to cancel launch stream state: {error}"))?;
+        .map_err(|error| format!("Unable to cancel open stream state: {error}"))?;
     streams.remove(&stream_id);
     Ok(())
 }
 
 #[tauri::command]
-fn close_launch_file_stream(
+fn close_open_file_stream(
     state: tauri::State<'_, LaunchArgState>,
     stream_id: String,
 ) -> Result<(), String> {
     let mut streams = state
-        .active_launch_streams
+        .active_open_streams
         .lock()
-        .map_err(|error| format!("Unable to close launch stream state: {error}"))?;
+        .map_err(|error| format!("Unable to close open stream state: {error}"))?;
     streams.remove(&stream_id);
     Ok(())
 }
@@ -692,16 +1095,84 @@ fn build_save_temp_path(target_path: &Path, stream_id: &str) -> Result<PathBuf,
     Ok(parent.join(temp_name))
 }
 
+/// FNV-1a, used instead of std's hasher because names built from it must
+/// stay the same across releases: recovery ids, for a snapshot to be found
+/// by the next session, and the prefix of a file's timestamped backups.
+pub(crate) fn stable_path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 #[tauri::command]
 fn start_save_file_stream(
     state: tauri::State<'_, LaunchArgState>,
+    watch_state: tauri::State<'_, file_watcher::FileWatchState>,
     file_path: String,
+    encoding: Option<String>,
+    write_bom: Option<bool>,
+    line_ending_policy: Option<line_endings::LineEndingPolicy>,
+    overwrite_external_changes: Option<bool>,
+) -> Result<SaveFileStreamStartResult, String> {
+    // The frontend asks before saving over a diverged file; this catches a
+    // change that lands between that check and the save.
+    if file_path != stdio::STDIO_ARG
+        && !overwrite_external_changes.unwrap_or(false)
+        && watch_state.disk_status(Path::new(&file_path))? == file_watcher::FileDiskStatus::Modified
+    {
+        return Err(format!(
+            "'{}' was changed by another program since it was opened",
+            file_path
+        ));
+    }
+
+    let encoding = match encoding.as_deref() {
+        Some(label) => text_encoding::resolve_encoding(label)?,
+        None => encoding_rs::UTF_8,
+    };
+
+    begin_save_stream(
+        &state,
+        file_path,
+        encoding,
+        write_bom.unwrap_or(false),
+        line_ending_policy.unwrap_or_default(),
+        false,
+    )
+}
+
+/// Creates the temp file a save stream writes to, next to the target so the
+/// finished file can be renamed over it.
+fn begin_save_stream(
+    state: &LaunchArgState,
+    file_path: String,
+    encoding: &'static encoding_rs::Encoding,
+    write_bom: bool,
+    line_ending_policy: line_endings::LineEndingPolicy,
+    export: bool,
 ) -> Result<SaveFileStreamStartResult, String> {
     if file_path.trim().is_empty() {
         return Err("Save path cannot be empty".to_string());
     }
 
-    let target_path = PathBuf::from(&file_path);
+    let to_stdout = !export && file_path == stdio::STDIO_ARG;
+    let stdout_spool = if to_stdout {
+        Some(
+            state
+                .stdout_spool
+                .clone()
+                .ok_or_else(|| format!("wisty was not started with {}", stdio::STDOUT_FLAG))?,
+        )
+    } else {
+        None
+    };
+
+    // Saving through a symlink replaces the file it points at, not the link.
+    let target_path = match stdout_spool {
+        Some(spool_path) => spool_path,
+        None => file_metadata::resolve_save_target(Path::new(&file_path))?,
+    };
+    let write_in_place = file_metadata::has_other_links(&target_path)?;
 
     let stream_id = {
         let mut counter = state
@@ -713,47 +1184,77 @@ fn start_save_file_stream(
     };
 
     let temp_path = build_save_temp_path(&target_path, &stream_id)?;
-    #[cfg(unix)]
-    let existing_mode = match std::fs::metadata(&target_path) {
-        Ok(metadata) => Some(metadata.permissions().mode()),
-        Err(error) if error.kind() == ErrorKind::NotFound => None,
+
+    // An in-place write also needs the target itself to be writable.
+    let created = if write_in_place {
+        OpenOptions::new().write(true).open(&target_path).map(drop)
+    } else {
+        Ok(())
+    }
+    .and_then(|()| {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+    });
+
+    // Without write access the content is staged privately and handed to the
+    // privileged helper when the stream finishes, once the user agrees.
+    // Exports are written in one go with nobody to ask, so they just fail.
+    let (temp_path, file, requires_elevation) = match created {
+        Ok(file) => (temp_path, file, false),
+        Err(error) if error.kind() == ErrorKind::PermissionDenied && !export => {
+            let (staging_path, file) =
+                privileged_save::create_staging_file(&target_path, &stream_id)?;
+            (staging_path, file, true)
+        }
         Err(error) => {
             return Err(format!(
-                "Unable to read permissions for '{}': {error}",
-                target_path.to_string_lossy()
-            ));
-        }
-    };
-
-    let file = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(&temp_path)
-        .map_err(|error| {
-            format!(
                 "Unable to create temporary save file '{}': {error}",
                 temp_path.to_string_lossy()
-            )
-        })?;
+            ))
+        }
+    };
 
-    #[cfg(unix)]
-    if let Some(mode) = existing_mode {
-        if let Err(error) =
-            std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(mode))
-        {
+    // A file written in place keeps its own owner and attributes, and the
+    // helper copies them for an elevated save.
+    if !write_in_place && !requires_elevation {
+        if let Err(error) = file_metadata::copy_metadata(&target_path, &temp_path) {
+            drop(file);
             let _ = std::fs::remove_file(&temp_path);
-            return Err(format!(
-                "Unable to copy permissions to temporary save file '{}': {error}",
-                temp_path.to_string_lossy()
-            ));
+            return Err(error);
         }
     }
 
+    let bom = if write_bom {
+        text_encoding::bom_bytes(encoding)
+    } else {
+        &[]
+    };
+    let mut content_hash = file_watcher::ContentHasher::new();
+    content_hash.update(bom);
+    let mut writer = BufWriter::new(file);
+    if let Err(error) = writer.write_all(bom) {
+        drop(writer);
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!(
+            "Unable to write byte-order mark to temporary save file '{}': {error}",
+            temp_path.to_string_lossy()
+        ));
+    }
+
     let stream = SaveFileStream {
         target_path,
         temp_path,
-        writer: BufWriter::new(file),
-        bytes_written_total: 0,
+        writer,
+        encoder: text_encoding::StreamEncoder::new(encoding),
+        line_endings: line_endings::LineEndingNormalizer::new(line_ending_policy),
+        bytes_written_total: bom.len() as u64,
+        content_hash,
+        write_in_place,
+        requires_elevation,
+        to_stdout,
+        export,
     };
 
     {
@@ -767,6 +1268,9 @@ fn start_save_file_stream(
     Ok(SaveFileStreamStartResult {
         stream_id,
         file_path,
+        encoding: encoding.name().to_string(),
+        has_bom: !bom.is_empty(),
+        requires_elevation,
     })
 }
 
@@ -785,7 +1289,15 @@ fn write_save_file_chunk(
         .get_mut(&stream_id)
         .ok_or_else(|| format!("Save stream '{}' not found", stream_id))?;
 
-    let bytes = text_chunk.as_bytes();
+    let normalized = stream.line_endings.apply(&text_chunk, false);
+    let bytes = stream.encoder.encode(&normalized, false)?;
+    write_save_stream_bytes(stream, &bytes)
+}
+
+fn write_save_stream_bytes(
+    stream: &mut SaveFileStream,
+    bytes: &[u8],
+) -> Result<SaveFileStreamWriteResult, String> {
     stream.writer.write_all(bytes).map_err(|error| {
         format!(
             "Unable to write save chunk for '{}': {error}",
@@ -794,27 +1306,62 @@ fn write_save_file_chunk(
     })?;
 
     stream.bytes_written_total += bytes.len() as u64;
+    stream.content_hash.update(bytes);
 
     Ok(SaveFileStreamWriteResult {
         bytes_written_total: stream.bytes_written_total,
     })
 }
 
+/// Runs as an async command because an elevated save waits for the user to
+/// answer the authentication prompt, which must not block the main thread.
 #[tauri::command]
-fn finish_save_file_stream(
-    state: tauri::State<'_, LaunchArgState>,
+async fn finish_save_file_stream(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
     stream_id: String,
 ) -> Result<SaveFileStreamFinishResult, String> {
+    finish_save_stream(&app, window.label(), &stream_id)
+}
+
+/// Finishes a save stream on behalf of `window`, which watches the saved
+/// file from then on.
+fn finish_save_stream(
+    app: &tauri::AppHandle,
+    window: &str,
+    stream_id: &str,
+) -> Result<SaveFileStreamFinishResult, String> {
+    let state = app.state::<LaunchArgState>();
+    let watch_state = app.state::<file_watcher::FileWatchState>();
+    let waits = app.state::<launch_wait::LaunchWaits>();
     let mut stream = {
         let mut streams = state
             .active_save_streams
             .lock()
             .map_err(|error| format!("Unable to finalize save stream state: {error}"))?;
         streams
-            .remove(&stream_id)
+            .remove(stream_id)
             .ok_or_else(|| format!("Save stream '{}' not found", stream_id))?
     };
 
+    let pending_line_ending = stream.line_endings.apply("", true);
+    let trailing = match stream.encoder.encode(&pending_line_ending, true) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            let _ = std::fs::remove_file(&stream.temp_path);
+            return Err(error);
+        }
+    };
+    if let Err(error) = stream.writer.write_all(&trailing) {
+        let _ = std::fs::remove_file(&stream.temp_path);
+        return Err(format!(
+            "Unable to write save chunk for '{}': {error}",
+            stream.target_path.to_string_lossy()
+        ));
+    }
+    stream.bytes_written_total += trailing.len() as u64;
+    stream.content_hash.update(&trailing);
+
     if let Err(error) = stream.writer.flush() {
         let _ = std::fs::remove_file(&stream.temp_path);
         return Err(format!(
@@ -833,14 +1380,103 @@ fn finish_save_file_stream(
         ));
     }
 
+    if stream.requires_elevation {
+        drop(stream.writer);
+        let saved = finish_elevated_save(
+            app,
+            window,
+            &watch_state,
+            &stream.target_path,
+            &stream.temp_path,
+            stream.content_hash.finish(),
+        );
+        let _ = std::fs::remove_file(&stream.temp_path);
+        saved?;
+        record_recent_file(app, &stream.target_path);
+        waits.mark_saved(&stream.target_path);
+        return Ok(SaveFileStreamFinishResult {
+            bytes_written_total: stream.bytes_written_total,
+        });
+    }
+
+    let backed_up = if stream.to_stdout || stream.export {
+        Ok(None)
+    } else {
+        backup::back_up_before_replace(
+            &stream.target_path,
+            &backup::BackupPolicy::from_settings(app),
+            std::time::SystemTime::now(),
+            !stream.write_in_place,
+        )
+    };
+    let backup_path = match backed_up {
+        Ok(backup_path) => backup_path,
+        Err(error) => {
+            drop(stream.writer);
+            let _ = std::fs::remove_file(&stream.temp_path);
+            return Err(error);
+        }
+    };
+
+    // Record the new fingerprint before the rename so the watcher recognizes
+    // the resulting event as our own write. Both the rename and the in-place
+    // copy leave the target with the temp file's mtime, so its metadata
+    // describes the saved file. Neither the stdout spool nor exports are
+    // watched.
+    let saved_fingerprint = stream
+        .writer
+        .get_ref()
+        .metadata()
+        .ok()
+        .filter(|_| !stream.to_stdout && !stream.export)
+        .map(|metadata| {
+            file_watcher::FileFingerprint::new(&metadata, stream.content_hash.finish())
+        });
+    let previous_fingerprint = watch_state.fingerprint(&stream.target_path);
+    if let Some(fingerprint) = saved_fingerprint {
+        watch_state.update_fingerprint(&stream.target_path, fingerprint);
+    }
+
     drop(stream.writer);
 
-    if let Err(error) = std::fs::rename(&stream.temp_path, &stream.target_path) {
-        let _ = std::fs::remove_file(&stream.temp_path);
-        return Err(format!(
-            "Unable to finalize save for '{}': {error}",
-            stream.target_path.to_string_lossy()
-        ));
+    let finalized = if stream.write_in_place {
+        file_metadata::write_in_place(&stream.temp_path, &stream.target_path)
+    } else {
+        std::fs::rename(&stream.temp_path, &stream.target_path).map_err(|error| {
+            let _ = std::fs::remove_file(&stream.temp_path);
+            format!(
+                "Unable to finalize save for '{}': {error}",
+                stream.target_path.to_string_lossy()
+            )
+        })
+    };
+    if let Err(error) = finalized {
+        // A backup linked to the target's inode would leave it with a second
+        // name, which the next save takes for a link to write through, over
+        // the backup. An in-place write may have got part way, so its backup
+        // (always a copy) is kept.
+        if let Some(backup_path) = backup_path.filter(|_| !stream.write_in_place) {
+            let _ = std::fs::remove_file(backup_path);
+        }
+        if saved_fingerprint.is_some() {
+            match previous_fingerprint {
+                Some(fingerprint) => {
+                    watch_state.update_fingerprint(&stream.target_path, fingerprint)
+                }
+                None => watch_state.forget_fingerprint(&stream.target_path),
+            }
+        }
+        return Err(error);
+    }
+
+    if let Some(fingerprint) = saved_fingerprint {
+        watch_state.track(app, window, &stream.target_path, fingerprint);
+    }
+    if !stream.export && !stream.to_stdout {
+        record_recent_file(app, &stream.target_path);
+    }
+    if !stream.export {
+        waits.mark_saved(&stream.target_path);
     }
 
     Ok(SaveFileStreamFinishResult {
@@ -848,6 +1484,52 @@ fn finish_save_file_stream(
     })
 }
 
+/// Installs a staged save through the privileged helper. The helper's write
+/// can't be fingerprinted in advance, so the target's fingerprint is dropped
+/// while it runs, silencing the watcher, and recorded again from the result.
+fn finish_elevated_save(
+    app: &tauri::AppHandle,
+    window: &str,
+    watch_state: &file_watcher::FileWatchState,
+    target: &Path,
+    staged: &Path,
+    content_hash: u64,
+) -> Result<(), String> {
+    let command = privileged_save::elevation_command()?;
+    let helper = std::env::current_exe()
+        .map_err(|error| format!("Unable to locate the wisty executable: {error}"))?;
+
+    // Backups usually sit next to the target and would need the same
+    // privileges, so a failed one doesn't hold up the save.
+    let backup_policy = backup::BackupPolicy::from_settings(app);
+    let backup_path =
+        backup::back_up_before_replace(target, &backup_policy, std::time::SystemTime::now(), true)
+            .unwrap_or_else(|error| {
+                log::warn!(target: "wisty::privileged_save", "{error}");
+                None
+            });
+
+    let previous_fingerprint = watch_state.fingerprint(target);
+    watch_state.forget_fingerprint(target);
+    let saved = privileged_save::save_elevated(&command, &helper, target, staged);
+    if saved.is_err() {
+        // As in an ordinary save, a backup may share the target's inode.
+        if let Some(backup_path) = backup_path {
+            let _ = std::fs::remove_file(backup_path);
+        }
+    }
+    let fingerprint = match saved {
+        Ok(()) => std::fs::metadata(target)
+            .ok()
+            .map(|metadata| file_watcher::FileFingerprint::new(&metadata, content_hash)),
+        Err(_) => previous_fingerprint,
+    };
+    if let Some(fingerprint) = fingerprint {
+        watch_state.track(app, window, target, fingerprint);
+    }
+    saved
+}
+
 #[tauri::command]
 fn cancel_save_file_stream(
     state: tauri::State<'_, LaunchArgState>,
@@ -878,9 +1560,355 @@ fn cancel_save_file_stream(
     Ok(())
 }
 
+/// Writes an exported file through a save stream, so an existing file is
+/// replaced atomically just as a save would replace it. A file open in a
+/// window is refused: exports bypass the conflict check and the watcher, and
+/// the window would be left showing text that is no longer on disk.
+fn write_export(
+    app: &tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    content: &[u8],
+) -> Result<SaveFileStreamFinishResult, String> {
+    let target_path = file_metadata::resolve_save_target(Path::new(&file_path))?;
+    if app
+        .state::<file_watcher::FileWatchState>()
+        .is_open(&target_path)
+    {
+        return Err(format!(
+            "'{file_path}' is open in wisty; export to a different file"
+        ));
+    }
+    let started = begin_save_stream(
+        &state,
+        file_path,
+        encoding_rs::UTF_8,
+        false,
+        line_endings::LineEndingPolicy::Keep,
+        true,
+    )?;
+    let stream_id = started.stream_id;
+    let written = state
+        .active_save_streams
+        .lock()
+        .map_err(|error| format!("Unable to read save stream state: {error}"))
+        .and_then(|mut streams| {
+            let stream = streams
+                .get_mut(&stream_id)
+                .ok_or_else(|| format!("Save stream '{}' not found", stream_id))?;
+            write_save_stream_bytes(stream, content)
+        });
+    let exported = written.and_then(|_| finish_save_stream(app, window.label(), &stream_id));
+    if exported.is_err() {
+        let _ = cancel_save_file_stream(state, stream_id);
+    }
+    exported
+}
+
+/// Writes `text` to `file_path` as a standalone HTML page styled like the
+/// Formatted view. The exports are async commands so that rendering and
+/// writing a long document don't block the main thread.
+#[tauri::command]
+async fn export_html(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    text: String,
+    style: export::ExportStyle,
+) -> Result<SaveFileStreamFinishResult, String> {
+    let title = Path::new(&file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let html = export::html_document(&text, &title, &style);
+    write_export(&app, window, state, file_path, html.as_bytes())
+}
+
+/// Writes `text` to `file_path` as a PDF laid out like the Formatted view.
+#[tauri::command]
+async fn export_pdf(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    text: String,
+    style: export::ExportStyle,
+    page: Option<pdf_export::PageSetup>,
+) -> Result<SaveFileStreamFinishResult, String> {
+    let pdf = pdf_export::render_pdf(&text, &style, &page.unwrap_or_default())?;
+    write_export(&app, window, state, file_path, &pdf)
+}
+
+/// Writes `text` to `file_path` as an OpenDocument Text file.
+#[tauri::command]
+async fn export_odt(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    text: String,
+    style: export::ExportStyle,
+) -> Result<SaveFileStreamFinishResult, String> {
+    let odt = office_export::odt_document(&text, &style)?;
+    write_export(&app, window, state, file_path, &odt)
+}
+
+/// Writes `text` to `file_path` as a Word document.
+#[tauri::command]
+async fn export_docx(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, LaunchArgState>,
+    file_path: String,
+    text: String,
+    style: export::ExportStyle,
+) -> Result<SaveFileStreamFinishResult, String> {
+    let docx = office_export::docx_document(&text, &style)?;
+    write_export(&app, window, state, file_path, &docx)
+}
+
+/// Shows the GTK print dialog for `text`, laid out like the Formatted view,
+/// and returns whether it was printed.
+#[tauri::command]
+fn print_document(
+    app: tauri::AppHandle,
+    text: String,
+    style: export::ExportStyle,
+    title: String,
+) -> Result<bool, String> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel::<Result<bool, String>>();
+
+        app.run_on_main_thread(move || {
+            if !gtk::is_initialized() {
+                if let Err(error) = gtk::init() {
+                    let _ = tx.send(Err(format!("Unable to start GTK: {error}")));
+                    return;
+                }
+            }
+            let _ = tx.send(print::print_with_dialog(print::PrintJob {
+                text,
+                style,
+                title,
+            }));
+        })
+        .map_err(|error| error.to_string())?;
+
+        rx.recv().map_err(|error| error.to_string())?
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (app, text, style, title);
+        Ok(false)
+    }
+}
+
+/// Opens another editor window like the one in `tauri.conf.json`. Once its
+/// frontend has loaded it takes the next pending launch file.
+fn open_document_window(app: &tauri::AppHandle) -> Result<(), String> {
+    let label = {
+        let state = app.state::<LaunchArgState>();
+        let mut counter = state
+            .document_window_counter
+            .lock()
+            .map_err(|error| format!("Unable to allocate window label: {error}"))?;
+        *counter += 1;
+        format!("{DOCUMENT_WINDOW_LABEL_PREFIX}{}", *counter)
+    };
+    let state = app.state::<LaunchArgState>();
+    set_window_loading(&state, &label, true);
+    tauri::WebviewWindowBuilder::new(app, &label, tauri::WebviewUrl::default())
+        .title("wisty")
+        .inner_size(800.0, 600.0)
+        .build()
+        .map(|_| ())
+        .map_err(|error| {
+            set_window_loading(&state, &label, false);
+            format!("Unable to open window: {error}")
+        })
+}
+
+fn set_window_loading(state: &LaunchArgState, label: &str, loading: bool) {
+    if let Ok(mut loading_windows) = state.loading_windows.lock() {
+        if loading {
+            loading_windows.insert(label.to_string());
+        } else {
+            loading_windows.remove(label);
+        }
+    }
+}
+
+/// Opens a window for every queued launch file that no loading window will
+/// take. The frontend calls this for forwarded files its own window has no
+/// room for.
+#[tauri::command]
+async fn open_pending_launch_windows(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, LaunchArgState>,
+) -> Result<(), String> {
+    let missing_count = {
+        let pending = state
+            .pending_files
+            .lock()
+            .map_err(|error| format!("Unable to read launch args state: {error}"))?;
+        let loading = state
+            .loading_windows
+            .lock()
+            .map_err(|error| format!("Unable to read launch args state: {error}"))?;
+        pending.len().saturating_sub(loading.len())
+    };
+    for _ in 0..missing_count {
+        open_document_window(&app)?;
+    }
+    Ok(())
+}
+
+/// The window forwarded files are announced to: the main one while it is
+/// open, otherwise any.
+fn primary_window(app: &tauri::AppHandle) -> Option<tauri::WebviewWindow> {
+    app.get_webview_window(MAIN_WINDOW_LABEL)
+        .or_else(|| app.webview_windows().into_values().next())
+}
+
+/// Queues the files another `wisty` invocation forwarded and tells the
+/// primary window to open them. Without files, that invocation just asked
+/// for a new window. For `--wait`, returns what the invocation waits on.
+fn receive_forwarded_launch(
+    app: &tauri::AppHandle,
+    request: ForwardedLaunch,
+) -> Option<Receiver<bool>> {
+    if request.files.is_empty() {
+        if let Err(error) = open_document_window(app) {
+            log::warn!(target: "wisty::single_instance", "{error}");
+        }
+        return None;
+    }
+
+    let state = app.state::<LaunchArgState>();
+    let mut files = Vec::new();
+    for forwarded in request.files {
+        // Checked again: the sender is another process, and the file may
+        // have changed since it looked.
+        let validated = validate_launch_file_arg(Path::new(&forwarded.path)).and_then(|file| {
+            if file.exists {
+                state.approved_open_paths.approve(Path::new(&file.path))?;
+            }
+            Ok(LaunchFileArg {
+                position: forwarded.position,
+                options: forwarded.options,
+                ..file
+            })
+        });
+        match validated {
+            Ok(file) => files.push(file),
+            Err(error) => log::warn!(target: "wisty::single_instance", "{error}"),
+        }
+    }
+
+    let queued_count = files.len();
+    let wait = {
+        let mut pending = match state.pending_files.lock() {
+            Ok(pending) => pending,
+            Err(error) => {
+                log::warn!(
+                    target: "wisty::single_instance",
+                    "Unable to update launch args state: {error}"
+                );
+                return None;
+            }
+        };
+        let wait = request.wait.then(|| {
+            let paths: Vec<PathBuf> = files.iter().map(|file| PathBuf::from(&file.path)).collect();
+            let (wait_ids, receiver) = app.state::<launch_wait::LaunchWaits>().begin(&paths);
+            for (file, wait_id) in files.iter_mut().zip(wait_ids) {
+                file.wait_id = Some(wait_id);
+            }
+            receiver
+        });
+        pending.extend(files);
+        wait
+    };
+
+    if let Some(window) = primary_window(app) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        let _ = app.emit_to(
+            window.label(),
+            single_instance::OPEN_FILES_EVENT,
+            queued_count,
+        );
+    }
+    wait
+}
+
+/// Runs `wisty convert`: renders a Markdown document without starting the
+/// app, for scripts that want the Formatted view's dialect.
+fn run_convert(options: &cli::ConvertOptions) -> Result<(), String> {
+    let bytes = match options.input.as_deref() {
+        None | Some(stdio::STDIO_ARG) => stdio::read_stdin(CLI_HARD_LIMIT_BYTES)?,
+        Some(raw) => {
+            let path = normalize_cli_path(raw)?;
+            std::fs::read(&path)
+                .map_err(|error| format!("Unable to read '{}': {error}", path.to_string_lossy()))?
+        }
+    };
+    let text = text_encoding::decode_document(&bytes)?;
+    let text = line_endings::LineEndingNormalizer::new(line_endings::LineEndingPolicy::Lf)
+        .apply(&text, true);
+    let output = match options.format {
+        cli::ConvertFormat::Html => markdown::to_html(&text),
+        cli::ConvertFormat::Text => markdown::to_plain_text(&text),
+    };
+
+    match options.output.as_deref() {
+        None | Some(stdio::STDIO_ARG) => std::io::stdout()
+            .lock()
+            .write_all(output.as_bytes())
+            .map_err(|error| format!("Unable to write to standard output: {error}")),
+        Some(raw) => {
+            let path = normalize_cli_path(raw)?;
+            std::fs::write(&path, output)
+                .map_err(|error| format!("Unable to write '{}': {error}", path.to_string_lossy()))
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let launch_file = match resolve_launch_file_arg() {
+    if let Some(exit_code) = privileged_save::run_helper_if_requested() {
+        std::process::exit(exit_code);
+    }
+
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Cli::Launch(options)) => options,
+        Ok(cli::Cli::Convert(options)) => match run_convert(&options) {
+            Ok(()) => std::process::exit(0),
+            Err(error) => {
+                eprintln!("wisty: {error}");
+                std::process::exit(1);
+            }
+        },
+        Ok(cli::Cli::Help) => {
+            println!("{}", cli::help_text());
+            std::process::exit(0);
+        }
+        Ok(cli::Cli::Version) => {
+            println!("{}", cli::version_text());
+            std::process::exit(0);
+        }
+        Err(error) => {
+            eprintln!("wisty: {error}\n{}", cli::error_hint());
+            std::process::exit(2);
+        }
+    };
+
+    let mut launch_args = match resolve_launch_args(&options) {
         Ok(value) => value,
         Err(error) => {
             eprintln!("wisty: {error}");
@@ -888,8 +1916,72 @@ pub fn run() {
         }
     };
 
+    // Standard input and output belong to this process, so pipelines always
+    // get an instance of their own.
+    let single_instance_socket = if options.new_instance
+        || launch_args.stdin_spool.is_some()
+        || launch_args.stdout_spool.is_some()
+    {
+        None
+    } else {
+        single_instance::socket_path()
+            .map_err(|error| eprintln!("wisty: {error}"))
+            .ok()
+    };
+    if let Some(socket_path) = &single_instance_socket {
+        let request = ForwardedLaunch {
+            files: launch_args.files.iter().cloned().collect(),
+            wait: options.wait,
+        };
+        match single_instance::forward(socket_path, &request) {
+            Ok(Some(forwarded)) if options.wait => {
+                let all_saved = forwarded.wait_for_close().unwrap_or_else(|error| {
+                    eprintln!("wisty: {error}");
+                    false
+                });
+                std::process::exit(launch_wait::exit_code(all_saved, options.wait_require_save));
+            }
+            Ok(Some(_)) => std::process::exit(0),
+            Ok(None) => {}
+            // An instance is running but didn't take the files; a second
+            // one would fight it for the socket and the settings.
+            Err(error) => {
+                eprintln!("wisty: {error}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Waiting on an instance of our own: it doesn't take forwarded files, so
+    // nothing but our documents keeps the caller blocked.
+    let launch_waits = launch_wait::LaunchWaits::default();
+    let local_wait = options.wait.then(|| {
+        let paths: Vec<PathBuf> = launch_args
+            .files
+            .iter()
+            .map(|file| PathBuf::from(&file.path))
+            .collect();
+        let (wait_ids, receiver) = launch_waits.begin(&paths);
+        for (file, wait_id) in launch_args.files.iter_mut().zip(wait_ids) {
+            file.wait_id = Some(wait_id);
+        }
+        launch_wait::LocalWait {
+            receiver: Mutex::new(receiver),
+            require_save: options.wait_require_save,
+        }
+    });
+    let single_instance_socket = single_instance_socket.filter(|_| local_wait.is_none());
+
+    // The window from tauri.conf.json takes the first file; every further
+    // file gets a window of its own.
+    let extra_windows = launch_args.files.len().saturating_sub(1);
+
     let is_debug_build = cfg!(debug_assertions);
+    // Not stdout, the default: with `--stdout` it carries the document.
     let log_plugin = tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::Stderr))
+        .target(Target::new(TargetKind::LogDir { file_name: None }))
         .level(if is_debug_build {
             LevelFilter::Info
         } else {
@@ -906,37 +1998,156 @@ pub fn run() {
         .build();
 
     tauri::Builder::default()
-        .manage(LaunchArgState::new(launch_file))
+        .manage(LaunchArgState::new(launch_args))
         .manage(spellcheck::SpellState::default())
+        .manage(recent_files::RecentFiles::default())
+        .manage(file_watcher::FileWatchState::default())
+        .manage(recovery::RecoveryState::default())
+        .manage(document_stats::DocumentStatsState::default())
+        .manage(launch_waits)
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(log_plugin)
+        .setup(move |app| {
+            for _ in 0..extra_windows {
+                open_document_window(app.handle())?;
+            }
+
+            if let Some(local_wait) = local_wait {
+                app.manage(local_wait);
+            }
+
+            if let Some(socket_path) = single_instance_socket {
+                let app_handle = app.handle().clone();
+                let listening = single_instance::listen(&socket_path, move |request| {
+                    receive_forwarded_launch(&app_handle, request)
+                });
+                match listening {
+                    Ok(true) => {
+                        app.manage(single_instance::ListeningSocket(socket_path));
+                    }
+                    Ok(false) => {}
+                    Err(error) => log::warn!(target: "wisty::single_instance", "{error}"),
+                }
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             choose_editor_font,
             take_launch_file_arg,
-            start_launch_file_stream,
-            read_launch_file_chunk,
-            cancel_launch_file_stream,
-            close_launch_file_stream,
+            release_launch_wait,
+            launch_writes_to_stdout,
+            open_pending_launch_windows,
+            pick_open_file,
+            start_open_file_stream,
+            start_stdin_stream,
+            read_open_file_chunk,
+            cancel_open_file_stream,
+            close_open_file_stream,
             start_save_file_stream,
             write_save_file_chunk,
             finish_save_file_stream,
             cancel_save_file_stream,
+            export_html,
+            export_pdf,
+            export_odt,
+            export_docx,
+            print_document,
+            document_stats,
+            document_stats::start_document_stats_stream,
+            document_stats::write_document_stats_chunk,
+            document_stats::finish_document_stats_stream,
+            document_stats::cancel_document_stats_stream,
+            file_watcher::unwatch_file,
+            file_watcher::check_file_on_disk,
+            recovery::start_recovery_snapshot,
+            recovery::write_recovery_snapshot_chunk,
+            recovery::finish_recovery_snapshot,
+            recovery::cancel_recovery_snapshot,
+            recovery::list_recoverable_documents,
+            recovery::discard_recovery_snapshot,
             window_title::set_window_title,
             spellcheck::spell_list_dictionaries,
+            spellcheck::spell_detect_language,
             spellcheck::spell_load_dictionary,
             spellcheck::spell_check_words,
             spellcheck::spell_suggest,
             spellcheck::spell_add_word,
             spellcheck::spell_ignore_word,
             spellcheck::spell_list_added_words,
-            spellcheck::spell_remove_word
+            spellcheck::spell_remove_word,
+            spellcheck::spell_install_dictionary,
+            spellcheck::spell_uninstall_dictionary,
+            spell_report_for_file,
+            spell_report::spell_report_for_text
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Destroyed,
+                ..
+            } => {
+                app.state::<launch_wait::LaunchWaits>()
+                    .release_window(&label);
+                app.state::<file_watcher::FileWatchState>()
+                    .release_window(&label);
+                set_window_loading(&app.state::<LaunchArgState>(), &label, false);
+            }
+            tauri::RunEvent::Exit => {
+                let state = app.state::<LaunchArgState>();
+                if let Some(spool_path) = &state.stdout_spool {
+                    stdio::flush_stdout_spool(spool_path);
+                }
+                // Standard input that no window took is removed with its spool.
+                if let Ok(mut pending_stdin) = state.pending_stdin.lock() {
+                    pending_stdin.take();
+                }
+                if let Some(socket) = app.try_state::<single_instance::ListeningSocket>() {
+                    single_instance::remove_socket(&socket.0);
+                }
+                // Forwarded waiters are told through the socket as their
+                // documents close; a local one gets the exit status.
+                app.state::<launch_wait::LaunchWaits>().release_all();
+                if let Some(local_wait) = app.try_state::<launch_wait::LocalWait>() {
+                    let all_saved = local_wait
+                        .receiver
+                        .lock()
+                        .is_ok_and(|receiver| receiver.try_recv() == Ok(true));
+                    let exit_code = launch_wait::exit_code(all_saved, local_wait.require_save);
+                    if exit_code != 0 {
+                        std::process::exit(exit_code);
+                    }
+                }
+            }
+            _ => {}
+        });
 }
+mod backup;
+mod cli;
+mod dictionary_package;
+mod document_stats;
+mod export;
+mod file_metadata;
+mod file_watcher;
+mod launch_position;
+mod launch_wait;
+mod line_endings;
+mod markdown;
+mod office_export;
+mod paged_layout;
+mod pdf_export;
+mod print;
+mod privileged_save;
+mod recent_files;
+mod recovery;
+mod single_instance;
+mod spell_report;
 mod spellcheck;
+mod stdio;
+mod text_encoding;
 mod window_title;
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+/// Line-ending style found in a document.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+    /// The document has no line breaks at all.
+    None,
+}
+
+/// How the save stream rewrites line breaks as chunks are written.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEndingPolicy {
+    /// Write the text exactly as received, for exports and saves that don't
+    /// ask for a policy. Not accepted from the frontend: the editor always
+    /// sends `\n`, so keeping a file's own line breaks takes `Crlf` or `Cr`.
+    #[default]
+    #[serde(skip_deserializing)]
+    Keep,
+    Lf,
+    Crlf,
+    /// Classic Mac OS line breaks, a lone `\r`.
+    Cr,
+}
+
+/// Classifies the line breaks in `text`. When `is_complete` is false the text
+/// is only a prefix of the document, so a trailing `\r` that may be the first
+/// half of a `\r\n` split by the sample boundary is ignored.
+pub fn detect_line_ending(text: &str, is_complete: bool) -> LineEnding {
+    let bytes = text.as_bytes();
+    let mut lf = 0_usize;
+    let mut crlf = 0_usize;
+    let mut cr = 0_usize;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\n' => lf += 1,
+            b'\r' if bytes.get(index + 1) == Some(&b'\n') => {
+                crlf += 1;
+                index += 1;
+            }
+            b'\r' if index + 1 == bytes.len() && !is_complete => {}
+            b'\r' => cr += 1,
+            _ => {}
+        }
+        index += 1;
+    }
+
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => LineEnding::None,
+        (true, false, false) => LineEnding::Lf,
+        (false, true, false) => LineEnding::Crlf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Applies a `LineEndingPolicy` to text arriving in arbitrary chunks. A `\r`
+/// at the end of one chunk is held back until the next chunk shows whether it
+/// starts a `\r\n` pair.
+pub struct LineEndingNormalizer {
+    policy: LineEndingPolicy,
+    pending_cr: bool,
+}
+
+impl LineEndingNormalizer {
+    pub fn new(policy: LineEndingPolicy) -> Self {
+        Self {
+            policy,
+            pending_cr: false,
+        }
+    }
+
+    /// Normalizes the next chunk. Pass `last` for the final call so a held
+    /// back `\r` is flushed.
+    pub fn apply(&mut self, text: &str, last: bool) -> String {
+        let newline = match self.policy {
+            LineEndingPolicy::Keep => return text.to_string(),
+            LineEndingPolicy::Lf => "\n",
+            LineEndingPolicy::Crlf => "\r\n",
+            LineEndingPolicy::Cr => "\r",
+        };
+
+        let mut output = String::with_capacity(text.len() + text.len() / 16);
+        let mut chars = text.chars().peekable();
+
+        if self.pending_cr {
+            self.pending_cr = false;
+            if chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            output.push_str(newline);
+        }
+
+        while let Some(character) = chars.next() {
+            match character {
+                '\r' => match chars.peek() {
+                    Some('\n') => {
+                        chars.next();
+                        output.push_str(newline);
+                    }
+                    Some(_) => output.push_str(newline),
+                    None if last => output.push_str(newline),
+                    None => self.pending_cr = true,
+                },
+                '\n' => output.push_str(newline),
+                other => output.push(other),
+            }
+        }
+
+        if last && self.pending_cr {
+            self.pending_cr = false;
+            output.push_str(newline);
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize_in_chunks(text: &str, chunk_chars: usize, policy: LineEndingPolicy) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut normalizer = LineEndingNormalizer::new(policy);
+        let mut output = String::new();
+        for chunk in chars.chunks(chunk_chars) {
+            output.push_str(&normalizer.apply(&chunk.iter().collect::<String>(), false));
+        }
+        output.push_str(&normalizer.apply("", true));
+        output
+    }
+
+    #[test]
+    fn detects_line_ending_styles() {
+        assert_eq!(detect_line_ending("one line", true), LineEnding::None);
+        assert_eq!(detect_line_ending("a\nb\n", true), LineEnding::Lf);
+        assert_eq!(detect_line_ending("a\r\nb\r\n", true), LineEnding::Crlf);
+        assert_eq!(detect_line_ending("a\rb\r", true), LineEnding::Cr);
+        assert_eq!(detect_line_ending("a\r\nb\n", true), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn ignores_trailing_cr_of_partial_sample() {
+        assert_eq!(detect_line_ending("a\r\nb\r", false), LineEnding::Crlf);
+        assert_eq!(detect_line_ending("a\r\nb\r", true), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn converts_across_chunk_boundaries() {
+        let text = "one\r\ntwo\nthree\rfour\r\n";
+        for chunk_chars in [1, 2, 3, 4, 64] {
+            assert_eq!(
+                normalize_in_chunks(text, chunk_chars, LineEndingPolicy::Lf),
+                "one\ntwo\nthree\nfour\n"
+            );
+            assert_eq!(
+                normalize_in_chunks(text, chunk_chars, LineEndingPolicy::Crlf),
+                "one\r\ntwo\r\nthree\r\nfour\r\n"
+            );
+            assert_eq!(
+                normalize_in_chunks(text, chunk_chars, LineEndingPolicy::Cr),
+                "one\rtwo\rthree\rfour\r"
+            );
+            assert_eq!(
+                normalize_in_chunks(text, chunk_chars, LineEndingPolicy::Keep),
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn flushes_trailing_cr() {
+        assert_eq!(
+            normalize_in_chunks("end\r", 4, LineEndingPolicy::Crlf),
+            "end\r\n"
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! The Markdown subset the editor's Formatted view recognises, for output
+//! that must look like it: bold, italic and ATX headings, matched exactly as
+//! `formatExtension.ts` matches them.
+
+use std::ops::Range;
+
+/// Longest run of content characters an emphasis span may contain, as in
+/// `EMPHASIS_MAX_LENGTH` on the frontend.
+const EMPHASIS_MAX_LENGTH: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emphasis {
+    Bold,
+    Italic,
+}
+
+/// An emphasis span: byte ranges of the opening delimiter, the content and
+/// the closing delimiter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmphasisSpan {
+    pub emphasis: Emphasis,
+    pub open: Range<usize>,
+    pub content: Range<usize>,
+    pub close: Range<usize>,
+}
+
+/// The level and prefix length (`## ` is 3 bytes) of an ATX heading line.
+pub fn heading_prefix(line: &str) -> Option<(usize, usize)> {
+    let level = line.bytes().take_while(|byte| *byte == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let spaces = line[level..]
+        .bytes()
+        .take_while(|byte| *byte == b' ' || *byte == b'\t')
+        .count();
+    (spaces > 0).then_some((level, level + spaces))
+}
+
+fn is_word_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// Matches `delimiter content delimiter` at `start`, where content is
+/// 1..=EMPHASIS_MAX_LENGTH characters that are neither `stop` nor (for
+/// single-line emphasis) a newline, and neither starts nor ends with
+/// whitespace. The content can't contain the delimiter's character, so the
+/// first one after `start` is the only possible close.
+fn match_delimited(
+    text: &str,
+    start: usize,
+    delimiter: &str,
+    stop: char,
+    single_line: bool,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let content_start = start + delimiter.len();
+    if !text[start..].starts_with(delimiter) {
+        return None;
+    }
+
+    let mut content_end = content_start;
+    let mut last = None;
+    for (count, (offset, character)) in text[content_start..].char_indices().enumerate() {
+        if character == stop {
+            content_end = content_start + offset;
+            break;
+        }
+        if (single_line && character == '\n') || count == EMPHASIS_MAX_LENGTH {
+            return None;
+        }
+        if count == 0 && character.is_whitespace() {
+            return None;
+        }
+        last = Some(character);
+        content_end = content_start + offset + character.len_utf8();
+    }
+
+    let last = last?;
+    if last.is_whitespace() || !text[content_end..].starts_with(delimiter) {
+        return None;
+    }
+    Some((
+        content_start..content_end,
+        content_end..content_end + delimiter.len(),
+    ))
+}
+
+fn match_emphasis_at(text: &str, start: usize) -> Option<EmphasisSpan> {
+    // Bold is tried before italic so `**x**` isn't read as two stray `*`.
+    if let Some((content, close)) = match_delimited(text, start, "**", '*', false) {
+        return Some(EmphasisSpan {
+            emphasis: Emphasis::Bold,
+            open: start..start + 2,
+            content,
+            close,
+        });
+    }
+    if let Some((content, close)) = match_delimited(text, start, "*", '*', true) {
+        return Some(EmphasisSpan {
+            emphasis: Emphasis::Italic,
+            open: start..start + 1,
+            content,
+            close,
+        });
+    }
+
+    // Underscores only count at word boundaries, so `snake_case` is left alone.
+    let after_word = text[..start].chars().next_back().is_some_and(is_word_char);
+    if after_word {
+        return None;
+    }
+    let (content, close) = match_delimited(text, start, "_", '_', true)?;
+    if text[close.end..].chars().next().is_some_and(is_word_char) {
+        return None;
+    }
+    Some(EmphasisSpan {
+        emphasis: Emphasis::Italic,
+        open: start..start + 1,
+        content,
+        close,
+    })
+}
+
+/// Every emphasis span in `text`, left to right and non-overlapping. Bold may
+/// cross line breaks; italic stays on one line.
+pub fn emphasis_spans(text: &str) -> Vec<EmphasisSpan> {
+    let mut spans = Vec::new();
+    let mut position = 0;
+    while position < text.len() {
+        let next = text[position..]
+            .find(['*', '_'])
+            .map(|offset| position + offset);
+        let Some(start) = next else {
+            break;
+        };
+        match match_emphasis_at(text, start) {
+            Some(span) => {
+                position = span.close.end;
+                spans.push(span);
+            }
+            None => position = start + 1,
+        }
+    }
+    spans
+}
+
+/// A run of visible text on one line, with its emphasis.
+pub struct Piece<'a> {
+    pub text: &'a str,
+    pub emphasis: Option<Emphasis>,
+}
+
+pub struct Line<'a> {
+    pub heading_level: Option<usize>,
+    pub pieces: Vec<Piece<'a>>,
+}
+
+/// Splits `text` into lines of visible pieces: delimiters and heading
+/// prefixes dropped, as the Formatted view hides them.
+pub fn visible_lines(text: &str) -> Vec<Line<'_>> {
+    let mut runs: Vec<(Range<usize>, Option<Emphasis>)> = Vec::new();
+    let mut position = 0;
+    for span in emphasis_spans(text) {
+        runs.push((position..span.open.start, None));
+        runs.push((span.content, Some(span.emphasis)));
+        position = span.close.end;
+    }
+    runs.push((position..text.len(), None));
+
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut first_run = 0;
+    for line_text in text.split('\n') {
+        let line_end = line_start + line_text.len();
+        let heading = heading_prefix(line_text);
+        let visible_start = line_start + heading.map_or(0, |(_, length)| length);
+
+        // Runs are in order, so earlier lines' runs never need looking at again.
+        while runs
+            .get(first_run)
+            .is_some_and(|(range, _)| range.end <= line_start)
+        {
+            first_run += 1;
+        }
+        let pieces = runs[first_run..]
+            .iter()
+            .take_while(|(range, _)| range.start < line_end)
+            .filter_map(|(range, emphasis)| {
+                let from = range.start.max(visible_start);
+                let to = range.end.min(line_end);
+                (from < to).then(|| Piece {
+                    text: &text[from..to],
+                    emphasis: *emphasis,
+                })
+            })
+            .collect();
+        lines.push(Line {
+            heading_level: heading.map(|(level, _)| level),
+            pieces,
+        });
+        line_start = line_end + 1;
+    }
+    lines
+}
+
+/// The text with the markup removed, line for line.
+pub fn to_plain_text(text: &str) -> String {
+    visible_lines(text)
+        .iter()
+        .map(|line| {
+            line.pieces
+                .iter()
+                .map(|piece| piece.text)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_html(text: &str, output: &mut String) {
+    for character in text.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            _ => output.push(character),
+        }
+    }
+}
+
+fn push_inline_html(line: &Line, output: &mut String) {
+    for piece in &line.pieces {
+        let tag = match piece.emphasis {
+            Some(Emphasis::Bold) => Some("strong"),
+            Some(Emphasis::Italic) => Some("em"),
+            None => None,
+        };
+        if let Some(tag) = tag {
+            output.push_str(&format!("<{tag}>"));
+        }
+        escape_html(piece.text, output);
+        if let Some(tag) = tag {
+            output.push_str(&format!("</{tag}>"));
+        }
+    }
+}
+
+/// A heading or paragraph of the document.
+pub enum Block<'a> {
+    Heading(usize, Line<'a>),
+    /// Consecutive non-blank lines, between blank lines or headings.
+    Paragraph(Vec<Line<'a>>),
+}
+
+/// Groups the visible lines into headings and paragraphs, dropping blank
+/// lines, the way HTML and office documents lay text out.
+pub fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut paragraph = Vec::new();
+    for line in visible_lines(text) {
+        let is_blank = line.pieces.iter().all(|piece| piece.text.trim().is_empty());
+        if !paragraph.is_empty() && (is_blank || line.heading_level.is_some()) {
+            blocks.push(Block::Paragraph(std::mem::take(&mut paragraph)));
+        }
+
+        if let Some(level) = line.heading_level {
+            blocks.push(Block::Heading(level, line));
+        } else if !is_blank {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(paragraph));
+    }
+    blocks
+}
+
+/// An HTML fragment: headings become `<h1>`..`<h6>`, runs of other lines
+/// separated by blank lines become paragraphs with `<br>` between lines.
+pub fn to_html(text: &str) -> String {
+    let mut output = String::new();
+    for block in blocks(text) {
+        match block {
+            Block::Heading(level, line) => {
+                output.push_str(&format!("<h{level}>"));
+                push_inline_html(&line, &mut output);
+                output.push_str(&format!("</h{level}>\n"));
+            }
+            Block::Paragraph(lines) => {
+                output.push_str("<p>");
+                for (index, line) in lines.iter().enumerate() {
+                    if index > 0 {
+                        output.push_str("<br>\n");
+                    }
+                    push_inline_html(line, &mut output);
+                }
+                output.push_str("</p>\n");
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emphasized(text: &str, emphasis: Emphasis) -> Vec<&str> {
+        emphasis_spans(text)
+            .into_iter()
+            .filter(|span| span.emphasis == emphasis)
+            .map(|span| &text[span.content])
+            .collect()
+    }
+
+    // The cases below pin this to the frontend's formatExtension.test.ts.
+
+    #[test]
+    fn hides_heading_markers() {
+        assert_eq!(heading_prefix("## Heading"), Some((2, 3)));
+        assert_eq!(to_plain_text("## Heading"), "Heading");
+        assert_eq!(to_html("## Heading"), "<h2>Heading</h2>\n");
+        assert_eq!(heading_prefix("####### seven"), None);
+        assert_eq!(heading_prefix("#hashtag"), None);
+    }
+
+    #[test]
+    fn hides_bold_and_italic_markers() {
+        let text = "**bold** and *italic* and _also italic_";
+        assert_eq!(emphasized(text, Emphasis::Bold), ["bold"]);
+        assert_eq!(
+            emphasized(text, Emphasis::Italic),
+            ["italic", "also italic"]
+        );
+        assert_eq!(to_plain_text(text), "bold and italic and also italic");
+        assert_eq!(
+            to_html(text),
+            "<p><strong>bold</strong> and <em>italic</em> and <em>also italic</em></p>\n"
+        );
+    }
+
+    #[test]
+    fn leaves_snake_case_alone() {
+        let text = "snake_case_name stays_put";
+        assert!(emphasis_spans(text).is_empty());
+        assert_eq!(to_plain_text(text), text);
+    }
+
+    #[test]
+    fn bold_spans_line_breaks() {
+        let text = "**bold\nstill bold** after";
+        assert_eq!(emphasized(text, Emphasis::Bold), ["bold\nstill bold"]);
+        assert_eq!(to_plain_text(text), "bold\nstill bold after");
+        assert_eq!(
+            to_html(text),
+            "<p><strong>bold</strong><br>\n<strong>still bold</strong> after</p>\n"
+        );
+    }
+
+    #[test]
+    fn bold_spans_blank_lines() {
+        assert_eq!(emphasized("**one\n\ntwo**", Emphasis::Bold), ["one\n\ntwo"]);
+    }
+
+    #[test]
+    fn keeps_italic_within_a_line() {
+        assert!(emphasis_spans("*one\ntwo* and _three\nfour_").is_empty());
+    }
+
+    #[test]
+    fn gives_up_on_spans_longer_than_the_cap() {
+        let text = format!("**{}**", "a".repeat(1001));
+        assert!(emphasis_spans(&text).is_empty());
+        let text = format!("**{}**", "é".repeat(1000));
+        assert_eq!(emphasis_spans(&text).len(), 1);
+    }
+
+    #[test]
+    fn star_bullets_are_not_emphasis() {
+        assert!(emphasis_spans("* first item\n* second item").is_empty());
+    }
+
+    #[test]
+    fn empty_runs_are_not_emphasis() {
+        assert!(emphasis_spans("****").is_empty());
+        assert_eq!(to_plain_text("****"), "****");
+    }
+
+    #[test]
+    fn html_separates_paragraphs_and_escapes() {
+        assert_eq!(
+            to_html("# Title\nfirst <line>\nsecond & last\n\nnext\n"),
+            "<h1>Title</h1>\n<p>first &lt;line&gt;<br>\nsecond &amp; last</p>\n<p>next</p>\n"
+        );
+    }
+}
@@ -0,0 +1,672 @@
+//! OpenDocument Text and Office Open XML (Word) packages for the Markdown
+//! subset the Formatted view shows: headings use the suites' own Heading 1-6
+//! styles and bold and italic become character formatting, so the documents
+//! stay editable rather than merely looking right.
+
+use crate::export::{self, ExportStyle};
+use crate::markdown::{self, Block, Emphasis, Line};
+use crate::paged_layout::POINTS_PER_PX;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const ODT_MIME_TYPE: &str = "application/vnd.oasis.opendocument.text";
+
+const ODF_NAMESPACES: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+office:version=\"1.3\"";
+
+const WORD_NAMESPACE: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// Escapes `text` for XML character data and attribute values, dropping
+/// control characters XML 1.0 cannot represent at all.
+fn escape_xml(text: &str, output: &mut String) {
+    for character in text.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' | '\n' | '\r' => output.push(character),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => {}
+            _ => output.push(character),
+        }
+    }
+}
+
+fn xml_text(text: &str) -> String {
+    let mut output = String::new();
+    escape_xml(text, &mut output);
+    output
+}
+
+/// The first family of a CSS font list, unquoted: office suites take one
+/// family name, not a fallback list.
+fn primary_family(font_family: &str) -> String {
+    let family = font_family
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .trim_matches(['"', '\'']);
+    if family.is_empty() {
+        "sans-serif".to_string()
+    } else {
+        family.to_string()
+    }
+}
+
+fn body_points(style: &ExportStyle) -> f64 {
+    style.font_size_px() * POINTS_PER_PX
+}
+
+fn is_bold(style: &ExportStyle) -> bool {
+    style.font_weight >= 600
+}
+
+fn is_italic(style: &ExportStyle) -> bool {
+    matches!(style.font_style.as_str(), "italic" | "oblique")
+}
+
+/// Rounds to two decimals so lengths read cleanly in the XML.
+fn points(value: f64) -> String {
+    format!("{}pt", (value * 100.0).round() / 100.0)
+}
+
+fn zip_package(parts: &[(&str, String)]) -> Result<Vec<u8>, String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in parts {
+        // ODF requires `mimetype` first and uncompressed, so that the type
+        // can be read at a fixed offset.
+        let method = if *name == "mimetype" {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        writer
+            .start_file(
+                *name,
+                SimpleFileOptions::default().compression_method(method),
+            )
+            .and_then(|_| writer.write_all(content.as_bytes()).map_err(Into::into))
+            .map_err(|error| format!("Unable to write {name}: {error}"))?;
+    }
+    writer
+        .finish()
+        .map(Cursor::into_inner)
+        .map_err(|error| format!("Unable to finish document: {error}"))
+}
+
+/// Appends a line of ODF text: runs of spaces and tabs need elements of
+/// their own or readers collapse them, as HTML would.
+fn push_odt_text(text: &str, at_line_start: &mut bool, output: &mut String) {
+    let mut spaces = 0;
+    let flush = |spaces: &mut usize, at_line_start: bool, output: &mut String| {
+        let mut count = *spaces;
+        if count > 0 && !at_line_start {
+            output.push(' ');
+            count -= 1;
+        }
+        if count > 0 {
+            output.push_str(&format!("<text:s text:c=\"{count}\"/>"));
+        }
+        *spaces = 0;
+    };
+    for character in text.chars() {
+        match character {
+            ' ' => spaces += 1,
+            '\t' => {
+                flush(&mut spaces, *at_line_start, output);
+                output.push_str("<text:tab/>");
+                *at_line_start = false;
+            }
+            _ => {
+                flush(&mut spaces, *at_line_start, output);
+                escape_xml(character.encode_utf8(&mut [0; 4]), output);
+                *at_line_start = false;
+            }
+        }
+    }
+    if spaces > 0 {
+        flush(&mut spaces, *at_line_start, output);
+        *at_line_start = false;
+    }
+}
+
+fn push_odt_line(line: &Line, output: &mut String) {
+    let mut at_line_start = true;
+    for piece in &line.pieces {
+        let style = match piece.emphasis {
+            Some(Emphasis::Bold) => Some("Strong_20_Emphasis"),
+            Some(Emphasis::Italic) => Some("Emphasis"),
+            None => None,
+        };
+        if let Some(style) = style {
+            output.push_str(&format!("<text:span text:style-name=\"{style}\">"));
+        }
+        push_odt_text(piece.text, &mut at_line_start, output);
+        if style.is_some() {
+            output.push_str("</text:span>");
+        }
+    }
+}
+
+fn odt_content(text: &str) -> String {
+    let mut body = String::new();
+    for block in markdown::blocks(text) {
+        match block {
+            Block::Heading(level, line) => {
+                body.push_str(&format!(
+                    "<text:h text:style-name=\"Heading_20_{level}\" text:outline-level=\"{level}\">"
+                ));
+                push_odt_line(&line, &mut body);
+                body.push_str("</text:h>\n");
+            }
+            Block::Paragraph(lines) => {
+                body.push_str("<text:p text:style-name=\"Text_20_body\">");
+                for (index, line) in lines.iter().enumerate() {
+                    if index > 0 {
+                        body.push_str("<text:line-break/>");
+                    }
+                    push_odt_line(line, &mut body);
+                }
+                body.push_str("</text:p>\n");
+            }
+        }
+    }
+    format!(
+        "{XML_DECLARATION}<office:document-content {ODF_NAMESPACES}>
+<office:body>
+<office:text>
+{body}</office:text>
+</office:body>
+</office:document-content>
+"
+    )
+}
+
+fn odt_styles(style: &ExportStyle) -> String {
+    let body = body_points(style);
+    let headings: String = (1..=6)
+        .map(|level| {
+            let (scale, line_height) = export::heading_metrics(level);
+            format!(
+                "<style:style style:name=\"Heading_20_{level}\" style:display-name=\"Heading {level}\" \
+style:family=\"paragraph\" style:parent-style-name=\"Heading\" style:next-style-name=\"Text_20_body\" \
+style:default-outline-level=\"{level}\" style:class=\"text\">\
+<style:paragraph-properties fo:line-height=\"{}%\"/>\
+<style:text-properties fo:font-size=\"{}\"/></style:style>\n",
+                (line_height * 100.0).round(),
+                points(body * scale),
+            )
+        })
+        .collect();
+    let weight = (style.font_weight.clamp(100, 900) + 50) / 100 * 100;
+    format!(
+        "{XML_DECLARATION}<office:document-styles {ODF_NAMESPACES}>
+<office:styles>
+<style:default-style style:family=\"paragraph\">\
+<style:text-properties fo:font-family=\"{}\" fo:font-size=\"{}\" fo:font-style=\"{}\" fo:font-weight=\"{weight}\"/>\
+</style:default-style>
+<style:style style:name=\"Standard\" style:family=\"paragraph\" style:class=\"text\"/>
+<style:style style:name=\"Text_20_body\" style:display-name=\"Text body\" style:family=\"paragraph\" \
+style:parent-style-name=\"Standard\" style:class=\"text\">\
+<style:paragraph-properties fo:margin-top=\"0pt\" fo:margin-bottom=\"{}\" fo:line-height=\"{}%\"/>\
+</style:style>
+<style:style style:name=\"Heading\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" \
+style:next-style-name=\"Text_20_body\" style:class=\"text\">\
+<style:paragraph-properties fo:margin-top=\"0pt\" fo:margin-bottom=\"0pt\" fo:keep-with-next=\"always\"/>\
+<style:text-properties fo:font-weight=\"bold\"/></style:style>
+{headings}<style:style style:name=\"Strong_20_Emphasis\" style:display-name=\"Strong Emphasis\" style:family=\"text\">\
+<style:text-properties fo:font-weight=\"bold\"/></style:style>
+<style:style style:name=\"Emphasis\" style:family=\"text\">\
+<style:text-properties fo:font-style=\"italic\"/></style:style>
+</office:styles>
+</office:document-styles>
+",
+        xml_text(&primary_family(&style.font_family)),
+        points(body),
+        if is_italic(style) { "italic" } else { "normal" },
+        points(body * export::LINE_HEIGHT),
+        (export::LINE_HEIGHT * 100.0).round(),
+    )
+}
+
+fn odt_manifest() -> String {
+    format!(
+        "{XML_DECLARATION}<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.3\">
+<manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.3\" manifest:media-type=\"{ODT_MIME_TYPE}\"/>
+<manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>
+<manifest:file-entry manifest:full-path=\"styles.xml\" manifest:media-type=\"text/xml\"/>
+</manifest:manifest>
+"
+    )
+}
+
+/// An OpenDocument Text package for `text` in the editor's font.
+pub fn odt_document(text: &str, style: &ExportStyle) -> Result<Vec<u8>, String> {
+    zip_package(&[
+        ("mimetype", ODT_MIME_TYPE.to_string()),
+        ("META-INF/manifest.xml", odt_manifest()),
+        ("content.xml", odt_content(text)),
+        ("styles.xml", odt_styles(style)),
+    ])
+}
+
+fn push_docx_line(line: &Line, output: &mut String) {
+    for piece in &line.pieces {
+        let properties = match piece.emphasis {
+            Some(Emphasis::Bold) => "<w:rPr><w:b/><w:bCs/></w:rPr>",
+            Some(Emphasis::Italic) => "<w:rPr><w:i/><w:iCs/></w:rPr>",
+            None => "",
+        };
+        output.push_str("<w:r>");
+        output.push_str(properties);
+        // Word reads a tab inside `w:t` as a space.
+        for (index, part) in piece.text.split('\t').enumerate() {
+            if index > 0 {
+                output.push_str("<w:tab/>");
+            }
+            if !part.is_empty() {
+                output.push_str("<w:t xml:space=\"preserve\">");
+                escape_xml(part, output);
+                output.push_str("</w:t>");
+            }
+        }
+        output.push_str("</w:r>");
+    }
+}
+
+fn docx_document_xml(text: &str) -> String {
+    let mut body = String::new();
+    for block in markdown::blocks(text) {
+        match block {
+            Block::Heading(level, line) => {
+                body.push_str(&format!(
+                    "<w:p><w:pPr><w:pStyle w:val=\"Heading{level}\"/></w:pPr>"
+                ));
+                push_docx_line(&line, &mut body);
+                body.push_str("</w:p>\n");
+            }
+            Block::Paragraph(lines) => {
+                body.push_str("<w:p>");
+                for (index, line) in lines.iter().enumerate() {
+                    if index > 0 {
+                        body.push_str("<w:r><w:br/></w:r>");
+                    }
+                    push_docx_line(line, &mut body);
+                }
+                body.push_str("</w:p>\n");
+            }
+        }
+    }
+    format!(
+        "{XML_DECLARATION}<w:document xmlns:w=\"{WORD_NAMESPACE}\">
+<w:body>
+{body}</w:body>
+</w:document>
+"
+    )
+}
+
+/// Word's line spacing is in 240ths of a line.
+fn docx_line(line_height: f64) -> i64 {
+    (line_height * 240.0).round() as i64
+}
+
+/// Word's font sizes are in half points.
+fn docx_size(points: f64) -> i64 {
+    (points * 2.0).round().max(2.0) as i64
+}
+
+fn docx_styles(style: &ExportStyle) -> String {
+    let body = body_points(style);
+    let family = xml_text(&primary_family(&style.font_family));
+    let headings: String = (1..=6)
+        .map(|level| {
+            let (scale, line_height) = export::heading_metrics(level);
+            let size = docx_size(body * scale);
+            format!(
+                "<w:style w:type=\"paragraph\" w:styleId=\"Heading{level}\"><w:name w:val=\"heading {level}\"/>\
+<w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:uiPriority w:val=\"9\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:after=\"0\" w:line=\"{}\" w:lineRule=\"auto\"/><w:outlineLvl w:val=\"{}\"/></w:pPr>\
+<w:rPr><w:b/><w:bCs/><w:sz w:val=\"{size}\"/><w:szCs w:val=\"{size}\"/></w:rPr></w:style>\n",
+                docx_line(line_height),
+                level - 1,
+            )
+        })
+        .collect();
+    let size = docx_size(body);
+    let mut run_defaults = format!(
+        "<w:rFonts w:ascii=\"{family}\" w:hAnsi=\"{family}\" w:eastAsia=\"{family}\" w:cs=\"{family}\"/>"
+    );
+    if is_bold(style) {
+        run_defaults.push_str("<w:b/><w:bCs/>");
+    }
+    if is_italic(style) {
+        run_defaults.push_str("<w:i/><w:iCs/>");
+    }
+    format!(
+        "{XML_DECLARATION}<w:styles xmlns:w=\"{WORD_NAMESPACE}\">
+<w:docDefaults><w:rPrDefault><w:rPr>{run_defaults}<w:sz w:val=\"{size}\"/><w:szCs w:val=\"{size}\"/></w:rPr></w:rPrDefault>\
+<w:pPrDefault><w:pPr><w:spacing w:before=\"0\" w:after=\"{}\" w:line=\"{}\" w:lineRule=\"auto\"/></w:pPr></w:pPrDefault></w:docDefaults>
+<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>
+{headings}</w:styles>
+",
+        // Paragraph spacing is in twentieths of a point.
+        (body * export::LINE_HEIGHT * 20.0).round() as i64,
+        docx_line(export::LINE_HEIGHT),
+    )
+}
+
+const DOCX_CONTENT_TYPES: &str = "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>
+<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>
+<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>
+</Types>
+";
+
+const DOCX_PACKAGE_RELATIONSHIPS: &str = "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>
+</Relationships>
+";
+
+const DOCX_DOCUMENT_RELATIONSHIPS: &str = "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>
+</Relationships>
+";
+
+/// A Word document package for `text` in the editor's font.
+pub fn docx_document(text: &str, style: &ExportStyle) -> Result<Vec<u8>, String> {
+    zip_package(&[
+        (
+            "[Content_Types].xml",
+            format!("{XML_DECLARATION}{DOCX_CONTENT_TYPES}"),
+        ),
+        (
+            "_rels/.rels",
+            format!("{XML_DECLARATION}{DOCX_PACKAGE_RELATIONSHIPS}"),
+        ),
+        ("word/document.xml", docx_document_xml(text)),
+        ("word/styles.xml", docx_styles(style)),
+        (
+            "word/_rels/document.xml.rels",
+            format!("{XML_DECLARATION}{DOCX_DOCUMENT_RELATIONSHIPS}"),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::name::ResolveResult;
+    use quick_xml::NsReader;
+    use std::io::Read;
+
+    const SAMPLE: &str =
+        "# Notes\n\nFirst  **bold** line\n\tsecond *italic* & <line>\n\n## Next\nlast\n";
+
+    fn style() -> ExportStyle {
+        ExportStyle {
+            font_family: "\"Noto Serif\", serif".to_string(),
+            font_size: 16.0,
+            font_style: "normal".to_string(),
+            font_weight: 400,
+            theme_mode: "dark".to_string(),
+        }
+    }
+
+    fn unzip(package: &[u8]) -> Vec<(String, String)> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        (0..archive.len())
+            .map(|index| {
+                let mut file = archive.by_index(index).unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+
+    fn part<'a>(parts: &'a [(String, String)], name: &str) -> &'a str {
+        parts
+            .iter()
+            .find(|(part, _)| part == name)
+            .map(|(_, content)| content.as_str())
+            .unwrap_or_else(|| panic!("missing {name}"))
+    }
+
+    enum Node {
+        /// An element's local name and its attributes, by local name.
+        Element(String, Vec<(String, String)>),
+        /// Text, with the local name of the element directly around it.
+        Text(String, String),
+    }
+
+    /// Parses `xml`, failing on anything malformed or on an undeclared
+    /// namespace prefix.
+    fn parse(xml: &str) -> Vec<Node> {
+        let mut reader = NsReader::from_str(xml);
+        let mut open = Vec::new();
+        let mut nodes = Vec::new();
+        let local = |name: &[u8]| String::from_utf8(name.to_vec()).unwrap();
+        loop {
+            let (namespace, event) = reader.read_resolved_event().unwrap();
+            match event {
+                Event::Start(ref element) | Event::Empty(ref element) => {
+                    if element.name().prefix().is_some() {
+                        assert!(matches!(namespace, ResolveResult::Bound(_)), "{xml}");
+                    }
+                    let mut attributes = Vec::new();
+                    for attribute in element.attributes() {
+                        let attribute = attribute.unwrap();
+                        let declaration = attribute
+                            .key
+                            .prefix()
+                            .is_some_and(|prefix| matches!(prefix.as_ref(), b"xml" | b"xmlns"));
+                        if attribute.key.prefix().is_some() && !declaration {
+                            let (namespace, _) = reader.resolve_attribute(attribute.key);
+                            assert!(matches!(namespace, ResolveResult::Bound(_)), "{xml}");
+                        }
+                        attributes.push((
+                            local(attribute.key.local_name().as_ref()),
+                            attribute.unescape_value().unwrap().into_owned(),
+                        ));
+                    }
+                    let name = local(element.local_name().as_ref());
+                    if matches!(event, Event::Start(_)) {
+                        open.push(name.clone());
+                    }
+                    nodes.push(Node::Element(name, attributes));
+                }
+                Event::End(_) => {
+                    open.pop();
+                }
+                Event::Text(ref text) => {
+                    let parent = open.last().cloned().unwrap_or_default();
+                    nodes.push(Node::Text(parent, text.unescape().unwrap().into_owned()));
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        nodes
+    }
+
+    fn elements(xml: &str) -> Vec<(String, Vec<(String, String)>)> {
+        parse(xml)
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::Element(name, attributes) => Some((name, attributes)),
+                Node::Text(..) => None,
+            })
+            .collect()
+    }
+
+    fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads the paragraphs back as (style, text), with line breaks, tabs
+    /// and runs of spaces restored.
+    fn odt_paragraphs(content: &str) -> Vec<(String, String)> {
+        let mut paragraphs: Vec<(String, String)> = Vec::new();
+        for node in parse(content) {
+            match node {
+                Node::Element(name, attributes) => match name.as_str() {
+                    "h" | "p" => paragraphs.push((
+                        attribute(&attributes, "style-name").unwrap().to_string(),
+                        String::new(),
+                    )),
+                    "line-break" => paragraphs.last_mut().unwrap().1.push('\n'),
+                    "tab" => paragraphs.last_mut().unwrap().1.push('\t'),
+                    "s" => {
+                        let count = attribute(&attributes, "c").map_or(1, |c| c.parse().unwrap());
+                        paragraphs
+                            .last_mut()
+                            .unwrap()
+                            .1
+                            .push_str(&" ".repeat(count));
+                    }
+                    _ => {}
+                },
+                Node::Text(parent, text) => {
+                    if matches!(parent.as_str(), "h" | "p" | "span") {
+                        paragraphs.last_mut().unwrap().1.push_str(&text);
+                    }
+                }
+            }
+        }
+        paragraphs
+    }
+
+    fn docx_paragraphs(document: &str) -> Vec<(String, String)> {
+        let mut paragraphs: Vec<(String, String)> = Vec::new();
+        for node in parse(document) {
+            match node {
+                Node::Element(name, attributes) => match name.as_str() {
+                    "p" => paragraphs.push(("Normal".to_string(), String::new())),
+                    "pStyle" => {
+                        paragraphs.last_mut().unwrap().0 =
+                            attribute(&attributes, "val").unwrap().to_string()
+                    }
+                    "br" => paragraphs.last_mut().unwrap().1.push('\n'),
+                    "tab" => paragraphs.last_mut().unwrap().1.push('\t'),
+                    _ => {}
+                },
+                Node::Text(parent, text) => {
+                    if parent == "t" {
+                        paragraphs.last_mut().unwrap().1.push_str(&text);
+                    }
+                }
+            }
+        }
+        paragraphs
+    }
+
+    fn expected(heading: &str, body: &str) -> Vec<(String, String)> {
+        [
+            (format!("{heading}1"), "Notes"),
+            (
+                body.to_string(),
+                "First  bold line\n\tsecond italic & <line>",
+            ),
+            (format!("{heading}2"), "Next"),
+            (body.to_string(), "last"),
+        ]
+        .into_iter()
+        .map(|(style, text)| (style, text.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn odt_round_trips_headings_paragraphs_and_emphasis() {
+        let parts = unzip(&odt_document(SAMPLE, &style()).unwrap());
+        assert_eq!(
+            parts[0],
+            ("mimetype".to_string(), ODT_MIME_TYPE.to_string())
+        );
+        for (_, content) in parts.iter().skip(1) {
+            parse(content);
+        }
+
+        let content = part(&parts, "content.xml");
+        assert_eq!(
+            odt_paragraphs(content),
+            expected("Heading_20_", "Text_20_body")
+        );
+        assert!(
+            content.contains("<text:span text:style-name=\"Strong_20_Emphasis\">bold</text:span>")
+        );
+        assert!(content.contains("<text:span text:style-name=\"Emphasis\">italic</text:span>"));
+
+        let styles = elements(part(&parts, "styles.xml"));
+        let heading = styles
+            .iter()
+            .find(|(name, attributes)| {
+                name == "style" && attribute(attributes, "name") == Some("Heading_20_1")
+            })
+            .unwrap();
+        assert_eq!(attribute(&heading.1, "default-outline-level"), Some("1"));
+        assert!(styles
+            .iter()
+            .any(|(name, attributes)| name == "text-properties"
+                && attribute(attributes, "font-family") == Some("Noto Serif")
+                && attribute(attributes, "font-size") == Some("12pt")));
+        assert!(part(&parts, "META-INF/manifest.xml").contains("styles.xml"));
+    }
+
+    #[test]
+    fn docx_round_trips_headings_paragraphs_and_emphasis() {
+        let parts = unzip(&docx_document(SAMPLE, &style()).unwrap());
+        for (_, content) in &parts {
+            parse(content);
+        }
+
+        let document = part(&parts, "word/document.xml");
+        assert_eq!(docx_paragraphs(document), expected("Heading", "Normal"));
+        assert!(document.contains(
+            "<w:r><w:rPr><w:b/><w:bCs/></w:rPr><w:t xml:space=\"preserve\">bold</w:t></w:r>"
+        ));
+        assert!(document.contains(
+            "<w:r><w:rPr><w:i/><w:iCs/></w:rPr><w:t xml:space=\"preserve\">italic</w:t></w:r>"
+        ));
+
+        let styles = elements(part(&parts, "word/styles.xml"));
+        for level in 1..=6 {
+            assert!(styles.iter().any(|(name, attributes)| name == "style"
+                && attribute(attributes, "styleId") == Some(&format!("Heading{level}"))));
+        }
+        let fonts = styles.iter().find(|(name, _)| name == "rFonts").unwrap();
+        assert_eq!(attribute(&fonts.1, "ascii"), Some("Noto Serif"));
+        assert!(styles
+            .iter()
+            .any(|(name, attributes)| name == "sz" && attribute(attributes, "val") == Some("24")));
+        assert!(part(&parts, "[Content_Types].xml").contains("/word/styles.xml"));
+        assert!(part(&parts, "word/_rels/document.xml.rels").contains("styles.xml"));
+    }
+
+    #[test]
+    fn drops_characters_xml_cannot_hold() {
+        let text = "bell\u{7} and \u{0}nul";
+        let odt = unzip(&odt_document(text, &style()).unwrap());
+        assert_eq!(
+            odt_paragraphs(part(&odt, "content.xml"))[0].1,
+            "bell and nul"
+        );
+        let docx = unzip(&docx_document(text, &style()).unwrap());
+        assert_eq!(
+            docx_paragraphs(part(&docx, "word/document.xml"))[0].1,
+            "bell and nul"
+        );
+    }
+}
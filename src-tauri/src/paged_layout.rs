@@ -0,0 +1,319 @@
+use crate::export::{self, ExportStyle};
+use crate::markdown::{self, Emphasis};
+use gtk::{cairo, pango};
+
+/// CSS pixels are 1/96 inch and PDF points 1/72, so the editor's font size
+/// prints at the same physical size it would have on a 96 dpi screen.
+pub const POINTS_PER_PX: f64 = 0.75;
+
+/// The light theme's text colour, `#1c2736`. Paper is white, so printed
+/// output always uses the light theme.
+pub const TEXT_RGB: [f64; 3] = [28.0 / 255.0, 39.0 / 255.0, 54.0 / 255.0];
+
+/// The light theme's gutter colour, `#9aacbf`.
+pub const GUTTER_RGB: [f64; 3] = [154.0 / 255.0, 172.0 / 255.0, 191.0 / 255.0];
+
+/// Opacity of a level 6 heading, as in `.cm-fmt-h6`.
+const H6_OPACITY: f64 = 0.85;
+
+/// Line numbers are set smaller than the text, like the editor's gutter.
+const LINE_NUMBER_SCALE: f64 = 0.85;
+
+/// Space between the line numbers and the text, in ems of the body font.
+const GUTTER_GAP_EMS: f64 = 1.0;
+
+fn to_pango_units(points: f64) -> i32 {
+    (points * pango::SCALE as f64).round() as i32
+}
+
+fn from_pango_units(units: i32) -> f64 {
+    units as f64 / pango::SCALE as f64
+}
+
+/// The editor's font at `scale` times its size, optionally bold.
+pub fn font_description(style: &ExportStyle, scale: f64, bold: bool) -> pango::FontDescription {
+    let mut description = pango::FontDescription::new();
+    // The setting may be a CSS list such as `"Noto Sans", sans-serif`, which
+    // Pango reads as a comma-separated list of families once unquoted.
+    description.set_family(&style.font_family.replace(['"', '\''], ""));
+    description.set_style(crate::to_pango_style(&style.font_style));
+    description.set_weight(if bold {
+        pango::Weight::Bold
+    } else {
+        crate::to_pango_weight(style.font_weight)
+    });
+    description.set_size(to_pango_units(style.font_size_px() * POINTS_PER_PX * scale));
+    description
+}
+
+struct LaidOutLine {
+    layout: pango::Layout,
+    /// Height of each of its rows: the font size times the line height.
+    advance: f64,
+    alpha: f64,
+}
+
+/// One row of a wrapped line, placed on a page.
+struct PlacedRow {
+    line: usize,
+    row: usize,
+    /// Offset of the row's top from the top of the text area.
+    top: f64,
+}
+
+/// A Pango layout for one source line: its visible text, with bold and
+/// italic applied as attributes over the byte ranges of their pieces.
+fn lay_out_line(
+    context: &pango::Context,
+    line: &markdown::Line,
+    style: &ExportStyle,
+    width: f64,
+) -> LaidOutLine {
+    let (scale, line_height) = line
+        .heading_level
+        .map_or((1.0, export::LINE_HEIGHT), export::heading_metrics);
+    let layout = pango::Layout::new(context);
+    layout.set_font_description(Some(&font_description(
+        style,
+        scale,
+        line.heading_level.is_some(),
+    )));
+    layout.set_width(to_pango_units(width));
+    layout.set_wrap(pango::WrapMode::WordChar);
+
+    let mut text = String::new();
+    let attributes = pango::AttrList::new();
+    for piece in &line.pieces {
+        let start = text.len() as u32;
+        text.push_str(piece.text);
+        let end = text.len() as u32;
+        let mut attribute = match piece.emphasis {
+            Some(Emphasis::Bold) => pango::AttrInt::new_weight(pango::Weight::Bold),
+            Some(Emphasis::Italic) => pango::AttrInt::new_style(pango::Style::Italic),
+            None => continue,
+        };
+        attribute.set_start_index(start);
+        attribute.set_end_index(end);
+        attributes.insert(attribute);
+    }
+    layout.set_text(&text);
+    layout.set_attributes(Some(&attributes));
+
+    LaidOutLine {
+        layout,
+        advance: style.font_size_px() * POINTS_PER_PX * scale * line_height,
+        alpha: if line.heading_level == Some(6) {
+            H6_OPACITY
+        } else {
+            1.0
+        },
+    }
+}
+
+/// A document laid out the way the Formatted view shows it and broken into
+/// pages, ready to be drawn page by page onto any Cairo context. Needs Pango
+/// and fontconfig but no display.
+pub struct PagedLayout {
+    context: pango::Context,
+    style: ExportStyle,
+    lines: Vec<LaidOutLine>,
+    pages: Vec<Vec<PlacedRow>>,
+    /// Width of the line number gutter, when line numbers are shown.
+    gutter: Option<f64>,
+}
+
+impl PagedLayout {
+    /// Lays `text` out in a text area `width` by `height` points, with the
+    /// gutter for line numbers taken from its width.
+    pub fn new(
+        context: &cairo::Context,
+        text: &str,
+        style: &ExportStyle,
+        width: f64,
+        height: f64,
+        line_numbers: bool,
+    ) -> Self {
+        let context = points_context(context);
+        let lines = markdown::visible_lines(text);
+        let gutter = line_numbers.then(|| {
+            let widest = "8".repeat(lines.len().to_string().len());
+            let layout = number_layout(&context, style, &widest);
+            let gap = style.font_size_px() * POINTS_PER_PX * GUTTER_GAP_EMS;
+            from_pango_units(layout.size().0) + gap
+        });
+        let text_width = (width - gutter.unwrap_or(0.0)).max(1.0);
+
+        let lines: Vec<LaidOutLine> = lines
+            .iter()
+            .map(|line| lay_out_line(&context, line, style, text_width))
+            .collect();
+
+        // A wrapped line is placed row by row so that a page can break
+        // between its rows.
+        let mut pages = vec![Vec::new()];
+        let mut top = 0.0;
+        for (line_index, line) in lines.iter().enumerate() {
+            for row in 0..line.layout.line_count() as usize {
+                if top + line.advance > height && top > 0.0 {
+                    pages.push(Vec::new());
+                    top = 0.0;
+                }
+                if let Some(page) = pages.last_mut() {
+                    page.push(PlacedRow {
+                        line: line_index,
+                        row,
+                        top,
+                    });
+                }
+                top += line.advance;
+            }
+        }
+
+        Self {
+            context,
+            style: style.clone(),
+            lines,
+            pages,
+            gutter,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Draws page `index` with the text area's top left corner at `left`,
+    /// `top`.
+    pub fn draw_page(&self, context: &cairo::Context, index: usize, left: f64, top: f64) {
+        let Some(rows) = self.pages.get(index) else {
+            return;
+        };
+        let [red, green, blue] = TEXT_RGB;
+        let text_left = left + self.gutter.unwrap_or(0.0);
+        pangocairo::functions::update_context(context, &self.context);
+
+        for placed in rows {
+            let line = &self.lines[placed.line];
+            line.layout.context_changed();
+            let Some(row) = line.layout.line_readonly(placed.row as i32) else {
+                continue;
+            };
+
+            // Each row is centred in its line height, as CSS does it.
+            let (_, logical) = row.extents();
+            let ascent = -from_pango_units(logical.y());
+            let height = from_pango_units(logical.height());
+            let baseline = top + placed.top + (line.advance - height) / 2.0 + ascent;
+
+            context.set_source_rgba(red, green, blue, line.alpha);
+            context.move_to(text_left, baseline);
+            pangocairo::functions::show_layout_line(context, &row);
+
+            if let (Some(gutter), 0) = (self.gutter, placed.row) {
+                self.draw_line_number(context, placed.line + 1, left, gutter, baseline);
+            }
+        }
+    }
+
+    /// Right-aligns the number in the gutter, on the baseline of its line's
+    /// first row.
+    fn draw_line_number(
+        &self,
+        context: &cairo::Context,
+        number: usize,
+        left: f64,
+        gutter: f64,
+        baseline: f64,
+    ) {
+        let layout = number_layout(&self.context, &self.style, &number.to_string());
+        let Some(row) = layout.line_readonly(0) else {
+            return;
+        };
+        let gap = self.style.font_size_px() * POINTS_PER_PX * GUTTER_GAP_EMS;
+        let width = from_pango_units(layout.size().0);
+        let [red, green, blue] = GUTTER_RGB;
+        context.set_source_rgb(red, green, blue);
+        context.move_to(left + gutter - gap - width, baseline);
+        pangocairo::functions::show_layout_line(context, &row);
+    }
+}
+
+/// A Pango context for drawing on `context`, whose user units are points.
+/// Pango's default of 96 dpi would make a 12pt font 16 units tall.
+pub fn points_context(context: &cairo::Context) -> pango::Context {
+    let pango_context = pangocairo::functions::create_context(context);
+    pangocairo::functions::context_set_resolution(&pango_context, 72.0);
+    pango_context
+}
+
+fn number_layout(context: &pango::Context, style: &ExportStyle, text: &str) -> pango::Layout {
+    let layout = pango::Layout::new(context);
+    layout.set_font_description(Some(&font_description(style, LINE_NUMBER_SCALE, false)));
+    layout.set_text(text);
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style() -> ExportStyle {
+        ExportStyle {
+            font_family: "sans-serif".to_string(),
+            font_size: 16.0,
+            font_style: "normal".to_string(),
+            font_weight: 400,
+            theme_mode: "light".to_string(),
+        }
+    }
+
+    fn context() -> cairo::Context {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 10, 10).unwrap();
+        cairo::Context::new(&surface).unwrap()
+    }
+
+    #[test]
+    fn breaks_pages_by_line_height() {
+        // Body text is 12pt with a line height of 1.55, so 18.6pt per row and
+        // ten rows to a page.
+        let layout = PagedLayout::new(
+            &context(),
+            &"row\n".repeat(99),
+            &style(),
+            400.0,
+            190.0,
+            false,
+        );
+        assert_eq!(layout.page_count(), 10);
+        assert_eq!(layout.pages[0].len(), 10);
+        assert_eq!(layout.pages[9].len(), 10);
+
+        let empty = PagedLayout::new(&context(), "", &style(), 400.0, 190.0, false);
+        assert_eq!(empty.page_count(), 1);
+    }
+
+    #[test]
+    fn sets_fonts_in_points() {
+        let layout = PagedLayout::new(&context(), "Mg", &style(), 400.0, 190.0, false);
+        let row = layout.lines[0].layout.line_readonly(0).unwrap();
+        // A 12pt font is about 14pt from ascent to descent, not 18.6.
+        let height = from_pango_units(row.extents().1.height());
+        assert!(height > 10.0 && height < 16.0, "{height}");
+    }
+
+    #[test]
+    fn headings_take_more_room() {
+        let layout = PagedLayout::new(&context(), "# One\n# Two", &style(), 400.0, 40.0, false);
+        // 12pt * 1.9 * 1.3 is 29.64pt, so only one heading fits per page.
+        assert_eq!(layout.page_count(), 2);
+    }
+
+    #[test]
+    fn line_numbers_narrow_the_text() {
+        let text = "word ".repeat(200);
+        let plain = PagedLayout::new(&context(), &text, &style(), 300.0, 1000.0, false);
+        let numbered = PagedLayout::new(&context(), &text, &style(), 300.0, 1000.0, true);
+        assert!(numbered.gutter.is_some_and(|gutter| gutter > 0.0));
+        assert!(numbered.lines[0].layout.line_count() >= plain.lines[0].layout.line_count());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::export::ExportStyle;
+use crate::paged_layout::PagedLayout;
+use gtk::cairo;
+use serde::Deserialize;
+
+/// Paper size and margins, in PDF points.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageSetup {
+    pub width: f64,
+    pub height: f64,
+    pub margin: f64,
+}
+
+impl Default for PageSetup {
+    /// A4 with 20 mm margins.
+    fn default() -> Self {
+        Self {
+            width: 595.0,
+            height: 842.0,
+            margin: 56.7,
+        }
+    }
+}
+
+impl PageSetup {
+    fn validate(&self) -> Result<(), String> {
+        let usable = |size: f64| size.is_finite() && size - 2.0 * self.margin >= 72.0;
+        if !self.margin.is_finite()
+            || self.margin < 0.0
+            || !usable(self.width)
+            || !usable(self.height)
+        {
+            return Err("Page size leaves no room for text inside the margins".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Lays `text` out on pages the way the Formatted view shows it and returns
+/// the PDF. Needs no display, so it also works without a window.
+pub fn render_pdf(text: &str, style: &ExportStyle, page: &PageSetup) -> Result<Vec<u8>, String> {
+    page.validate()?;
+    let surface = cairo::PdfSurface::for_stream(page.width, page.height, Vec::<u8>::new())
+        .map_err(|error| format!("Unable to create PDF: {error}"))?;
+    let context =
+        cairo::Context::new(&surface).map_err(|error| format!("Unable to draw PDF: {error}"))?;
+
+    let layout = PagedLayout::new(
+        &context,
+        text,
+        style,
+        page.width - 2.0 * page.margin,
+        page.height - 2.0 * page.margin,
+        false,
+    );
+    for index in 0..layout.page_count() {
+        layout.draw_page(&context, index, page.margin, page.margin);
+        context
+            .show_page()
+            .map_err(|error| format!("Unable to draw PDF: {error}"))?;
+    }
+
+    drop(context);
+    let stream = surface
+        .finish_output_stream()
+        .map_err(|error| format!("Unable to finish PDF: {}", error.error))?;
+    stream
+        .downcast::<Vec<u8>>()
+        .map(|bytes| *bytes)
+        .map_err(|_| "Unable to finish PDF".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style() -> ExportStyle {
+        ExportStyle {
+            font_family: "\"DejaVu Sans\", sans-serif".to_string(),
+            font_size: 14.0,
+            font_style: "normal".to_string(),
+            font_weight: 400,
+            theme_mode: "dark".to_string(),
+        }
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        let pdf = String::from_utf8_lossy(pdf);
+        pdf.matches("/Type /Page").count() - pdf.matches("/Type /Pages").count()
+    }
+
+    #[test]
+    fn renders_formatted_text_without_a_display() {
+        let pdf = render_pdf(
+            "# Notes\n\nSome **bold** and *italic* text.",
+            &style(),
+            &PageSetup::default(),
+        )
+        .unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert_eq!(page_count(&pdf), 1);
+    }
+
+    #[test]
+    fn breaks_long_documents_into_pages() {
+        let text = "A line that is long enough to wrap across the page more than once. ".repeat(8)
+            + &"\nline".repeat(200);
+        let pdf = render_pdf(&text, &style(), &PageSetup::default()).unwrap();
+        assert!(page_count(&pdf) > 3);
+    }
+
+    #[test]
+    fn rejects_pages_without_room_for_text() {
+        let page = PageSetup {
+            width: 100.0,
+            height: 842.0,
+            margin: 40.0,
+        };
+        assert!(render_pdf("text", &style(), &page).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::export::ExportStyle;
+use crate::paged_layout::{self, PagedLayout};
+use gtk::prelude::*;
+use gtk::{cairo, glib, pango};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Header and footer text is set smaller than the body, like line numbers.
+const HEADER_SCALE: f64 = 0.8;
+
+/// Height of the header and footer bands, in ems of their font. The text sits
+/// at the outer edge and the rest is space between it and the document.
+const HEADER_BAND_EMS: f64 = 2.5;
+
+/// What the Text tab of the print dialog controls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintOptions {
+    pub line_numbers: bool,
+    pub header_footer: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            line_numbers: false,
+            header_footer: true,
+        }
+    }
+}
+
+/// The document to print and how it looked in the editor.
+pub struct PrintJob {
+    pub text: String,
+    pub style: ExportStyle,
+    /// Shown in the header and used as the job name, usually the file name.
+    pub title: String,
+}
+
+thread_local! {
+    // The dialog starts from the printer, paper and options chosen last time,
+    // for as long as the app runs. GTK objects stay on the main thread.
+    static PRINT_SETTINGS: RefCell<Option<gtk::PrintSettings>> = const { RefCell::new(None) };
+    static PAGE_SETUP: RefCell<Option<gtk::PageSetup>> = const { RefCell::new(None) };
+    static OPTIONS: Cell<Option<PrintOptions>> = const { Cell::new(None) };
+}
+
+/// Shows the print dialog for `job`, with page setup embedded in it, and
+/// prints what the user chooses. Returns whether the job was sent to a
+/// printer. Must run on the GTK main thread.
+pub fn print_with_dialog(job: PrintJob) -> Result<bool, String> {
+    let settings = PRINT_SETTINGS.with(|settings| settings.borrow().clone());
+    let options = OPTIONS.with(Cell::get).unwrap_or_default();
+    let operation = print_operation(job, settings.as_ref(), options);
+    run(&operation, gtk::PrintOperationAction::PrintDialog)
+}
+
+fn run(operation: &gtk::PrintOperation, action: gtk::PrintOperationAction) -> Result<bool, String> {
+    let result = operation
+        .run(action, None::<&gtk::Window>)
+        .map_err(|error| format!("Unable to print: {error}"))?;
+    if result != gtk::PrintOperationResult::Apply {
+        return Ok(false);
+    }
+    if let Some(settings) = operation.print_settings() {
+        PRINT_SETTINGS.with(|saved| *saved.borrow_mut() = Some(settings));
+    }
+    Ok(true)
+}
+
+fn print_operation(
+    job: PrintJob,
+    settings: Option<&gtk::PrintSettings>,
+    options: PrintOptions,
+) -> gtk::PrintOperation {
+    let operation = gtk::PrintOperation::new();
+    operation.set_job_name(&job.title);
+    operation.set_unit(gtk::Unit::Points);
+    operation.set_embed_page_setup(true);
+    operation.set_print_settings(settings);
+    PAGE_SETUP.with(|page_setup| {
+        operation.set_default_page_setup(page_setup.borrow().as_ref());
+    });
+
+    let job = Rc::new(job);
+    let options = Rc::new(Cell::new(options));
+    let layout = Rc::new(RefCell::new(None::<PagedLayout>));
+    let date = glib::DateTime::now_local()
+        .and_then(|now| now.format("%x"))
+        .map(|date| date.to_string())
+        .unwrap_or_default();
+
+    operation.set_custom_tab_label(Some("Text"));
+    let checks = Rc::new(RefCell::new(None::<(gtk::CheckButton, gtk::CheckButton)>));
+    operation.connect_create_custom_widget({
+        let options = Rc::clone(&options);
+        let checks = Rc::clone(&checks);
+        move |_| {
+            let line_numbers = gtk::CheckButton::with_mnemonic("Print _line numbers");
+            let header_footer =
+                gtk::CheckButton::with_mnemonic("Print _header and footer with page numbers");
+            line_numbers.set_active(options.get().line_numbers);
+            header_footer.set_active(options.get().header_footer);
+
+            let container = gtk::Box::new(gtk::Orientation::Vertical, 6);
+            container.set_border_width(12);
+            container.pack_start(&line_numbers, false, false, 0);
+            container.pack_start(&header_footer, false, false, 0);
+            container.show_all();
+            *checks.borrow_mut() = Some((line_numbers, header_footer));
+            container.upcast()
+        }
+    });
+    operation.connect_custom_widget_apply({
+        let options = Rc::clone(&options);
+        move |_, _| {
+            if let Some((line_numbers, header_footer)) = checks.borrow().as_ref() {
+                let chosen = PrintOptions {
+                    line_numbers: line_numbers.is_active(),
+                    header_footer: header_footer.is_active(),
+                };
+                options.set(chosen);
+                OPTIONS.with(|saved| saved.set(Some(chosen)));
+            }
+        }
+    });
+
+    operation.connect_begin_print({
+        let job = Rc::clone(&job);
+        let options = Rc::clone(&options);
+        let layout = Rc::clone(&layout);
+        move |operation, context| {
+            PAGE_SETUP.with(|page_setup| *page_setup.borrow_mut() = context.page_setup());
+            let Some(cairo_context) = context.cairo_context() else {
+                return;
+            };
+            let band = band_height(&job.style, options.get());
+            let paged = PagedLayout::new(
+                &cairo_context,
+                &job.text,
+                &job.style,
+                context.width(),
+                (context.height() - 2.0 * band).max(1.0),
+                options.get().line_numbers,
+            );
+            operation.set_n_pages(paged.page_count() as i32);
+            *layout.borrow_mut() = Some(paged);
+        }
+    });
+
+    operation.connect_draw_page(move |_, context, page| {
+        let layout = layout.borrow();
+        let (Some(paged), Some(cairo_context)) = (layout.as_ref(), context.cairo_context()) else {
+            return;
+        };
+        let band = band_height(&job.style, options.get());
+        paged.draw_page(&cairo_context, page as usize, 0.0, band);
+
+        if options.get().header_footer {
+            let footer = format!("Page {} of {}", page + 1, paged.page_count());
+            draw_header_footer(
+                &cairo_context,
+                &job.style,
+                context.width(),
+                context.height(),
+                [&job.title, &date, &footer],
+            );
+        }
+    });
+
+    operation
+}
+
+/// Room taken from the top and bottom of the page for the header and footer.
+fn band_height(style: &ExportStyle, options: PrintOptions) -> f64 {
+    if options.header_footer {
+        style.font_size_px() * paged_layout::POINTS_PER_PX * HEADER_SCALE * HEADER_BAND_EMS
+    } else {
+        0.0
+    }
+}
+
+/// Draws the title at the top left, the date at the top right and the page
+/// number centred at the bottom, in the gutter colour.
+fn draw_header_footer(
+    context: &cairo::Context,
+    style: &ExportStyle,
+    width: f64,
+    height: f64,
+    [title, date, footer]: [&str; 3],
+) {
+    let pango_context = paged_layout::points_context(context);
+    let description = paged_layout::font_description(style, HEADER_SCALE, false);
+    let [red, green, blue] = paged_layout::GUTTER_RGB;
+    context.set_source_rgb(red, green, blue);
+
+    let place = |text: &str, align: pango::Alignment, top: bool| {
+        let layout = pango::Layout::new(&pango_context);
+        layout.set_font_description(Some(&description));
+        layout.set_width((width * pango::SCALE as f64) as i32);
+        layout.set_ellipsize(pango::EllipsizeMode::Middle);
+        layout.set_alignment(align);
+        layout.set_text(text);
+        let text_height = layout.size().1 as f64 / pango::SCALE as f64;
+        context.move_to(0.0, if top { 0.0 } else { height - text_height });
+        pangocairo::functions::show_layout(context, &layout);
+    };
+    place(title, pango::Alignment::Left, true);
+    place(date, pango::Alignment::Right, true);
+    place(footer, pango::Alignment::Center, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Prints `job` with `settings` and no dialog. With GTK's "Print to
+    /// File" printer and an `output-uri` this writes a PDF.
+    fn print_with_settings(
+        job: PrintJob,
+        settings: &gtk::PrintSettings,
+        options: PrintOptions,
+    ) -> Result<bool, String> {
+        let operation = print_operation(job, Some(settings), options);
+        run(&operation, gtk::PrintOperationAction::Print)
+    }
+
+    fn style() -> ExportStyle {
+        ExportStyle {
+            font_family: "sans-serif".to_string(),
+            font_size: 14.0,
+            font_style: "normal".to_string(),
+            font_weight: 400,
+            theme_mode: "light".to_string(),
+        }
+    }
+
+    #[test]
+    fn prints_to_a_file_through_the_file_backend() {
+        // GTK needs a display to start, which headless CI may not have.
+        if gtk::init().is_err() {
+            eprintln!("skipping: GTK could not be initialised");
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.pdf");
+        let uri = glib::filename_to_uri(&path, None).unwrap();
+
+        let settings = gtk::PrintSettings::new();
+        settings.set_printer("Print to File");
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(&uri));
+        settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+
+        let job = PrintJob {
+            text: "# Notes\n\nSome **bold** text.\n".repeat(100),
+            style: style(),
+            title: "notes.md".to_string(),
+        };
+        let options = PrintOptions {
+            line_numbers: true,
+            header_footer: true,
+        };
+        assert_eq!(print_with_settings(job, &settings, options), Ok(true));
+
+        // The file backend writes the job out asynchronously.
+        let finished = |pdf: &[u8]| pdf.trim_ascii_end().ends_with(b"%%EOF");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut pdf = Vec::new();
+        while Instant::now() < deadline && !finished(&pdf) {
+            while gtk::events_pending() {
+                gtk::main_iteration();
+            }
+            std::thread::sleep(Duration::from_millis(20));
+            pdf = std::fs::read(&path).unwrap_or_default();
+        }
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(finished(&pdf));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::file_metadata;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// First argument that switches the `wisty` binary into helper mode:
+/// `wisty --privileged-save <target>` writes stdin to `<target>` and exits.
+pub const HELPER_FLAG: &str = "--privileged-save";
+
+/// Overrides the elevation command, split on whitespace. Setting it to `env`
+/// runs the helper without elevation, which is how the flow is tested. Only
+/// read by debug builds: a release build must not let the environment pick
+/// the program that is handed root.
+#[cfg(debug_assertions)]
+const ELEVATION_COMMAND_ENV: &str = "WISTY_ELEVATION_COMMAND";
+
+/// Commands tried in order when the override is unset. `sudo -A` only works
+/// with an askpass program configured, since there is no terminal.
+const ELEVATION_COMMANDS: &[&[&str]] = &[&["pkexec"], &["sudo", "-A"]];
+
+/// Runs the helper when the process was started as one. Returns the exit code
+/// to end the process with, or None for a normal launch.
+pub fn run_helper_if_requested() -> Option<i32> {
+    let mut args = std::env::args_os().skip(1);
+    if args.next()? != HELPER_FLAG {
+        return None;
+    }
+    let Some(target) = args.next() else {
+        eprintln!("wisty: {HELPER_FLAG} needs a target path");
+        return Some(2);
+    };
+
+    match write_atomically(Path::new(&target), &mut std::io::stdin().lock()) {
+        Ok(()) => Some(0),
+        Err(error) => {
+            eprintln!("wisty: {error}");
+            Some(1)
+        }
+    }
+}
+
+/// What the helper does with its elevated rights: the same temp file, sync
+/// and rename as an ordinary save, keeping the target's owner, mode and
+/// attributes, or an in-place write for hard-linked files.
+pub fn write_atomically(target: &Path, content: &mut impl Read) -> Result<(), String> {
+    let target = file_metadata::resolve_save_target(target)?;
+    let write_in_place = file_metadata::has_other_links(&target)?;
+    let temp_path = crate::build_save_temp_path(&target, "privileged")?;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|error| {
+            format!(
+                "Unable to create temporary save file '{}': {error}",
+                temp_path.to_string_lossy()
+            )
+        })?;
+    let written = std::io::copy(content, &mut file).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!(
+            "Unable to write temporary save file '{}': {error}",
+            temp_path.to_string_lossy()
+        ));
+    }
+
+    if write_in_place {
+        return file_metadata::write_in_place(&temp_path, &target);
+    }
+    if let Err(error) = file_metadata::copy_metadata(&target, &temp_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error);
+    }
+    fs::rename(&temp_path, &target).map_err(|error| {
+        let _ = fs::remove_file(&temp_path);
+        format!(
+            "Unable to finalize save for '{}': {error}",
+            target.to_string_lossy()
+        )
+    })
+}
+
+/// Creates the private file a save stream writes to when the target's
+/// directory isn't writable. It lives in the temp directory, readable only by
+/// the user, until `save_elevated` hands it to the helper.
+pub fn create_staging_file(target: &Path, stream_id: &str) -> Result<(PathBuf, File), String> {
+    let file_name = target.file_name().ok_or_else(|| {
+        format!(
+            "Cannot determine file name for '{}'",
+            target.to_string_lossy()
+        )
+    })?;
+    let staging_path =
+        crate::build_save_temp_path(&std::env::temp_dir().join(file_name), stream_id)?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(&staging_path).map_err(|error| {
+        format!(
+            "Unable to create temporary save file '{}': {error}",
+            staging_path.to_string_lossy()
+        )
+    })?;
+    Ok((staging_path, file))
+}
+
+fn find_in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// The command that runs the helper with elevated rights, e.g. `pkexec`.
+pub fn elevation_command() -> Result<Vec<String>, String> {
+    #[cfg(debug_assertions)]
+    if let Ok(value) = std::env::var(ELEVATION_COMMAND_ENV) {
+        let command: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+        if !command.is_empty() {
+            return Ok(command);
+        }
+    }
+    ELEVATION_COMMANDS
+        .iter()
+        .find(|command| find_in_path(command[0]))
+        .map(|command| command.iter().map(|part| part.to_string()).collect())
+        .ok_or_else(|| "Saving this file needs pkexec or sudo, and neither was found".to_string())
+}
+
+/// Runs `command helper --privileged-save target` and pipes the staged
+/// content to it.
+pub fn save_elevated(
+    command: &[String],
+    helper: &Path,
+    target: &Path,
+    staged: &Path,
+) -> Result<(), String> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| "Elevation command is empty".to_string())?;
+    let mut source = File::open(staged).map_err(|error| {
+        format!(
+            "Unable to read temporary save file '{}': {error}",
+            staged.to_string_lossy()
+        )
+    })?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .arg(helper)
+        .arg(HELPER_FLAG)
+        .arg(target)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Unable to run '{program}' to save as administrator: {error}"))?;
+
+    // Dropping stdin closes the pipe so the helper sees end of input. A
+    // write error here usually means the helper exited early, and its exit
+    // status below says why.
+    let piped = child
+        .stdin
+        .take()
+        .map(|mut stdin| std::io::copy(&mut source, &mut stdin).and_then(|_| stdin.flush()));
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("Unable to wait for '{program}': {error}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        // pkexec's codes for a dismissed or refused authentication prompt.
+        return Err(match output.status.code() {
+            Some(126) => "Authentication was cancelled".to_string(),
+            Some(127) => "Not authorized to save this file".to_string(),
+            _ if stderr.is_empty() => format!(
+                "Saving '{}' as administrator failed ({})",
+                target.to_string_lossy(),
+                output.status
+            ),
+            _ => stderr,
+        });
+    }
+    if let Some(Err(error)) = piped {
+        return Err(format!(
+            "Unable to send file contents to '{program}': {error}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn fake_elevation_command(dir: &Path, script: &str) -> Vec<String> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("fake-elevate");
+        fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        vec![path.to_string_lossy().to_string()]
+    }
+
+    #[test]
+    fn helper_write_replaces_target() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let target = dir.join("hosts");
+        fs::write(&target, "old").unwrap();
+
+        write_atomically(&target, &mut &b"new contents"[..]).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new contents");
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipes_staged_content_through_elevation_command() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let target = dir.join("hosts");
+        let staged = dir.join("staged");
+        fs::write(&staged, "127.0.0.1 wisty\n").unwrap();
+        // Stands in for `pkexec wisty --privileged-save <target>`.
+        let command = fake_elevation_command(
+            dir,
+            r#"[ "$2" = "--privileged-save" ] || exit 2
+cat > "$3""#,
+        );
+
+        save_elevated(&command, Path::new("/usr/bin/wisty"), &target, &staged).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "127.0.0.1 wisty\n");
+
+        let cancelled = fake_elevation_command(dir, "exit 126");
+        assert_eq!(
+            save_elevated(&cancelled, Path::new("wisty"), &target, &staged),
+            Err("Authentication was cancelled".to_string())
+        );
+    }
+}
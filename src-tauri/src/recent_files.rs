@@ -0,0 +1,95 @@
+//! Files the user opened or saved, kept by the backend so that the Recent
+//! menu can reopen them in a later session. The frontend's `recentFiles`
+//! setting is only what the menu shows: the webview can write it, so it
+//! can't be what approves a path for reading.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+const RECENT_FILES_FILE: &str = "recent_files.json";
+
+/// More than the menu's three, so a file it still shows stays approved
+/// while other windows open files in between.
+const MAX_RECENT_FILES: usize = 16;
+
+#[derive(Default)]
+pub struct RecentFiles {
+    /// Held while the list is rewritten, so two windows recording at once
+    /// don't drop each other's entry.
+    writing: Mutex<()>,
+}
+
+fn list_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(RECENT_FILES_FILE))
+        .map_err(|error| format!("Unable to resolve app data directory: {error}"))
+}
+
+fn read_list(path: &Path) -> Vec<PathBuf> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// `list` with `path` moved to the front, capped at `MAX_RECENT_FILES`.
+fn with_recorded(mut list: Vec<PathBuf>, path: &Path) -> Vec<PathBuf> {
+    list.retain(|entry| entry != path);
+    list.insert(0, path.to_path_buf());
+    list.truncate(MAX_RECENT_FILES);
+    list
+}
+
+impl RecentFiles {
+    /// Records a canonical path the user opened or saved. The list is read
+    /// again first, since other running instances write it too.
+    pub fn record(&self, app: &tauri::AppHandle, canonical_path: &Path) -> Result<(), String> {
+        let _writing = self
+            .writing
+            .lock()
+            .map_err(|error| format!("Unable to update recent files: {error}"))?;
+        let path = list_path(app)?;
+        let list = with_recorded(read_list(&path), canonical_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("Unable to save recent files: {error}"))?;
+        }
+        let json = serde_json::to_vec_pretty(&list)
+            .map_err(|error| format!("Unable to save recent files: {error}"))?;
+        fs::write(&path, json).map_err(|error| format!("Unable to save recent files: {error}"))
+    }
+
+    /// Whether `canonical_path` was opened or saved recently.
+    pub fn contains(&self, app: &tauri::AppHandle, canonical_path: &Path) -> bool {
+        list_path(app)
+            .map(|path| read_list(&path).iter().any(|entry| entry == canonical_path))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_first_without_duplicates() {
+        let list: Vec<PathBuf> = (0..MAX_RECENT_FILES)
+            .map(|index| PathBuf::from(format!("/notes/{index}.md")))
+            .collect();
+
+        let list = with_recorded(list, Path::new("/notes/3.md"));
+        assert_eq!(list[0], Path::new("/notes/3.md"));
+        assert_eq!(list.len(), MAX_RECENT_FILES);
+
+        let list = with_recorded(list, Path::new("/notes/new.md"));
+        assert_eq!(list[0], Path::new("/notes/new.md"));
+        assert_eq!(list.len(), MAX_RECENT_FILES);
+        assert!(!list.contains(&PathBuf::from(format!(
+            "/notes/{}.md",
+            MAX_RECENT_FILES - 1
+        ))));
+    }
+}
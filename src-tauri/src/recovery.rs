@@ -0,0 +1,580 @@
+use crate::line_endings::LineEndingPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+const RECOVERY_DIR_NAME: &str = "recovery";
+/// Held locked by the instance that owns a session directory, so other
+/// instances can tell a live session from one left behind by a crash.
+const SESSION_LOCK_FILE_NAME: &str = "session.lock";
+const SNAPSHOT_EXTENSION: &str = "txt";
+const ENTRY_EXTENSION: &str = "json";
+
+/// A document with unsaved edits that outlived the session that made them.
+/// Stored as `<document_id>.json` next to the `<document_id>.txt` snapshot
+/// in the directory of the session that wrote it; the entry is written
+/// last, so a snapshot without one is incomplete.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryEntry {
+    document_id: String,
+    /// Where the document lives on disk, or None for an untitled buffer.
+    file_path: Option<String>,
+    snapshot_path: String,
+    saved_at_ms: u64,
+    size_bytes: u64,
+    encoding: Option<String>,
+    write_bom: Option<bool>,
+    line_ending_policy: Option<LineEndingPolicy>,
+}
+
+struct RecoverySnapshot {
+    entry: RecoveryEntry,
+    snapshot_path: PathBuf,
+    temp_path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+struct Session {
+    dir: PathBuf,
+    /// Locked for as long as this instance runs.
+    _lock: File,
+}
+
+#[derive(Default)]
+pub struct RecoveryState {
+    snapshot_counter: Mutex<u64>,
+    active_snapshots: Mutex<HashMap<String, RecoverySnapshot>>,
+    session: Mutex<Option<Session>>,
+    /// Documents taken over from ended sessions and not yet restored,
+    /// discarded or snapshotted again.
+    adopted: Mutex<HashSet<String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoverySnapshotStartResult {
+    stream_id: String,
+    document_id: String,
+}
+
+fn recovery_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| format!("Unable to resolve data directory: {error}"))?
+        .join(RECOVERY_DIR_NAME);
+    create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Creates `dir` and any missing parents, with `dir` itself accessible only
+/// by the user: snapshots hold unsaved text, which may come from files
+/// other users can't read. One made by an earlier version is tightened.
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(dir)
+        .map_err(|error| format!("Unable to create recovery directory: {error}"))?;
+    #[cfg(unix)]
+    fs::set_permissions(dir, std::os::unix::fs::PermissionsExt::from_mode(0o700))
+        .map_err(|error| format!("Unable to create recovery directory: {error}"))?;
+    Ok(())
+}
+
+fn open_session_lock(dir: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dir.join(SESSION_LOCK_FILE_NAME))
+}
+
+/// Creates a directory for this instance's snapshots, locked until exit.
+fn create_session(root: &Path) -> Result<Session, String> {
+    let dir = root.join(format!("session-{}-{}", std::process::id(), now_ms()));
+    create_private_dir(&dir)?;
+    let lock = open_session_lock(&dir)
+        .map_err(|error| format!("Unable to lock recovery directory: {error}"))?;
+    lock.try_lock()
+        .map_err(|error| format!("Unable to lock recovery directory: {error}"))?;
+    Ok(Session { dir, _lock: lock })
+}
+
+impl RecoveryState {
+    fn session_dir(&self, app: &tauri::AppHandle) -> Result<PathBuf, String> {
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|error| format!("Unable to read recovery session: {error}"))?;
+        if let Some(session) = session.as_ref() {
+            return Ok(session.dir.clone());
+        }
+        let created = create_session(&recovery_dir(app)?)?;
+        let dir = created.dir.clone();
+        *session = Some(created);
+        Ok(dir)
+    }
+
+    fn forget_adopted(&self, document_id: &str) {
+        if let Ok(mut adopted) = self.adopted.lock() {
+            adopted.remove(document_id);
+        }
+    }
+}
+
+/// Snapshots of a saved file are keyed by its path so each new snapshot
+/// replaces the last; untitled buffers use an id chosen by the frontend.
+fn document_id(file_path: Option<&str>, untitled_id: Option<&str>) -> Result<String, String> {
+    if let Some(file_path) = file_path.filter(|path| !path.is_empty()) {
+        return Ok(format!("file-{:016x}", crate::stable_path_hash(file_path)));
+    }
+    let untitled_id = untitled_id.unwrap_or_default();
+    if untitled_id.is_empty() || untitled_id.len() > 64 {
+        return Err("Untitled documents need an id of 1 to 64 characters".to_string());
+    }
+    validate_document_id(untitled_id)?;
+    Ok(format!("untitled-{untitled_id}"))
+}
+
+/// Ids become file names, so anything beyond ASCII letters, digits and `-`
+/// is rejected rather than escaped.
+fn validate_document_id(document_id: &str) -> Result<(), String> {
+    if document_id
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || character == '-')
+    {
+        Ok(())
+    } else {
+        Err(format!("Invalid recovery document id '{document_id}'"))
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Same steps as `finish_save_file_stream`: write a temp file beside the
+/// target, sync it, then rename it into place.
+fn finish_atomic_write(
+    mut writer: BufWriter<File>,
+    temp_path: &Path,
+    target_path: &Path,
+) -> Result<(), String> {
+    let result = writer
+        .flush()
+        .and_then(|()| writer.get_ref().sync_all())
+        .and_then(|()| {
+            drop(writer);
+            fs::rename(temp_path, target_path)
+        });
+    if let Err(error) = result {
+        let _ = fs::remove_file(temp_path);
+        return Err(format!(
+            "Unable to write recovery file '{}': {error}",
+            target_path.to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+fn create_temp_writer(target_path: &Path, tag: &str) -> Result<(PathBuf, BufWriter<File>), String> {
+    let temp_path = crate::build_save_temp_path(target_path, tag)?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(&temp_path).map_err(|error| {
+        format!(
+            "Unable to create temporary recovery file '{}': {error}",
+            temp_path.to_string_lossy()
+        )
+    })?;
+    Ok((temp_path, BufWriter::new(file)))
+}
+
+fn write_entry(dir: &Path, entry: &RecoveryEntry) -> Result<(), String> {
+    let entry_path = dir.join(format!("{}.{ENTRY_EXTENSION}", entry.document_id));
+    let json = serde_json::to_vec_pretty(entry)
+        .map_err(|error| format!("Unable to serialize recovery entry: {error}"))?;
+    let (temp_path, mut writer) = create_temp_writer(&entry_path, "entry")?;
+    if let Err(error) = writer.write_all(&json) {
+        drop(writer);
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Unable to write recovery entry: {error}"));
+    }
+    finish_atomic_write(writer, &temp_path, &entry_path)
+}
+
+fn remove_if_present(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(format!(
+            "Unable to remove recovery file '{}': {error}",
+            path.to_string_lossy()
+        )),
+    }
+}
+
+/// Reads every complete entry in `dir`, newest first. Unreadable entries and
+/// entries whose snapshot is gone are skipped, not reported: there is
+/// nothing the user could restore from them. Snapshot paths are taken from
+/// where the entry is now, since adopting a session moves its files.
+fn read_entries(dir: &Path) -> Result<Vec<RecoveryEntry>, String> {
+    let read_dir =
+        fs::read_dir(dir).map_err(|error| format!("Unable to read recovery directory: {error}"))?;
+    let mut entries: Vec<RecoveryEntry> = read_dir
+        .filter_map(Result::ok)
+        .map(|dir_entry| dir_entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION))
+        .filter_map(|path| fs::read(&path).ok())
+        .filter_map(|bytes| serde_json::from_slice::<RecoveryEntry>(&bytes).ok())
+        .filter(|entry| validate_document_id(&entry.document_id).is_ok())
+        .filter_map(|mut entry| {
+            let snapshot_path = dir.join(format!("{}.{SNAPSHOT_EXTENSION}", entry.document_id));
+            snapshot_path.is_file().then(|| {
+                entry.snapshot_path = snapshot_path.to_string_lossy().to_string();
+                entry
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.saved_at_ms));
+    Ok(entries)
+}
+
+/// Moves the entries of every session whose instance has ended into `own`
+/// and returns their ids. Holding a session's lock while moving keeps two
+/// instances starting together from both taking it. Where two sessions
+/// left the same file behind, the newer snapshot wins.
+fn adopt_ended_sessions(root: &Path, own: &Path) -> Vec<String> {
+    let Ok(read_dir) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut adopted = Vec::new();
+    for dir in read_dir
+        .filter_map(Result::ok)
+        .map(|dir_entry| dir_entry.path())
+    {
+        if dir == own || !dir.is_dir() {
+            continue;
+        }
+        // A session still being created has no lock file yet; leave it.
+        let Ok(lock) = File::open(dir.join(SESSION_LOCK_FILE_NAME)) else {
+            continue;
+        };
+        match lock.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => continue,
+            Err(TryLockError::Error(error)) => {
+                log::warn!(target: "wisty::recovery", "{error}");
+                continue;
+            }
+        }
+        let mut all_moved = true;
+        for entry in read_entries(&dir).unwrap_or_default() {
+            match adopt_entry(&dir, own, &entry) {
+                Ok(true) => adopted.push(entry.document_id),
+                Ok(false) => {}
+                Err(error) => {
+                    all_moved = false;
+                    log::warn!(target: "wisty::recovery", "{error}");
+                }
+            }
+        }
+        drop(lock);
+        if !all_moved {
+            continue;
+        }
+        // Whatever is left is unfinished temp files and the lock itself.
+        if let Err(error) = fs::remove_dir_all(&dir) {
+            log::warn!(target: "wisty::recovery", "{error}");
+        }
+    }
+    adopted
+}
+
+/// Moves one entry and its snapshot from `from` into `to`, snapshot first as
+/// when they are written. Returns false when `to` already holds a newer
+/// snapshot of the same document.
+fn adopt_entry(from: &Path, to: &Path, entry: &RecoveryEntry) -> Result<bool, String> {
+    let file_name =
+        |dir: &Path, extension: &str| dir.join(format!("{}.{extension}", entry.document_id));
+    let newer_exists = read_entries(to)?.iter().any(|existing| {
+        existing.document_id == entry.document_id && existing.saved_at_ms >= entry.saved_at_ms
+    });
+    if newer_exists {
+        return Ok(false);
+    }
+    for extension in [SNAPSHOT_EXTENSION, ENTRY_EXTENSION] {
+        fs::rename(file_name(from, extension), file_name(to, extension))
+            .map_err(|error| format!("Unable to move recovery file: {error}"))?;
+    }
+    Ok(true)
+}
+
+/// True when `canonical_path` is a snapshot in this instance's recovery
+/// directory, which `start_open_file_stream` accepts so a restore can stream
+/// it back in.
+pub fn is_recovery_snapshot(
+    app: &tauri::AppHandle,
+    state: &RecoveryState,
+    canonical_path: &Path,
+) -> bool {
+    let Ok(dir) = state
+        .session_dir(app)
+        .and_then(|dir| fs::canonicalize(&dir).map_err(|error| error.to_string()))
+    else {
+        return false;
+    };
+    canonical_path.parent() == Some(dir.as_path())
+        && canonical_path
+            .extension()
+            .is_some_and(|ext| ext == SNAPSHOT_EXTENSION)
+}
+
+#[tauri::command]
+pub fn start_recovery_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RecoveryState>,
+    file_path: Option<String>,
+    untitled_id: Option<String>,
+    encoding: Option<String>,
+    write_bom: Option<bool>,
+    line_ending_policy: Option<LineEndingPolicy>,
+) -> Result<RecoverySnapshotStartResult, String> {
+    let document_id = document_id(file_path.as_deref(), untitled_id.as_deref())?;
+    let dir = state.session_dir(&app)?;
+    let snapshot_path = dir.join(format!("{document_id}.{SNAPSHOT_EXTENSION}"));
+
+    let stream_id = {
+        let mut counter = state
+            .snapshot_counter
+            .lock()
+            .map_err(|error| format!("Unable to allocate recovery stream id: {error}"))?;
+        *counter += 1;
+        format!("recovery-{}", *counter)
+    };
+    let (temp_path, writer) = create_temp_writer(&snapshot_path, &stream_id)?;
+
+    let snapshot = RecoverySnapshot {
+        entry: RecoveryEntry {
+            document_id: document_id.clone(),
+            file_path: file_path.filter(|path| !path.is_empty()),
+            snapshot_path: snapshot_path.to_string_lossy().to_string(),
+            saved_at_ms: 0,
+            size_bytes: 0,
+            encoding,
+            write_bom,
+            line_ending_policy,
+        },
+        snapshot_path,
+        temp_path,
+        writer,
+    };
+
+    state
+        .active_snapshots
+        .lock()
+        .map_err(|error| format!("Unable to store recovery stream state: {error}"))?
+        .insert(stream_id.clone(), snapshot);
+
+    Ok(RecoverySnapshotStartResult {
+        stream_id,
+        document_id,
+    })
+}
+
+#[tauri::command]
+pub fn write_recovery_snapshot_chunk(
+    state: tauri::State<'_, RecoveryState>,
+    stream_id: String,
+    text_chunk: String,
+) -> Result<(), String> {
+    let mut snapshots = state
+        .active_snapshots
+        .lock()
+        .map_err(|error| format!("Unable to read recovery stream state: {error}"))?;
+    let snapshot = snapshots
+        .get_mut(&stream_id)
+        .ok_or_else(|| format!("Recovery stream '{}' not found", stream_id))?;
+
+    snapshot
+        .writer
+        .write_all(text_chunk.as_bytes())
+        .map_err(|error| format!("Unable to write recovery snapshot: {error}"))?;
+    snapshot.entry.size_bytes += text_chunk.len() as u64;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn finish_recovery_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RecoveryState>,
+    stream_id: String,
+) -> Result<RecoveryEntry, String> {
+    let mut snapshot = state
+        .active_snapshots
+        .lock()
+        .map_err(|error| format!("Unable to finalize recovery stream state: {error}"))?
+        .remove(&stream_id)
+        .ok_or_else(|| format!("Recovery stream '{}' not found", stream_id))?;
+
+    finish_atomic_write(
+        snapshot.writer,
+        &snapshot.temp_path,
+        &snapshot.snapshot_path,
+    )?;
+    snapshot.entry.saved_at_ms = now_ms();
+    write_entry(&state.session_dir(&app)?, &snapshot.entry)?;
+    // Written by this session now, so no longer one to offer.
+    state.forget_adopted(&snapshot.entry.document_id);
+    Ok(snapshot.entry)
+}
+
+#[tauri::command]
+pub fn cancel_recovery_snapshot(
+    state: tauri::State<'_, RecoveryState>,
+    stream_id: String,
+) -> Result<(), String> {
+    let maybe_snapshot = state
+        .active_snapshots
+        .lock()
+        .map_err(|error| format!("Unable to cancel recovery stream state: {error}"))?
+        .remove(&stream_id);
+
+    if let Some(snapshot) = maybe_snapshot {
+        drop(snapshot.writer);
+        remove_if_present(&snapshot.temp_path)?;
+    }
+    Ok(())
+}
+
+/// Lists documents left behind by instances that have ended, newest first.
+/// Their snapshots move into this instance's directory, so one left
+/// undecided is offered again after this instance ends too.
+#[tauri::command]
+pub fn list_recoverable_documents(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RecoveryState>,
+) -> Result<Vec<RecoveryEntry>, String> {
+    let dir = state.session_dir(&app)?;
+    let mut adopted = state
+        .adopted
+        .lock()
+        .map_err(|error| format!("Unable to read recovery session: {error}"))?;
+    adopted.extend(adopt_ended_sessions(&recovery_dir(&app)?, &dir));
+    Ok(read_entries(&dir)?
+        .into_iter()
+        .filter(|entry| adopted.contains(&entry.document_id))
+        .collect())
+}
+
+#[tauri::command]
+pub fn discard_recovery_snapshot(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RecoveryState>,
+    document_id: String,
+) -> Result<(), String> {
+    validate_document_id(&document_id)?;
+    state.forget_adopted(&document_id);
+    let dir = state.session_dir(&app)?;
+    // Entry first: without it the snapshot is ignored, so a failure between
+    // the two removals can't resurrect a discarded document.
+    remove_if_present(&dir.join(format!("{document_id}.{ENTRY_EXTENSION}")))?;
+    remove_if_present(&dir.join(format!("{document_id}.{SNAPSHOT_EXTENSION}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(document_id: &str, saved_at_ms: u64) -> RecoveryEntry {
+        RecoveryEntry {
+            document_id: document_id.to_string(),
+            file_path: None,
+            snapshot_path: String::new(),
+            saved_at_ms,
+            size_bytes: 0,
+            encoding: None,
+            write_bom: None,
+            line_ending_policy: None,
+        }
+    }
+
+    fn write_complete(dir: &Path, document_id: &str, saved_at_ms: u64) {
+        fs::write(
+            dir.join(format!("{document_id}.{SNAPSHOT_EXTENSION}")),
+            "text",
+        )
+        .unwrap();
+        write_entry(dir, &entry(document_id, saved_at_ms)).unwrap();
+    }
+
+    #[test]
+    fn derives_stable_file_safe_ids() {
+        let first = document_id(Some("/home/user/notes.txt"), None).unwrap();
+        assert_eq!(
+            first,
+            document_id(Some("/home/user/notes.txt"), Some("x")).unwrap()
+        );
+        assert_ne!(
+            first,
+            document_id(Some("/home/user/notes.md"), None).unwrap()
+        );
+        assert_eq!(
+            document_id(None, Some("1f3a-b2")).unwrap(),
+            "untitled-1f3a-b2"
+        );
+        assert!(document_id(None, Some("../escape")).is_err());
+        assert!(document_id(Some(""), None).is_err());
+    }
+
+    #[test]
+    fn adopts_complete_entries_from_ended_sessions_only() {
+        let root = tempfile::tempdir().unwrap();
+        let own = create_session(root.path()).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&own.dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let live = root.path().join("session-live");
+        let ended = root.path().join("session-ended");
+        for dir in [&live, &ended] {
+            fs::create_dir(dir).unwrap();
+            open_session_lock(dir).unwrap();
+            write_complete(dir, "untitled-a", 10);
+        }
+        let live_lock = open_session_lock(&live).unwrap();
+        live_lock.try_lock().unwrap();
+        write_complete(&ended, "untitled-b", 20);
+        // Entry without a snapshot, snapshot without an entry, and junk.
+        write_entry(&ended, &entry("untitled-orphan", 30)).unwrap();
+        fs::write(ended.join("untitled-partial.txt"), "text").unwrap();
+        fs::write(ended.join("broken.json"), "{").unwrap();
+
+        let mut adopted = adopt_ended_sessions(root.path(), &own.dir);
+        adopted.sort();
+        assert_eq!(adopted, ["untitled-a", "untitled-b"]);
+        let ids: Vec<String> = read_entries(&own.dir)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.document_id)
+            .collect();
+        assert_eq!(ids, ["untitled-b", "untitled-a"]);
+        assert!(!ended.exists());
+        assert_eq!(read_entries(&live).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,303 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Starts a separate process even when wisty is already running.
+pub const NEW_INSTANCE_FLAG: &str = "--new-instance";
+
+/// Emitted to the primary instance's window when another invocation has
+/// queued files for it.
+pub const OPEN_FILES_EVENT: &str = "open-files";
+
+const SOCKET_FILE_NAME: &str = "wisty.sock";
+const ACCEPTED_REPLY: &str = "ok";
+/// Sent after `ok` to a waiting invocation once its documents are closed.
+const SAVED_REPLY: &str = "saved";
+const UNSAVED_REPLY: &str = "unsaved";
+/// Far more than any list of paths needs.
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+/// A running instance answers at once; one that doesn't within this is
+/// reported rather than hanging the launch or starting a second instance.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The per-user socket: in `$XDG_RUNTIME_DIR`, which only the user can
+/// enter, or else in a private directory under the temp directory.
+pub fn socket_path() -> Result<PathBuf, String> {
+    if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(runtime_dir).join(SOCKET_FILE_NAME));
+    }
+
+    let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+    let dir = std::env::temp_dir().join(format!("wisty-{user}"));
+    ensure_private_dir(&dir)?;
+    Ok(dir.join(SOCKET_FILE_NAME))
+}
+
+fn ensure_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+        Err(error) => {
+            return Err(format!(
+                "Unable to create '{}': {error}",
+                dir.to_string_lossy()
+            ))
+        }
+    }
+
+    // Someone else could have created it first in the shared temp directory.
+    // `/proc/self` belongs to this process's user, as std has no getuid.
+    let metadata = fs::symlink_metadata(dir)
+        .map_err(|error| format!("Unable to inspect '{}': {error}", dir.to_string_lossy()))?;
+    let owned_by_us = fs::metadata("/proc/self").is_ok_and(|own| own.uid() == metadata.uid());
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 || !owned_by_us {
+        return Err(format!(
+            "'{}' is not a private directory",
+            dir.to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+/// The connection to a running instance that accepted a forwarded request.
+pub struct Forwarded {
+    reader: BufReader<UnixStream>,
+}
+
+impl Forwarded {
+    /// Blocks until the running instance reports that the forwarded documents
+    /// were closed, and returns whether all of them were saved. An instance
+    /// that exits first counts as closing them unsaved.
+    pub fn wait_for_close(mut self) -> Result<bool, String> {
+        self.reader
+            .get_ref()
+            .set_read_timeout(None)
+            .map_err(|error| format!("Unable to wait for the running wisty: {error}"))?;
+        let mut reply = String::new();
+        self.reader
+            .read_line(&mut reply)
+            .map_err(|error| format!("Unable to wait for the running wisty: {error}"))?;
+        Ok(reply.trim() == SAVED_REPLY)
+    }
+}
+
+/// Hands `request` to the running instance. None means there is none and
+/// this process should start normally.
+pub fn forward<T: Serialize>(socket_path: &Path, request: &T) -> Result<Option<Forwarded>, String> {
+    let mut stream = match UnixStream::connect(socket_path) {
+        Ok(stream) => stream,
+        Err(error)
+            if matches!(
+                error.kind(),
+                ErrorKind::NotFound | ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(error) => {
+            return Err(format!(
+                "Unable to reach the running wisty at '{}': {error}",
+                socket_path.to_string_lossy()
+            ))
+        }
+    };
+
+    let exchange = (|| -> std::io::Result<(String, BufReader<UnixStream>)> {
+        stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+        stream.set_write_timeout(Some(FORWARD_TIMEOUT))?;
+        let message = serde_json::to_vec(request)?;
+        stream.write_all(&message)?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut reader = BufReader::new(stream);
+        let mut reply = String::new();
+        reader.read_line(&mut reply)?;
+        Ok((reply, reader))
+    })();
+
+    match exchange {
+        Ok((reply, reader)) if reply.trim() == ACCEPTED_REPLY => Ok(Some(Forwarded { reader })),
+        Ok((reply, _)) => Err(format!(
+            "The running wisty refused the files: {}",
+            reply.trim()
+        )),
+        Err(error)
+            if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut =>
+        {
+            Err(format!(
+                "The running wisty did not answer; try again, or pass {NEW_INSTANCE_FLAG} to start another"
+            ))
+        }
+        Err(error) => Err(format!(
+            "Unable to send files to the running wisty: {error}"
+        )),
+    }
+}
+
+/// Listens on `socket_path` for forwarded requests and passes each one to
+/// `handler`, each connection on a thread of its own so a sender that
+/// stalls doesn't hold up the others. When the handler returns a receiver,
+/// the sender is kept connected until it reports whether the documents were
+/// saved. Returns false without listening when another live instance
+/// already owns the socket.
+pub fn listen<T, F>(socket_path: &Path, handler: F) -> Result<bool, String>
+where
+    T: DeserializeOwned,
+    F: Fn(T) -> Option<Receiver<bool>> + Send + Sync + 'static,
+{
+    let listener = match UnixListener::bind(socket_path) {
+        Ok(listener) => listener,
+        Err(error) if error.kind() == ErrorKind::AddrInUse => {
+            if UnixStream::connect(socket_path).is_ok() {
+                return Ok(false);
+            }
+            // Left behind by an instance that didn't exit cleanly.
+            fs::remove_file(socket_path).map_err(|error| {
+                format!(
+                    "Unable to remove stale socket '{}': {error}",
+                    socket_path.to_string_lossy()
+                )
+            })?;
+            UnixListener::bind(socket_path).map_err(|error| {
+                format!(
+                    "Unable to listen on '{}': {error}",
+                    socket_path.to_string_lossy()
+                )
+            })?
+        }
+        Err(error) => {
+            return Err(format!(
+                "Unable to listen on '{}': {error}",
+                socket_path.to_string_lossy()
+            ))
+        }
+    };
+
+    let handler = Arc::new(handler);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let handler = Arc::clone(&handler);
+            std::thread::spawn(move || serve(&stream, handler.as_ref()));
+        }
+    });
+    Ok(true)
+}
+
+/// Reads one forwarded request from `stream`, hands it to `handler` and
+/// replies, waiting for the documents to close if the handler asks to.
+fn serve<T, F>(mut stream: &UnixStream, handler: &F)
+where
+    T: DeserializeOwned,
+    F: Fn(T) -> Option<Receiver<bool>>,
+{
+    let request = match read_request::<T>(stream) {
+        Ok(request) => request,
+        Err(error) => {
+            log::warn!(target: "wisty::single_instance", "{error}");
+            let _ = stream.write_all(format!("{error}\n").as_bytes());
+            return;
+        }
+    };
+    let wait = handler(request);
+    if stream
+        .write_all(format!("{ACCEPTED_REPLY}\n").as_bytes())
+        .is_err()
+    {
+        return;
+    }
+    if let Some(wait) = wait {
+        let reply = match wait.recv() {
+            Ok(true) => SAVED_REPLY,
+            _ => UNSAVED_REPLY,
+        };
+        let _ = stream.write_all(format!("{reply}\n").as_bytes());
+    }
+}
+
+fn read_request<T: DeserializeOwned>(stream: &UnixStream) -> Result<T, String> {
+    stream
+        .set_read_timeout(Some(FORWARD_TIMEOUT))
+        .map_err(|error| format!("Unable to configure forwarded request: {error}"))?;
+    let mut message = Vec::new();
+    stream
+        .take(MAX_REQUEST_BYTES)
+        .read_to_end(&mut message)
+        .map_err(|error| format!("Unable to read forwarded request: {error}"))?;
+    serde_json::from_slice(&message).map_err(|error| format!("Invalid forwarded request: {error}"))
+}
+
+/// Managed by the instance that listens on the socket.
+pub struct ListeningSocket(pub PathBuf);
+
+/// Removes the socket as the primary instance exits, so the next launch
+/// doesn't have to clear it.
+pub fn remove_socket(socket_path: &Path) {
+    let _ = fs::remove_file(socket_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn forwards_requests_to_the_listening_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join(SOCKET_FILE_NAME);
+        assert!(forward(&socket, &vec!["notes.txt"]).unwrap().is_none());
+
+        let (sender, receiver) = mpsc::channel();
+        assert!(listen(&socket, move |files: Vec<String>| {
+            sender.send(files).unwrap();
+            None
+        })
+        .unwrap());
+        assert!(!listen(&socket, |_: Vec<String>| None).unwrap());
+
+        // A sender that connects and says nothing doesn't hold up the next.
+        let _stalled = UnixStream::connect(&socket).unwrap();
+        let started = std::time::Instant::now();
+        assert!(forward(&socket, &vec!["notes.txt", "todo.md"])
+            .unwrap()
+            .is_some());
+        assert!(started.elapsed() < FORWARD_TIMEOUT / 2);
+        assert_eq!(
+            receiver.recv_timeout(FORWARD_TIMEOUT).unwrap(),
+            vec!["notes.txt", "todo.md"]
+        );
+        assert!(forward(&socket, &"not a list").is_err());
+    }
+
+    #[test]
+    fn waiting_sender_learns_whether_documents_were_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join(SOCKET_FILE_NAME);
+        let (closed_sender, closed) = mpsc::channel();
+        let closed_sender = std::sync::Mutex::new(closed_sender);
+        assert!(listen(&socket, move |saved: bool| {
+            let (done, wait) = mpsc::channel();
+            closed_sender.lock().unwrap().send((done, saved)).unwrap();
+            Some(wait)
+        })
+        .unwrap());
+
+        for saved in [true, false] {
+            let forwarded = forward(&socket, &saved).unwrap().unwrap();
+            // The document is "closed" only after the sender has been
+            // accepted and is waiting.
+            let (done, saved) = closed.recv_timeout(FORWARD_TIMEOUT).unwrap();
+            done.send(saved).unwrap();
+            assert_eq!(forwarded.wait_for_close(), Ok(saved));
+        }
+    }
+}
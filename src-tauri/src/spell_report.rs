@@ -0,0 +1,555 @@
+//! Every misspelled word in a document and where it is, found by the
+//! backend so that a long manuscript can be checked without scrolling the
+//! whole of it through the editor's viewport.
+
+use crate::spellcheck::{SpellState, Suggestion};
+use crate::text_encoding::{self, StreamDecoder};
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tauri::{Emitter, Manager};
+
+/// Event sent to the window that asked for a report while a large one is
+/// put together.
+pub const SPELL_REPORT_PROGRESS_EVENT: &str = "spell-report-progress";
+
+/// Inputs smaller than this are done too quickly to report progress on.
+const PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Text is checked this much at a time, so the dictionaries are never held
+/// for long and the editor's own checks can get in between.
+const CHECK_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Corrections offered for each misspelled word.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Only the most frequent misspellings get suggestions: hunspell can take a
+/// good fraction of a second over each one.
+const MAX_SUGGESTED_WORDS: usize = 200;
+
+/// Positions kept for each misspelled word; the count goes on past them.
+const MAX_OCCURRENCES: usize = 100;
+
+/// Where a word starts. Lines and columns count from 1, offsets from 0.
+/// `\n`, `\r\n` and a lone `\r` each end a line. The byte offset is into
+/// the text as UTF-8, which for a file is the decoded text, not the file's
+/// own bytes. Columns and character offsets count Unicode scalar values, not
+/// UTF-16 units, with `\r\n` counted once as the editor shows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextPosition {
+    pub byte_offset: u64,
+    pub char_offset: u64,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Default for TextPosition {
+    fn default() -> Self {
+        Self {
+            byte_offset: 0,
+            char_offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    pub count: u64,
+    /// Empty for words past the most frequent few, which aren't looked up.
+    pub suggestions: Vec<Suggestion>,
+    /// The first occurrences, in document order.
+    pub occurrences: Vec<TextPosition>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellReport {
+    pub words_checked: u64,
+    /// Occurrences of all the misspelled words together.
+    pub misspelled_count: u64,
+    /// Most frequent first, then in the order they first appear.
+    pub misspellings: Vec<Misspelling>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpellReportProgress {
+    /// `"checking"` counts bytes read, `"suggesting"` misspellings looked up.
+    stage: &'static str,
+    done: u64,
+    total: u64,
+}
+
+/// Letters and the combining marks that go on them, as the editor's
+/// `[\p{L}\p{M}]` word pattern matches.
+fn is_word_char(character: char) -> bool {
+    character.is_alphabetic()
+        || matches!(
+            character,
+            '\u{0300}'..='\u{036F}'
+                | '\u{1AB0}'..='\u{1AFF}'
+                | '\u{1DC0}'..='\u{1DFF}'
+                | '\u{20D0}'..='\u{20FF}'
+                | '\u{FE20}'..='\u{FE2F}'
+        )
+}
+
+/// Splits text pushed in arbitrary chunks into words the way the editor
+/// does: runs of letters, joined by single apostrophes as in "don't".
+#[derive(Default)]
+struct WordScanner {
+    /// Where the next character goes.
+    position: TextPosition,
+    word: String,
+    word_start: TextPosition,
+    /// An apostrophe after `word`, which joins it to a letter that follows.
+    apostrophe: Option<char>,
+    /// The last character was `\r`, which a `\n` joins into one line break.
+    after_carriage_return: bool,
+}
+
+impl WordScanner {
+    fn push(&mut self, text: &str, words: &mut Vec<(String, TextPosition)>) {
+        for character in text.chars() {
+            if is_word_char(character) {
+                if self.word.is_empty() {
+                    self.word_start = self.position;
+                }
+                if let Some(apostrophe) = self.apostrophe.take() {
+                    self.word.push(apostrophe);
+                }
+                self.word.push(character);
+            } else if matches!(character, '\'' | '’')
+                && !self.word.is_empty()
+                && self.apostrophe.is_none()
+            {
+                self.apostrophe = Some(character);
+            } else {
+                self.end_word(words);
+            }
+
+            self.position.byte_offset += character.len_utf8() as u64;
+            let ends_crlf = character == '\n' && self.after_carriage_return;
+            self.after_carriage_return = character == '\r';
+            if ends_crlf {
+                continue;
+            }
+            self.position.char_offset += 1;
+            if matches!(character, '\n' | '\r') {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+    }
+
+    fn end_word(&mut self, words: &mut Vec<(String, TextPosition)>) {
+        self.apostrophe = None;
+        if !self.word.is_empty() {
+            words.push((std::mem::take(&mut self.word), self.word_start));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Occurrences {
+    count: u64,
+    positions: Vec<TextPosition>,
+}
+
+/// Checks text pushed in arbitrary chunks, asking `check` about each
+/// distinct word only once however often it appears.
+#[derive(Default)]
+struct ReportBuilder {
+    scanner: WordScanner,
+    words_checked: u64,
+    verdicts: HashMap<String, bool>,
+    misspelled: HashMap<String, Occurrences>,
+}
+
+impl ReportBuilder {
+    fn push(
+        &mut self,
+        text: &str,
+        check: &mut impl FnMut(&[String]) -> Result<Vec<bool>, String>,
+    ) -> Result<(), String> {
+        let mut words = Vec::new();
+        self.scanner.push(text, &mut words);
+        self.record(words, check)
+    }
+
+    fn record(
+        &mut self,
+        words: Vec<(String, TextPosition)>,
+        check: &mut impl FnMut(&[String]) -> Result<Vec<bool>, String>,
+    ) -> Result<(), String> {
+        let mut unknown: Vec<String> = words
+            .iter()
+            .filter(|(word, _)| !self.verdicts.contains_key(word))
+            .map(|(word, _)| word.clone())
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            let verdicts = check(&unknown)?;
+            self.verdicts.extend(unknown.into_iter().zip(verdicts));
+        }
+
+        for (word, position) in words {
+            self.words_checked += 1;
+            if self.verdicts.get(&word) != Some(&false) {
+                continue;
+            }
+            let occurrences = self.misspelled.entry(word).or_default();
+            occurrences.count += 1;
+            if occurrences.positions.len() < MAX_OCCURRENCES {
+                occurrences.positions.push(position);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the last word and lists the misspellings, without suggestions.
+    fn finish(
+        mut self,
+        check: &mut impl FnMut(&[String]) -> Result<Vec<bool>, String>,
+    ) -> Result<SpellReport, String> {
+        let mut words = Vec::new();
+        self.scanner.end_word(&mut words);
+        self.record(words, check)?;
+
+        let mut misspellings: Vec<Misspelling> = self
+            .misspelled
+            .into_iter()
+            .map(|(word, occurrences)| Misspelling {
+                word,
+                count: occurrences.count,
+                suggestions: Vec::new(),
+                occurrences: occurrences.positions,
+            })
+            .collect();
+        misspellings.sort_by_key(|misspelling| {
+            (
+                std::cmp::Reverse(misspelling.count),
+                misspelling.occurrences[0].byte_offset,
+            )
+        });
+        Ok(SpellReport {
+            words_checked: self.words_checked,
+            misspelled_count: misspellings
+                .iter()
+                .map(|misspelling| misspelling.count)
+                .sum(),
+            misspellings,
+        })
+    }
+}
+
+/// Looks up corrections for the most frequent misspellings, reporting each
+/// one done to `progress`.
+fn add_suggestions(
+    report: &mut SpellReport,
+    mut suggest: impl FnMut(&str) -> Result<Vec<Suggestion>, String>,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<(), String> {
+    let total = report.misspellings.len().min(MAX_SUGGESTED_WORDS);
+    for (index, misspelling) in report.misspellings.iter_mut().take(total).enumerate() {
+        let mut suggestions = suggest(&misspelling.word)?;
+        suggestions.truncate(MAX_SUGGESTIONS);
+        misspelling.suggestions = suggestions;
+        progress(index as u64 + 1, total as u64);
+    }
+    Ok(())
+}
+
+/// Reports on `text`, checking it a chunk at a time.
+fn report_for_text(
+    text: &str,
+    mut check: impl FnMut(&[String]) -> Result<Vec<bool>, String>,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<SpellReport, String> {
+    let mut builder = ReportBuilder::default();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = (start + CHECK_CHUNK_BYTES).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        builder.push(&text[start..end], &mut check)?;
+        progress(end as u64, text.len() as u64);
+        start = end;
+    }
+    builder.finish(&mut check)
+}
+
+/// Reports on what `reader` holds, `total` bytes of it, decoded the way the
+/// open stream would decode it.
+fn report_for_reader(
+    reader: impl Read,
+    forced_encoding: Option<&'static Encoding>,
+    total: u64,
+    mut check: impl FnMut(&[String]) -> Result<Vec<bool>, String>,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<SpellReport, String> {
+    let mut document = text_encoding::sample_document(reader, forced_encoding)
+        .map_err(|error| format!("Unable to read file: {error}"))?;
+
+    let mut decoder = StreamDecoder::new(document.encoding);
+    let mut builder = ReportBuilder::default();
+    let mut buffer = vec![0; CHECK_CHUNK_BYTES];
+    let mut done = document.bom.len() as u64;
+    loop {
+        let read = document
+            .reader
+            .read(&mut buffer)
+            .map_err(|error| format!("Unable to read file: {error}"))?;
+        builder.push(&decoder.decode(&buffer[..read], read == 0)?, &mut check)?;
+        if read == 0 {
+            return builder.finish(&mut check);
+        }
+        done += read as u64;
+        progress(done.min(total), total);
+    }
+}
+
+/// Sends progress to `window` for inputs large enough to take a while.
+fn progress_reporter<'a>(
+    window: &'a tauri::WebviewWindow,
+    stage: &'static str,
+    size_bytes: u64,
+) -> impl FnMut(u64, u64) + 'a {
+    move |done, total| {
+        if size_bytes < PROGRESS_MIN_BYTES {
+            return;
+        }
+        let _ = window.emit_to(
+            window.label(),
+            SPELL_REPORT_PROGRESS_EVENT,
+            SpellReportProgress { stage, done, total },
+        );
+    }
+}
+
+fn ensure_loaded(state: &SpellState) -> Result<(), String> {
+    if state.is_loaded()? {
+        Ok(())
+    } else {
+        Err("No spell-check dictionary is loaded".to_string())
+    }
+}
+
+/// Reports on the file at `path` in `encoding`, or the one it is detected
+/// to be in, against the loaded dictionaries.
+pub fn report_for_file(
+    window: &tauri::WebviewWindow,
+    state: &SpellState,
+    path: &Path,
+    forced_encoding: Option<&'static Encoding>,
+) -> Result<SpellReport, String> {
+    ensure_loaded(state)?;
+    let file = File::open(path)
+        .map_err(|error| format!("Unable to open file '{}': {error}", path.display()))?;
+    let size_bytes = file
+        .metadata()
+        .map_err(|error| format!("Unable to open file '{}': {error}", path.display()))?
+        .len();
+    let mut report = report_for_reader(
+        file,
+        forced_encoding,
+        size_bytes,
+        |words| state.check_words(words),
+        progress_reporter(window, "checking", size_bytes),
+    )?;
+    add_suggestions(
+        &mut report,
+        |word| state.suggest(word),
+        progress_reporter(window, "suggesting", size_bytes),
+    )?;
+    Ok(report)
+}
+
+/// Lists every misspelled word in `text`, for a document with unsaved
+/// changes. Checking runs on a blocking thread, so it holds up neither the
+/// main thread nor the async runtime.
+#[tauri::command]
+pub async fn spell_report_for_text(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    text: String,
+) -> Result<SpellReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SpellState>();
+        ensure_loaded(&state)?;
+        let size_bytes = text.len() as u64;
+        let mut report = report_for_text(
+            &text,
+            |words| state.check_words(words),
+            progress_reporter(&window, "checking", size_bytes),
+        )?;
+        add_suggestions(
+            &mut report,
+            |word| state.suggest(word),
+            progress_reporter(&window, "suggesting", size_bytes),
+        )?;
+        Ok(report)
+    })
+    .await
+    .map_err(|error| format!("Unable to check spelling: {error}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Treats words with a doubled letter as misspelled.
+    fn check(words: &[String]) -> Result<Vec<bool>, String> {
+        Ok(words
+            .iter()
+            .map(|word| {
+                let characters: Vec<char> = word.chars().collect();
+                !characters.windows(2).any(|pair| pair[0] == pair[1])
+            })
+            .collect())
+    }
+
+    fn report(text: &str) -> SpellReport {
+        report_for_text(text, check, |_, _| {}).unwrap()
+    }
+
+    fn words(text: &str) -> Vec<String> {
+        let mut scanner = WordScanner::default();
+        let mut words = Vec::new();
+        scanner.push(text, &mut words);
+        scanner.end_word(&mut words);
+        words.into_iter().map(|(word, _)| word).collect()
+    }
+
+    #[test]
+    fn splits_words_like_the_editor() {
+        assert_eq!(
+            words("don't it’s 'quoted' rock'n'roll"),
+            ["don't", "it’s", "quoted", "rock'n'roll"]
+        );
+        assert_eq!(
+            words("a''b x2y café cafe\u{301}"),
+            ["a", "b", "x", "y", "café", "cafe\u{301}"]
+        );
+        assert!(words("3.14 -- ...").is_empty());
+    }
+
+    #[test]
+    fn reports_positions_counts_and_order() {
+        let report = report("Ça speeling\nok ok\n  wrrong speeling");
+        assert_eq!(report.words_checked, 6);
+        assert_eq!(report.misspelled_count, 3);
+        let found: Vec<(&str, u64)> = report
+            .misspellings
+            .iter()
+            .map(|misspelling| (misspelling.word.as_str(), misspelling.count))
+            .collect();
+        assert_eq!(found, [("speeling", 2), ("wrrong", 1)]);
+
+        // "Ç" takes two bytes but one character and column.
+        let speeling = &report.misspellings[0].occurrences;
+        assert_eq!(
+            speeling[0],
+            TextPosition {
+                byte_offset: 4,
+                char_offset: 3,
+                line: 1,
+                column: 4,
+            }
+        );
+        assert_eq!(
+            speeling[1],
+            TextPosition {
+                byte_offset: 28,
+                char_offset: 27,
+                line: 3,
+                column: 10,
+            }
+        );
+
+        // A "\r\n" is one line break and one character, a lone "\r" another.
+        let report = self::report("ok\r\nok\rspeeling");
+        assert_eq!(
+            report.misspellings[0].occurrences[0],
+            TextPosition {
+                byte_offset: 7,
+                char_offset: 6,
+                line: 3,
+                column: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn chunk_boundaries_do_not_change_the_report() {
+        let text = "Zwölf Boxkämpfer jaggen Viktor quer über den Sylter Deich, don't sttop.\r\n"
+            .repeat(500);
+        let whole = report(&text);
+        for size in [1, 7, 4096] {
+            let mut builder = ReportBuilder::default();
+            let mut check = check;
+            let mut start = 0;
+            while start < text.len() {
+                let mut end = (start + size).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end += 1;
+                }
+                builder.push(&text[start..end], &mut check).unwrap();
+                start = end;
+            }
+            assert_eq!(
+                builder.finish(&mut check).unwrap(),
+                whole,
+                "chunks of {size}"
+            );
+        }
+        assert_eq!(whole.words_checked, 500 * 11);
+        assert_eq!(whole.misspellings[0].count, 500);
+        assert_eq!(whole.misspellings[0].occurrences.len(), MAX_OCCURRENCES);
+    }
+
+    #[test]
+    fn reads_files_and_reports_progress() {
+        let text = "Déjà vu, sso to speak.\n";
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        let mut progress = Vec::new();
+        let from_file = report_for_reader(
+            &latin1[..],
+            Some(encoding_rs::WINDOWS_1252),
+            latin1.len() as u64,
+            check,
+            |done, total| progress.push((done, total)),
+        )
+        .unwrap();
+        assert_eq!(from_file, report(text));
+        assert_eq!(
+            progress.last(),
+            Some(&(latin1.len() as u64, latin1.len() as u64))
+        );
+
+        let mut frequent = report("aab aab cc dd");
+        let mut looked_up = Vec::new();
+        add_suggestions(
+            &mut frequent,
+            |word| {
+                looked_up.push(word.to_string());
+                Ok(Vec::new())
+            },
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(looked_up, ["aab", "cc", "dd"]);
+    }
+}
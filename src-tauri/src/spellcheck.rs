@@ -1,28 +1,81 @@
+use crate::dictionary_package;
 use hunspell_rs::{CheckResult, Hunspell};
 use serde::Serialize;
+use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::Manager;
+use whatlang::{Detector, Lang};
 
-/// System locations searched for hunspell dictionaries, in priority order.
-/// These are the same files LibreOffice reads.
+/// System locations searched for hunspell dictionaries, in priority order,
+/// after the user's own. These are the same files LibreOffice reads.
 const DICTIONARY_DIRS: &[&str] = &[
     "/usr/share/hunspell",
     "/usr/share/myspell/dicts",
     "/usr/share/myspell",
 ];
 
+/// Subdirectory of the user's data directory (`$XDG_DATA_HOME`, usually
+/// `~/.local/share`) and of each system data directory searched for
+/// dictionaries, as hunspell itself does.
+const USER_DICTIONARY_DIR: &str = "hunspell";
+
+/// The system data directories when `XDG_DATA_DIRS` is unset or empty, as
+/// the base directory specification has it.
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// Setting with extra directories to search before all the others: a list of
+/// absolute paths, for dictionaries kept with a project, say.
+const DICTIONARY_DIRS_SETTING: &str = "spellDictionaryDirs";
+
 const PERSONAL_DICTIONARY_FILE: &str = "personal_dictionary.txt";
 
+/// Record of the dictionary packages installed into the user's directory,
+/// in the app's data directory.
+const INSTALLED_PACKAGES_FILE: &str = "installed_dictionaries.json";
+
+/// Most of a document's text looked at when detecting its language. A few
+/// paragraphs are plenty, and the detector's cost grows with its input.
+const DETECTION_SAMPLE_CHARS: usize = 8 * 1024;
+
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct DictionaryInfo {
     code: String,
     label: String,
+    /// The directory its files are in.
+    directory: String,
+    /// Whether it is in the user's own dictionary directory, where it can be
+    /// removed from.
+    removable: bool,
+}
+
+/// A suggested correction and the dictionary that offered it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    word: String,
+    dictionary: String,
+}
+
+/// An installed dictionary whose language the text may be in.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageGuess {
+    code: String,
+    label: String,
+    /// The detected language's ISO 639-3 code, shared by every dictionary
+    /// for it, such as `eng` for both `en_GB` and `en_US`.
+    language: String,
+    /// How sure the detector is, from 0 to 1, that the text is in this
+    /// language rather than one ranked below it. `None` for the last, which
+    /// had nothing left to be compared with.
+    confidence: Option<f64>,
 }
 
 struct LoadedDictionary {
+    code: String,
     hunspell: Hunspell,
 }
 
@@ -40,12 +93,72 @@ struct PersonalDictionary {
 
 #[derive(Default)]
 pub struct SpellState {
-    dictionary: Mutex<Option<LoadedDictionary>>,
+    /// Every dictionary in use, in the order they were asked for. A word is
+    /// spelled correctly if any of them accepts it.
+    dictionaries: Mutex<Vec<LoadedDictionary>>,
     personal: Mutex<PersonalDictionary>,
     /// Words ignored for this session only. Kept separately from `personal`
-    /// (which is persisted) so reloading the dictionary can still restore
+    /// (which is persisted) so reloading the dictionaries can still restore
     /// them; a fresh `Hunspell` instance otherwise has no memory of them.
     ignored: Mutex<Vec<String>>,
+    /// Held while a dictionary is installed or removed, so the record of
+    /// installed packages is read and written by one at a time.
+    installing: Mutex<()>,
+    /// Counts dictionary loads, so one that finishes after a later one
+    /// started is dropped instead of replacing newer dictionaries.
+    load_generation: Mutex<u64>,
+}
+
+impl SpellState {
+    /// Whether any dictionary is loaded to check against.
+    pub(crate) fn is_loaded(&self) -> Result<bool, String> {
+        let guard = self
+            .dictionaries
+            .lock()
+            .map_err(|error| format!("Spell state poisoned: {error}"))?;
+        Ok(!guard.is_empty())
+    }
+
+    /// Returns, aligned to `words`, whether each word is spelled correctly.
+    /// Words without alphabetic characters are always treated as correct.
+    pub(crate) fn check_words(&self, words: &[String]) -> Result<Vec<bool>, String> {
+        let guard = self
+            .dictionaries
+            .lock()
+            .map_err(|error| format!("Spell state poisoned: {error}"))?;
+
+        if guard.is_empty() {
+            return Ok(vec![true; words.len()]);
+        }
+
+        let results = words
+            .iter()
+            .map(|word| {
+                if !word.chars().any(char::is_alphabetic) {
+                    return true;
+                }
+                guard.iter().any(|dictionary| {
+                    dictionary.hunspell.check(word) == CheckResult::FoundInDictionary
+                })
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Suggestions from every loaded dictionary, each tagged with its code.
+    pub(crate) fn suggest(&self, word: &str) -> Result<Vec<Suggestion>, String> {
+        let guard = self
+            .dictionaries
+            .lock()
+            .map_err(|error| format!("Spell state poisoned: {error}"))?;
+
+        let lists = guard
+            .iter()
+            .map(|dictionary| (dictionary.code.as_str(), dictionary.hunspell.suggest(word)))
+            .collect();
+        Ok(merge_suggestions(lists))
+    }
 }
 
 /// Maps a dictionary code (e.g. `en_US`) to a human-readable label.
@@ -81,12 +194,85 @@ fn label_for_code(code: &str) -> String {
     }
 }
 
-/// Scans the system dictionary directories for `.dic`/`.aff` pairs.
-fn discover_dictionaries() -> Vec<DictionaryInfo> {
+/// Puts the directories searched for dictionaries in order, most preferred
+/// first: `configured`, then the entries of `dicpath` (`DICPATH`, which
+/// hunspell reads too), then the dictionary directory in `data_dir`, then
+/// the one in each entry of `data_dirs` (`XDG_DATA_DIRS`, which covers
+/// Flatpak runtimes and `/usr/local`), then the other system ones. Relative
+/// entries are skipped, as is any directory already listed under another
+/// spelling.
+fn search_dirs(
+    configured: Vec<PathBuf>,
+    dicpath: Option<&OsStr>,
+    data_dir: Option<PathBuf>,
+    data_dirs: Option<&OsStr>,
+) -> Vec<PathBuf> {
+    let data_dirs = data_dirs
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or(OsStr::new(DEFAULT_DATA_DIRS));
+    let candidates = configured
+        .into_iter()
+        .chain(dicpath.into_iter().flat_map(std::env::split_paths))
+        .chain(data_dir.map(|dir| dir.join(USER_DICTIONARY_DIR)))
+        .chain(std::env::split_paths(data_dirs).map(|dir| dir.join(USER_DICTIONARY_DIR)))
+        .chain(DICTIONARY_DIRS.iter().map(PathBuf::from));
+
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in candidates.filter(|dir| dir.is_absolute()) {
+        let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Reads the extra directories from the settings store. Anything that isn't
+/// a list of strings is ignored.
+fn configured_dirs(app: &tauri::AppHandle) -> Vec<PathBuf> {
+    use tauri_plugin_store::StoreExt;
+
+    let Ok(store) = app.store(crate::SETTINGS_STORE_FILE) else {
+        return Vec::new();
+    };
+    let Some(serde_json::Value::Array(entries)) = store.get(DICTIONARY_DIRS_SETTING) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| entry.as_str())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The user's own dictionary directory, where dictionaries are installed.
+fn user_dictionary_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .data_dir()
+        .map(|dir| dir.join(USER_DICTIONARY_DIR))
+        .map_err(|error| format!("Unable to resolve data directory: {error}"))
+}
+
+/// The directories searched for dictionaries, most preferred first.
+fn dictionary_dirs(app: &tauri::AppHandle) -> Vec<PathBuf> {
+    search_dirs(
+        configured_dirs(app),
+        std::env::var_os("DICPATH").as_deref(),
+        app.path().data_dir().ok(),
+        std::env::var_os("XDG_DATA_DIRS").as_deref(),
+    )
+}
+
+/// Scans `dirs` for `.dic`/`.aff` pairs. When several directories have a
+/// dictionary with the same code, the first one's is used, as
+/// `resolve_dictionary_paths` does.
+fn discover_dictionaries(dirs: &[PathBuf]) -> Vec<DictionaryInfo> {
     let mut seen_codes: Vec<String> = Vec::new();
     let mut dictionaries: Vec<DictionaryInfo> = Vec::new();
 
-    for dir in DICTIONARY_DIRS {
+    for dir in dirs {
         let entries = match fs::read_dir(dir) {
             Ok(entries) => entries,
             Err(_) => continue,
@@ -108,18 +294,126 @@ fn discover_dictionaries() -> Vec<DictionaryInfo> {
             dictionaries.push(DictionaryInfo {
                 label: label_for_code(&code),
                 code,
+                directory: dir.to_string_lossy().into_owned(),
+                removable: false,
             });
         }
     }
 
-    dictionaries.sort_by(|a, b| a.label.cmp(&b.label));
+    dictionaries.sort_by(|a, b| a.label.cmp(&b.label).then_with(|| a.code.cmp(&b.code)));
     dictionaries
 }
 
-/// Resolves the `.aff`/`.dic` path pair for a dictionary code.
-fn resolve_dictionary_paths(code: &str) -> Option<(PathBuf, PathBuf)> {
-    for dir in DICTIONARY_DIRS {
-        let base = Path::new(dir).join(code);
+/// The language detected for a dictionary code such as `en_US` or `pt-BR`.
+fn detected_language_for_code(code: &str) -> Option<Lang> {
+    let lang = code.split(['_', '-']).next()?.to_ascii_lowercase();
+    let language = match lang.as_str() {
+        "af" => Lang::Afr,
+        "ar" => Lang::Ara,
+        "az" => Lang::Aze,
+        "be" => Lang::Bel,
+        "bg" => Lang::Bul,
+        "bn" => Lang::Ben,
+        "ca" => Lang::Cat,
+        "cs" => Lang::Ces,
+        "da" => Lang::Dan,
+        "de" => Lang::Deu,
+        "el" => Lang::Ell,
+        "en" => Lang::Eng,
+        "eo" => Lang::Epo,
+        "es" => Lang::Spa,
+        "et" => Lang::Est,
+        "fa" => Lang::Pes,
+        "fi" => Lang::Fin,
+        "fr" => Lang::Fra,
+        "gu" => Lang::Guj,
+        "he" => Lang::Heb,
+        "hi" => Lang::Hin,
+        "hr" => Lang::Hrv,
+        "hu" => Lang::Hun,
+        "hy" => Lang::Hye,
+        "id" => Lang::Ind,
+        "it" => Lang::Ita,
+        "ka" => Lang::Kat,
+        "la" => Lang::Lat,
+        "lt" => Lang::Lit,
+        "lv" => Lang::Lav,
+        "mk" => Lang::Mkd,
+        "ml" => Lang::Mal,
+        "mr" => Lang::Mar,
+        // Nynorsk is close enough to Bokmål for the detector to tell it
+        // apart from other languages.
+        "nb" | "nn" | "no" => Lang::Nob,
+        "ne" => Lang::Nep,
+        "nl" => Lang::Nld,
+        "pa" => Lang::Pan,
+        "pl" => Lang::Pol,
+        "pt" => Lang::Por,
+        "ro" => Lang::Ron,
+        "ru" => Lang::Rus,
+        "si" => Lang::Sin,
+        "sk" => Lang::Slk,
+        "sl" => Lang::Slv,
+        "sr" => Lang::Srp,
+        "sv" => Lang::Swe,
+        "ta" => Lang::Tam,
+        "te" => Lang::Tel,
+        "th" => Lang::Tha,
+        "tl" => Lang::Tgl,
+        "tr" => Lang::Tur,
+        "uk" => Lang::Ukr,
+        "ur" => Lang::Urd,
+        "uz" => Lang::Uzb,
+        "vi" => Lang::Vie,
+        "yi" => Lang::Yid,
+        "zu" => Lang::Zul,
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Ranks `dictionaries` by how likely `text` is to be in their language,
+/// most likely first. The detector picks the best of the languages still in
+/// the running, which is then set aside, until it can't pick any more: the
+/// rest don't fit the text's script or have no detector profile, and are
+/// left out. Dictionaries for the same language keep their given order.
+fn rank_dictionaries(text: &str, dictionaries: &[DictionaryInfo]) -> Vec<LanguageGuess> {
+    let sample: String = text.chars().take(DETECTION_SAMPLE_CHARS).collect();
+    let mut candidates: Vec<Lang> = Vec::new();
+    for dictionary in dictionaries {
+        if let Some(language) = detected_language_for_code(&dictionary.code) {
+            if !candidates.contains(&language) {
+                candidates.push(language);
+            }
+        }
+    }
+
+    let mut ranked: Vec<LanguageGuess> = Vec::new();
+    while !candidates.is_empty() {
+        let last = candidates.len() == 1;
+        let Some(info) = Detector::with_allowlist(candidates.clone()).detect(&sample) else {
+            break;
+        };
+        candidates.retain(|language| *language != info.lang());
+        for dictionary in dictionaries {
+            if detected_language_for_code(&dictionary.code) == Some(info.lang()) {
+                ranked.push(LanguageGuess {
+                    code: dictionary.code.clone(),
+                    label: dictionary.label.clone(),
+                    language: info.lang().code().to_string(),
+                    confidence: (!last).then(|| info.confidence()),
+                });
+            }
+        }
+    }
+    ranked
+}
+
+/// Resolves the `.aff`/`.dic` path pair for a dictionary code, from the first
+/// of `dirs` that has both.
+fn resolve_dictionary_paths(dirs: &[PathBuf], code: &str) -> Option<(PathBuf, PathBuf)> {
+    for dir in dirs {
+        let base = dir.join(code);
         let aff = base.with_extension("aff");
         let dic = base.with_extension("dic");
         if aff.exists() && dic.exists() {
@@ -129,6 +423,27 @@ fn resolve_dictionary_paths(code: &str) -> Option<(PathBuf, PathBuf)> {
     None
 }
 
+/// Every dictionary found, with those in the user's own directory marked
+/// removable.
+fn list_dictionaries(app: &tauri::AppHandle) -> Vec<DictionaryInfo> {
+    let user_dir = user_dictionary_dir(app).ok();
+    let mut dictionaries = discover_dictionaries(&dictionary_dirs(app));
+    for dictionary in &mut dictionaries {
+        dictionary.removable = user_dir.as_deref() == Some(Path::new(&dictionary.directory));
+    }
+    dictionaries
+}
+
+fn installed_packages_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| format!("Unable to resolve data directory: {error}"))?;
+    fs::create_dir_all(&dir)
+        .map_err(|error| format!("Unable to create data directory: {error}"))?;
+    Ok(dir.join(INSTALLED_PACKAGES_FILE))
+}
+
 fn personal_dictionary_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let dir = app
         .path()
@@ -179,70 +494,168 @@ fn save_personal_words(personal: &PersonalDictionary) -> Result<(), String> {
     fs::write(path, contents).map_err(|error| format!("Unable to save personal dictionary: {error}"))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Interleaves each dictionary's suggestions by rank, so the best guess of
+/// every language comes first, and keeps only the first of any duplicates.
+fn merge_suggestions(lists: Vec<(&str, Vec<String>)>) -> Vec<Suggestion> {
+    let longest = lists
+        .iter()
+        .map(|(_, words)| words.len())
+        .max()
+        .unwrap_or(0);
+    let mut merged: Vec<Suggestion> = Vec::new();
+    for rank in 0..longest {
+        for (code, words) in &lists {
+            let Some(word) = words.get(rank) else {
+                continue;
+            };
+            if merged.iter().any(|existing| &existing.word == word) {
+                continue;
+            }
+            merged.push(Suggestion {
+                word: word.clone(),
+                dictionary: code.to_string(),
+            });
+        }
+    }
+    merged
+}
 
-    #[test]
-    fn discovers_and_checks_english() {
-        let dictionaries = discover_dictionaries();
-        let Some((aff, dic)) = resolve_dictionary_paths("en_US") else {
-            eprintln!("en_US dictionary not installed; skipping");
-            return;
-        };
-        assert!(dictionaries.iter().any(|entry| entry.code == "en_US"));
+#[tauri::command]
+pub fn spell_list_dictionaries(app: tauri::AppHandle) -> Vec<DictionaryInfo> {
+    list_dictionaries(&app)
+}
 
-        let hunspell = Hunspell::new(&aff.to_string_lossy(), &dic.to_string_lossy());
-        assert_eq!(hunspell.check("hello"), CheckResult::FoundInDictionary);
-        assert_eq!(hunspell.check("teh"), CheckResult::MissingInDictionary);
-        assert!(hunspell.suggest("teh").iter().any(|s| s == "the"));
-    }
+/// Ranks the installed dictionaries by how likely `text`, usually the start
+/// of a document, is to be in their language. Empty when none fit.
+#[tauri::command]
+pub fn spell_detect_language(app: tauri::AppHandle, text: String) -> Vec<LanguageGuess> {
+    rank_dictionaries(&text, &discover_dictionaries(&dictionary_dirs(&app)))
 }
 
+/// Installs the dictionaries in a LibreOffice extension (`.oxt`) or a zip of
+/// `.aff`/`.dic` files into the user's dictionary directory and returns them
+/// as now listed. Runs as an async command because unpacking a large
+/// thesaurus must not hold up the main thread. Dictionaries already loaded
+/// keep their old words until reloaded.
 #[tauri::command]
-pub fn spell_list_dictionaries() -> Vec<DictionaryInfo> {
-    discover_dictionaries()
+pub async fn spell_install_dictionary(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SpellState>,
+    package_path: String,
+) -> Result<Vec<DictionaryInfo>, String> {
+    let _installing = state
+        .installing
+        .lock()
+        .map_err(|error| format!("Spell state poisoned: {error}"))?;
+
+    let dir = user_dictionary_dir(&app)?;
+    fs::create_dir_all(&dir)
+        .map_err(|error| format!("Unable to create dictionary directory: {error}"))?;
+    let records_path = installed_packages_path(&app)?;
+    let mut packages = dictionary_package::read_records(&records_path);
+    let installed = dictionary_package::install_package(Path::new(&package_path), &dir, &packages)?;
+    let codes = installed.codes.clone();
+    dictionary_package::record_install(&dir, &mut packages, installed);
+    dictionary_package::save_records(&records_path, &packages)?;
+
+    Ok(list_dictionaries(&app)
+        .into_iter()
+        .filter(|dictionary| codes.contains(&dictionary.code))
+        .collect())
 }
 
+/// Removes a dictionary from the user's dictionary directory, with the
+/// thesaurus and hyphenation files of its package once nothing else from the
+/// package is left. Dictionaries elsewhere are never touched. A loaded copy
+/// stays in use until the dictionaries are reloaded.
 #[tauri::command]
-pub fn spell_load_dictionary(
+pub fn spell_uninstall_dictionary(
     app: tauri::AppHandle,
     state: tauri::State<'_, SpellState>,
     code: String,
-) -> Result<bool, String> {
-    let (aff, dic) = match resolve_dictionary_paths(&code) {
-        Some(paths) => paths,
-        None => return Ok(false),
-    };
+) -> Result<(), String> {
+    let _installing = state
+        .installing
+        .lock()
+        .map_err(|error| format!("Spell state poisoned: {error}"))?;
 
-    let mut hunspell = Hunspell::new(&aff.to_string_lossy(), &dic.to_string_lossy());
+    let dir = user_dictionary_dir(&app)?;
+    let records_path = installed_packages_path(&app)?;
+    let mut packages = dictionary_package::read_records(&records_path);
+    dictionary_package::uninstall(&dir, &code, &mut packages)?;
+    dictionary_package::save_records(&records_path, &packages)
+}
 
+/// Replaces the loaded dictionaries with those for `codes`, in that order,
+/// and returns the codes that were found. Codes with no installed dictionary
+/// are skipped. Async so that building the dictionaries doesn't block the
+/// main thread; checks keep using the old ones until the new ones are in.
+#[tauri::command]
+pub async fn spell_load_dictionary(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SpellState>,
+    codes: Vec<String>,
+) -> Result<Vec<String>, String> {
     ensure_personal_loaded(&state, &app)?;
+    let generation = {
+        let mut counter = state
+            .load_generation
+            .lock()
+            .map_err(|error| format!("Spell state poisoned: {error}"))?;
+        *counter += 1;
+        *counter
+    };
+
+    let dirs = dictionary_dirs(&app);
+    let mut dictionaries: Vec<LoadedDictionary> = Vec::new();
+    for code in codes {
+        if dictionaries.iter().any(|loaded| loaded.code == code) {
+            continue;
+        }
+        let Some((aff, dic)) = resolve_dictionary_paths(&dirs, &code) else {
+            continue;
+        };
+        let hunspell = Hunspell::new(&aff.to_string_lossy(), &dic.to_string_lossy());
+        dictionaries.push(LoadedDictionary { code, hunspell });
+    }
+    let loaded = dictionaries
+        .iter()
+        .map(|loaded| loaded.code.clone())
+        .collect();
+
+    let mut guard = state
+        .dictionaries
+        .lock()
+        .map_err(|error| format!("Spell state poisoned: {error}"))?;
+    let latest_generation = *state
+        .load_generation
+        .lock()
+        .map_err(|error| format!("Spell state poisoned: {error}"))?;
+    if latest_generation != generation {
+        return Ok(loaded);
+    }
+
+    // Read under the dictionaries lock: adding and ignoring words update
+    // their lists before taking it, so a word added meanwhile is either in
+    // these lists or added to the new instances once the lock is released.
     let personal_words = state
         .personal
         .lock()
         .map_err(|error| format!("Spell state poisoned: {error}"))?
         .words
         .clone();
-    for word in personal_words {
-        hunspell.add(&word);
-    }
-
     let ignored_words = state
         .ignored
         .lock()
         .map_err(|error| format!("Spell state poisoned: {error}"))?
         .clone();
-    for word in ignored_words {
-        hunspell.add(&word);
+    for dictionary in &mut dictionaries {
+        for word in personal_words.iter().chain(&ignored_words) {
+            dictionary.hunspell.add(word);
+        }
     }
-
-    let mut guard = state
-        .dictionary
-        .lock()
-        .map_err(|error| format!("Spell state poisoned: {error}"))?;
-    *guard = Some(LoadedDictionary { hunspell });
-    Ok(true)
+    *guard = dictionaries;
+    Ok(loaded)
 }
 
 /// Returns, aligned to `words`, whether each word is spelled correctly.
@@ -252,45 +665,20 @@ pub fn spell_check_words(
     state: tauri::State<'_, SpellState>,
     words: Vec<String>,
 ) -> Result<Vec<bool>, String> {
-    let guard = state
-        .dictionary
-        .lock()
-        .map_err(|error| format!("Spell state poisoned: {error}"))?;
-
-    let Some(dictionary) = guard.as_ref() else {
-        return Ok(vec![true; words.len()]);
-    };
-
-    let results = words
-        .iter()
-        .map(|word| {
-            if !word.chars().any(char::is_alphabetic) {
-                return true;
-            }
-            dictionary.hunspell.check(word) == CheckResult::FoundInDictionary
-        })
-        .collect();
-
-    Ok(results)
+    state.check_words(&words)
 }
 
+/// Suggestions from every loaded dictionary, each tagged with its code.
 #[tauri::command]
 pub fn spell_suggest(
     state: tauri::State<'_, SpellState>,
     word: String,
-) -> Result<Vec<String>, String> {
-    let guard = state
-        .dictionary
-        .lock()
-        .map_err(|error| format!("Spell state poisoned: {error}"))?;
-
-    match guard.as_ref() {
-        Some(dictionary) => Ok(dictionary.hunspell.suggest(&word)),
-        None => Ok(Vec::new()),
-    }
+) -> Result<Vec<Suggestion>, String> {
+    state.suggest(&word)
 }
 
-/// Adds a word to the active dictionary and persists it to the personal list.
+/// Adds a word to every loaded dictionary and persists it to the personal
+/// list.
 #[tauri::command]
 pub fn spell_add_word(
     app: tauri::AppHandle,
@@ -299,26 +687,28 @@ pub fn spell_add_word(
 ) -> Result<(), String> {
     ensure_personal_loaded(&state, &app)?;
 
-    {
-        let mut guard = state
-            .dictionary
+    // The list is updated first so a load running meanwhile can't miss it.
+    let saved = {
+        let mut personal = state
+            .personal
             .lock()
             .map_err(|error| format!("Spell state poisoned: {error}"))?;
-        if let Some(dictionary) = guard.as_mut() {
-            dictionary.hunspell.add(&word);
+        if personal.words.iter().any(|existing| existing == &word) {
+            Ok(())
+        } else {
+            personal.words.push(word.clone());
+            save_personal_words(&personal)
         }
-    }
+    };
 
-    let mut personal = state
-        .personal
+    let mut guard = state
+        .dictionaries
         .lock()
         .map_err(|error| format!("Spell state poisoned: {error}"))?;
-
-    if personal.words.iter().any(|existing| existing == &word) {
-        return Ok(());
+    for dictionary in guard.iter_mut() {
+        dictionary.hunspell.add(&word);
     }
-    personal.words.push(word);
-    save_personal_words(&personal)
+    saved
 }
 
 /// Returns the personal dictionary's words, sorted for display.
@@ -338,9 +728,9 @@ pub fn spell_list_added_words(
     Ok(words)
 }
 
-/// Removes a word from the personal dictionary. The active in-memory
-/// dictionary isn't touched here since libhunspell has no "unlearn" call;
-/// callers should reload the dictionary afterwards to pick up the change.
+/// Removes a word from the personal dictionary. The loaded in-memory
+/// dictionaries aren't touched here since libhunspell has no "unlearn" call;
+/// callers should reload the dictionaries afterwards to pick up the change.
 #[tauri::command]
 pub fn spell_remove_word(
     app: tauri::AppHandle,
@@ -358,31 +748,166 @@ pub fn spell_remove_word(
     save_personal_words(&personal)
 }
 
-/// Adds a word to the active dictionary for this session only (not persisted).
-/// Recorded separately so a later dictionary reload (e.g. after removing a
-/// personal word) can restore it, since a fresh `Hunspell` instance has no
-/// memory of words added to the previous one.
+/// Adds a word to every loaded dictionary for this session only (not
+/// persisted). Recorded separately so a later dictionary reload (e.g. after
+/// removing a personal word) can restore it, since a fresh `Hunspell` instance
+/// has no memory of words added to the previous one.
 #[tauri::command]
-pub fn spell_ignore_word(
-    state: tauri::State<'_, SpellState>,
-    word: String,
-) -> Result<(), String> {
+pub fn spell_ignore_word(state: tauri::State<'_, SpellState>, word: String) -> Result<(), String> {
     {
-        let mut guard = state
-            .dictionary
+        let mut ignored = state
+            .ignored
             .lock()
             .map_err(|error| format!("Spell state poisoned: {error}"))?;
-        if let Some(dictionary) = guard.as_mut() {
-            dictionary.hunspell.add(&word);
+        if !ignored.iter().any(|existing| existing == &word) {
+            ignored.push(word.clone());
         }
     }
 
-    let mut ignored = state
-        .ignored
+    let mut guard = state
+        .dictionaries
         .lock()
         .map_err(|error| format!("Spell state poisoned: {error}"))?;
-    if !ignored.iter().any(|existing| existing == &word) {
-        ignored.push(word);
+    for dictionary in guard.iter_mut() {
+        dictionary.hunspell.add(&word);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_and_checks_english() {
+        let dirs = search_dirs(Vec::new(), None, None, None);
+        let dictionaries = discover_dictionaries(&dirs);
+        let Some((aff, dic)) = resolve_dictionary_paths(&dirs, "en_US") else {
+            eprintln!("en_US dictionary not installed; skipping");
+            return;
+        };
+        assert!(dictionaries.iter().any(|entry| entry.code == "en_US"));
+
+        let hunspell = Hunspell::new(&aff.to_string_lossy(), &dic.to_string_lossy());
+        assert_eq!(hunspell.check("hello"), CheckResult::FoundInDictionary);
+        assert_eq!(hunspell.check("teh"), CheckResult::MissingInDictionary);
+        assert!(hunspell.suggest("teh").iter().any(|s| s == "the"));
+    }
+
+    #[test]
+    fn searches_user_dirs_before_system_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        let dicpath = root.path().join("dicpath");
+        let data = root.path().join("data");
+        let shared = root.path().join("shared");
+        let install = |dir: &Path, code: &str| {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join(format!("{code}.aff")), "SET UTF-8\n").unwrap();
+            fs::write(dir.join(format!("{code}.dic")), "1\nword\n").unwrap();
+        };
+        install(&project, "en_GB");
+        install(&dicpath, "en_GB");
+        install(&dicpath, "fr_FR");
+        install(&shared.join(USER_DICTIONARY_DIR), "de_DE");
+
+        let joined = std::env::join_paths([&dicpath, &project]).unwrap();
+        let dirs = search_dirs(
+            vec![project.clone(), PathBuf::from("relative")],
+            Some(&joined),
+            Some(data.clone()),
+            Some(shared.as_os_str()),
+        );
+        assert_eq!(
+            dirs[..4],
+            [
+                project.clone(),
+                dicpath.clone(),
+                data.join(USER_DICTIONARY_DIR),
+                shared.join(USER_DICTIONARY_DIR)
+            ]
+        );
+        assert!(dirs[4..].iter().eq(DICTIONARY_DIRS.iter().map(Path::new)));
+
+        let directory_of = |code: &str| {
+            discover_dictionaries(&dirs)
+                .into_iter()
+                .find(|dictionary| dictionary.code == code)
+                .map(|dictionary| PathBuf::from(dictionary.directory))
+        };
+        assert_eq!(directory_of("en_GB"), Some(project));
+        assert_eq!(directory_of("fr_FR"), Some(dicpath));
+        assert_eq!(
+            directory_of("de_DE"),
+            Some(shared.join(USER_DICTIONARY_DIR))
+        );
+    }
+
+    #[test]
+    fn merges_suggestions_by_rank() {
+        let words = |list: &[&str]| list.iter().map(|word| word.to_string()).collect();
+        let merged = merge_suggestions(vec![
+            ("en_US", words(&["house", "horse", "mouse"])),
+            ("de_DE", words(&["Haus", "house"])),
+        ]);
+        let tagged: Vec<(&str, &str)> = merged
+            .iter()
+            .map(|suggestion| (suggestion.word.as_str(), suggestion.dictionary.as_str()))
+            .collect();
+        assert_eq!(
+            tagged,
+            [
+                ("house", "en_US"),
+                ("Haus", "de_DE"),
+                ("horse", "en_US"),
+                ("mouse", "en_US"),
+            ]
+        );
+        assert!(merge_suggestions(Vec::new()).is_empty());
+    }
+
+    fn dictionaries(codes: &[&str]) -> Vec<DictionaryInfo> {
+        codes
+            .iter()
+            .map(|code| DictionaryInfo {
+                code: code.to_string(),
+                label: label_for_code(code),
+                directory: DICTIONARY_DIRS[0].to_string(),
+                removable: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_dictionaries_by_detected_language() {
+        let installed = dictionaries(&["en_GB", "en_US", "es_ES", "fr_FR", "xx_XX"]);
+        let codes = |text: &str| -> Vec<String> {
+            rank_dictionaries(text, &installed)
+                .into_iter()
+                .map(|guess| guess.code)
+                .collect()
+        };
+
+        let french = "Nous sommes allés au marché ce matin pour acheter des légumes, \
+                      du pain et un peu de fromage pour le déjeuner.";
+        assert_eq!(codes(french)[0], "fr_FR");
+
+        let english = "We went to the market this morning to buy vegetables, some \
+                       bread and a little cheese for lunch.";
+        let ranked = rank_dictionaries(english, &installed);
+        assert_eq!(ranked[0].code, "en_GB");
+        assert_eq!(ranked[1].code, "en_US");
+        assert_eq!(ranked[0].language, "eng");
+        assert_eq!(ranked[1].language, "eng");
+        assert_eq!(ranked[0].confidence, ranked[1].confidence);
+        assert!(ranked[0].confidence.unwrap_or(0.0) > 0.5);
+        // The unknown code is never ranked, and the last one has nothing to
+        // be compared with.
+        assert_eq!(ranked.len(), 4);
+        assert_eq!(ranked[3].confidence, None);
+
+        // None of these fit Cyrillic text.
+        assert!(codes("Мы ходили утром на рынок за овощами и хлебом.").is_empty());
+        assert!(codes("").is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The path argument that stands for standard input, and the save path the
+/// frontend uses for the `--stdout` document.
+pub const STDIO_ARG: &str = "-";
+
+/// Makes saves of the launch document go to standard output on exit.
+pub const STDOUT_FLAG: &str = "--stdout";
+
+/// Standard input for the `-` argument, copied to a private file so the
+/// frontend can stream it like any other file without the whole input held
+/// in memory. The file is removed when this is dropped.
+pub struct StdinSpool {
+    path: PathBuf,
+    size_bytes: u64,
+}
+
+impl StdinSpool {
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+
+    /// Opens the spooled input for reading. The open file stays readable
+    /// after the spool is dropped.
+    pub fn open(&self) -> Result<File, String> {
+        File::open(&self.path)
+            .map_err(|error| format!("Unable to read standard input buffer: {error}"))
+    }
+}
+
+impl Drop for StdinSpool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn ensure_piped(stdin: &std::io::Stdin) -> Result<(), String> {
+    if stdin.is_terminal() {
+        return Err(format!(
+            "Standard input is a terminal; pipe text into 'wisty {STDIO_ARG}'"
+        ));
+    }
+    Ok(())
+}
+
+/// Copies all of standard input for the `-` argument into a `StdinSpool`.
+/// This happens before the window opens, like `vim -`; `limit_bytes` is the
+/// same hard limit files opened from the CLI have.
+pub fn spool_stdin(limit_bytes: u64) -> Result<StdinSpool, String> {
+    let stdin = std::io::stdin();
+    ensure_piped(&stdin)?;
+    let path = spool_path(&std::env::temp_dir(), "wisty-stdin")?;
+    let mut file = create_private_file(&path, "standard input buffer")?;
+    // Made before copying, so that a failed read removes the file.
+    let mut spool = StdinSpool {
+        path,
+        size_bytes: 0,
+    };
+    spool.size_bytes = std::io::copy(&mut stdin.lock().take(limit_bytes + 1), &mut file)
+        .map_err(|error| format!("Unable to read standard input: {error}"))?;
+    if spool.size_bytes > limit_bytes {
+        return Err(too_large(limit_bytes));
+    }
+    Ok(spool)
+}
+
+/// Reads all of standard input into memory, for `wisty convert`, which needs
+/// the whole document at once anyway.
+pub fn read_stdin(limit_bytes: u64) -> Result<Vec<u8>, String> {
+    let stdin = std::io::stdin();
+    ensure_piped(&stdin)?;
+    read_limited(&mut stdin.lock(), limit_bytes)
+}
+
+fn too_large(limit_bytes: u64) -> String {
+    format!(
+        "Standard input is too large to open safely (hard limit is {} MB)",
+        limit_bytes / (1024 * 1024)
+    )
+}
+
+fn read_limited(source: &mut impl Read, limit_bytes: u64) -> Result<Vec<u8>, String> {
+    let mut content = Vec::new();
+    source
+        .take(limit_bytes + 1)
+        .read_to_end(&mut content)
+        .map_err(|error| format!("Unable to read standard input: {error}"))?;
+    if content.len() as u64 > limit_bytes {
+        return Err(too_large(limit_bytes));
+    }
+    Ok(content)
+}
+
+/// A new path in `dir` for a spool named after `name`. The name is unique to
+/// this process and moment, so a spool left by a crashed instance that had
+/// the same process id can't get in the way.
+fn spool_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    crate::build_save_temp_path(&dir.join(name), "spool")
+}
+
+/// Creates `path` readable only by the user, failing if it exists.
+fn create_private_file(path: &Path, description: &str) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path).map_err(|error| {
+        format!(
+            "Unable to create {description} '{}': {error}",
+            path.to_string_lossy()
+        )
+    })
+}
+
+/// Creates the private file in `dir` that holds the `--stdout` document
+/// until the window closes, starting with `initial` so closing without
+/// saving passes the input through unchanged.
+pub fn create_stdout_spool(dir: &Path, initial: &mut impl Read) -> Result<PathBuf, String> {
+    let spool_path = spool_path(dir, "wisty-stdout")?;
+    let mut file = create_private_file(&spool_path, "standard output buffer")?;
+    if let Err(error) = std::io::copy(initial, &mut file) {
+        drop(file);
+        let _ = fs::remove_file(&spool_path);
+        return Err(format!(
+            "Unable to write standard output buffer '{}': {error}",
+            spool_path.to_string_lossy()
+        ));
+    }
+    Ok(spool_path)
+}
+
+fn copy_spool(spool_path: &Path, output: &mut impl Write) -> Result<(), String> {
+    let mut spool = File::open(spool_path).map_err(|error| {
+        format!(
+            "Unable to read standard output buffer '{}': {error}",
+            spool_path.to_string_lossy()
+        )
+    })?;
+    std::io::copy(&mut spool, output)
+        .and_then(|_| output.flush())
+        .map(|_| ())
+        .map_err(|error| format!("Unable to write to standard output: {error}"))
+}
+
+/// Writes the last saved `--stdout` document to standard output and removes
+/// the spool. Called once, as the app exits.
+pub fn flush_stdout_spool(spool_path: &Path) {
+    if let Err(error) = copy_spool(spool_path, &mut std::io::stdout().lock()) {
+        eprintln!("wisty: {error}");
+    }
+    let _ = fs::remove_file(spool_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_input_up_to_the_limit() {
+        assert_eq!(read_limited(&mut &b"piped"[..], 5).unwrap(), b"piped");
+        assert!(read_limited(&mut &b"piped!"[..], 5).is_err());
+    }
+
+    #[test]
+    fn spool_round_trips_to_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool_path = create_stdout_spool(dir.path(), &mut &b"from stdin\n"[..]).unwrap();
+        let other = create_stdout_spool(dir.path(), &mut std::io::empty()).unwrap();
+        assert_ne!(spool_path, other);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&spool_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut output = Vec::new();
+        copy_spool(&spool_path, &mut output).unwrap();
+        assert_eq!(output, b"from stdin\n");
+    }
+}
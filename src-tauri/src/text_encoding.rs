@@ -0,0 +1,447 @@
+use encoding_rs::{DecoderResult, EncoderResult, Encoding, REPLACEMENT, UTF_16BE, UTF_16LE, UTF_8};
+use std::io::{Chain, Cursor, Read};
+
+/// Number of leading bytes inspected when guessing a file's encoding. Large
+/// enough that a Latin-1 file whose first non-ASCII byte is a few pages in is
+/// still recognised, small enough to read up front without noticeable delay.
+pub const DETECTION_SAMPLE_BYTES: usize = 1024 * 1024;
+
+/// Guesses the encoding of `sample`, the first bytes of a file. A byte-order
+/// mark wins outright; otherwise BOM-less UTF-16 and valid UTF-8 are checked
+/// before falling back to chardetng's heuristic detection for legacy
+/// single- and multi-byte encodings. `is_complete` is true when `sample`
+/// holds the whole file.
+pub fn detect_encoding(sample: &[u8], is_complete: bool) -> &'static Encoding {
+    if let Some((encoding, _bom_length)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+
+    if let Some(encoding) = detect_bomless_utf16(sample) {
+        return encoding;
+    }
+
+    if is_utf8_prefix(sample, is_complete) {
+        return UTF_8;
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(sample, is_complete);
+    detector.guess(None, false)
+}
+
+/// Length of the byte-order mark at the start of `sample`, or 0 if none.
+pub fn bom_length(sample: &[u8]) -> usize {
+    Encoding::for_bom(sample)
+        .map(|(_encoding, length)| length)
+        .unwrap_or(0)
+}
+
+/// Length of the byte-order mark to skip at the start of `sample` when
+/// decoding it. A forced encoding only skips its own byte-order mark; anything
+/// else is content in that encoding.
+pub fn skipped_bom_length(sample: &[u8], forced_encoding: Option<&'static Encoding>) -> usize {
+    match forced_encoding {
+        Some(encoding) if sample.starts_with(bom_bytes(encoding)) => bom_bytes(encoding).len(),
+        Some(_) => 0,
+        None => bom_length(sample),
+    }
+}
+
+/// The byte-order mark for text saved as `encoding`, or an empty slice for
+/// encodings that have none.
+pub fn bom_bytes(encoding: &'static Encoding) -> &'static [u8] {
+    if encoding == UTF_8 {
+        b"\xEF\xBB\xBF"
+    } else if encoding == UTF_16LE {
+        b"\xFF\xFE"
+    } else if encoding == UTF_16BE {
+        b"\xFE\xFF"
+    } else {
+        &[]
+    }
+}
+
+/// A document whose start has been read to detect its encoding.
+pub struct SampledDocument<R> {
+    pub encoding: &'static Encoding,
+    /// The byte-order mark skipped at the start, which `reader` leaves out.
+    pub bom: Vec<u8>,
+    /// Whether the sample took in the whole document.
+    pub sample_is_complete: bool,
+    /// The rest of the document: the sample after the byte-order mark,
+    /// replayed ahead of what hasn't been read, since the source may not be
+    /// able to seek.
+    pub reader: Chain<Cursor<Vec<u8>>, R>,
+}
+
+impl<R> SampledDocument<R> {
+    /// The sampled bytes after the byte-order mark.
+    pub fn sample(&self) -> &[u8] {
+        self.reader.get_ref().0.get_ref()
+    }
+}
+
+/// Reads up to `DETECTION_SAMPLE_BYTES` from `source` and detects its
+/// encoding, unless `forced_encoding` is given, the way every reader of a
+/// document on disk starts.
+pub fn sample_document<R: Read>(
+    mut source: R,
+    forced_encoding: Option<&'static Encoding>,
+) -> std::io::Result<SampledDocument<R>> {
+    let mut sample = Vec::new();
+    source
+        .by_ref()
+        .take(DETECTION_SAMPLE_BYTES as u64)
+        .read_to_end(&mut sample)?;
+    let sample_is_complete = sample.len() < DETECTION_SAMPLE_BYTES;
+    let encoding = forced_encoding.unwrap_or_else(|| detect_encoding(&sample, sample_is_complete));
+    let rest = sample.split_off(skipped_bom_length(&sample, forced_encoding));
+    Ok(SampledDocument {
+        encoding,
+        bom: sample,
+        sample_is_complete,
+        reader: Cursor::new(rest).chain(source),
+    })
+}
+
+/// Decodes a whole document held in memory the way the open stream would:
+/// detected encoding, byte-order mark skipped, malformed input an error.
+pub fn decode_document(bytes: &[u8]) -> Result<String, String> {
+    let mut decoder = StreamDecoder::new(detect_encoding(bytes, true));
+    decoder.decode(&bytes[bom_length(bytes)..], true)
+}
+
+/// Resolves a WHATWG encoding label (e.g. `utf-8`, `latin1`, `shift_jis`).
+pub fn resolve_encoding(label: &str) -> Result<&'static Encoding, String> {
+    match Encoding::for_label(label.trim().as_bytes()) {
+        Some(encoding) if encoding != REPLACEMENT => Ok(encoding),
+        _ => Err(format!("Unsupported text encoding: {label}")),
+    }
+}
+
+fn is_utf8_prefix(sample: &[u8], is_complete: bool) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        // A sequence cut off by the end of the sample is fine unless the
+        // sample is the whole file.
+        Err(error) => error.error_len().is_none() && !is_complete,
+    }
+}
+
+/// Recognises UTF-16 without a byte-order mark by the NUL high bytes that
+/// mostly-Latin text leaves in every other position.
+fn detect_bomless_utf16(sample: &[u8]) -> Option<&'static Encoding> {
+    let pair_count = sample.len() / 2;
+    if pair_count < 4 {
+        return None;
+    }
+
+    let mut even_zeros = 0;
+    let mut odd_zeros = 0;
+    for pair in sample.chunks_exact(2) {
+        if pair[0] == 0 {
+            even_zeros += 1;
+        }
+        if pair[1] == 0 {
+            odd_zeros += 1;
+        }
+    }
+
+    let is_mostly = |count: usize| count * 10 >= pair_count * 4;
+    let is_rare = |count: usize| count * 20 <= pair_count;
+
+    if is_mostly(odd_zeros) && is_rare(even_zeros) {
+        Some(UTF_16LE)
+    } else if is_mostly(even_zeros) && is_rare(odd_zeros) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+pub fn split_utf8_prefix(input: &[u8]) -> Result<(String, Vec<u8>), String> {
+    match std::str::from_utf8(input) {
+        Ok(text) => Ok((text.to_string(), Vec::new())),
+        Err(error) => {
+            let valid_up_to = error.valid_up_to();
+            if let Some(_error_len) = error.error_len() {
+                return Err("File contains invalid UTF-8 data".to_string());
+            }
+
+            let valid = std::str::from_utf8(&input[..valid_up_to])
+                .map_err(|_| "Failed to decode UTF-8 prefix".to_string())?;
+
+            Ok((valid.to_string(), input[valid_up_to..].to_vec()))
+        }
+    }
+}
+
+/// Incremental decoder that turns a file's bytes into UTF-8 text chunk by
+/// chunk. Malformed input is an error rather than being replaced with U+FFFD,
+/// since the replacement would silently corrupt the file on the next save.
+pub enum StreamDecoder {
+    Utf8 {
+        carry: Vec<u8>,
+    },
+    Other {
+        encoding: &'static Encoding,
+        decoder: encoding_rs::Decoder,
+    },
+}
+
+impl StreamDecoder {
+    pub fn new(encoding: &'static Encoding) -> Self {
+        if encoding == UTF_8 {
+            return Self::Utf8 { carry: Vec::new() };
+        }
+
+        // The open stream skips any byte-order mark before decoding starts
+        // and reports it separately, so the decoder must not look for one.
+        Self::Other {
+            encoding,
+            decoder: encoding.new_decoder_without_bom_handling(),
+        }
+    }
+
+    /// Decodes the next slice of input. Pass `last` once the file is
+    /// exhausted so any incomplete trailing sequence is reported.
+    pub fn decode(&mut self, input: &[u8], last: bool) -> Result<String, String> {
+        match self {
+            Self::Utf8 { carry } => {
+                let mut combined = Vec::with_capacity(carry.len() + input.len());
+                combined.extend_from_slice(carry);
+                combined.extend_from_slice(input);
+
+                let (decoded, rest) = split_utf8_prefix(&combined)?;
+                if last && !rest.is_empty() {
+                    return Err("File ended with invalid UTF-8 sequence".to_string());
+                }
+                *carry = rest;
+                Ok(decoded)
+            }
+            Self::Other { encoding, decoder } => {
+                let mut output = String::new();
+                let mut remaining = input;
+                loop {
+                    let needed = decoder
+                        .max_utf8_buffer_length_without_replacement(remaining.len())
+                        .ok_or_else(|| "File chunk is too large to decode".to_string())?;
+                    output.reserve(needed);
+
+                    let (result, read) =
+                        decoder.decode_to_string_without_replacement(remaining, &mut output, last);
+                    remaining = &remaining[read..];
+
+                    match result {
+                        DecoderResult::InputEmpty => return Ok(output),
+                        DecoderResult::OutputFull => continue,
+                        DecoderResult::Malformed(_, _) => {
+                            return Err(format!("File contains invalid {} data", encoding.name()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Incremental encoder for the save stream. encoding_rs only encodes to
+/// ASCII-compatible targets, so UTF-16 output is produced by hand.
+pub enum StreamEncoder {
+    Utf8,
+    Utf16 {
+        big_endian: bool,
+    },
+    Other {
+        encoding: &'static Encoding,
+        encoder: encoding_rs::Encoder,
+    },
+}
+
+impl StreamEncoder {
+    pub fn new(encoding: &'static Encoding) -> Self {
+        if encoding == UTF_8 {
+            Self::Utf8
+        } else if encoding == UTF_16LE {
+            Self::Utf16 { big_endian: false }
+        } else if encoding == UTF_16BE {
+            Self::Utf16 { big_endian: true }
+        } else {
+            Self::Other {
+                encoding,
+                encoder: encoding.new_encoder(),
+            }
+        }
+    }
+
+    /// Encodes the next slice of text. Pass `last` for the final call so
+    /// stateful encodings (ISO-2022-JP) can return to their initial state.
+    /// Characters the target encoding cannot represent are an error.
+    pub fn encode(&mut self, text: &str, last: bool) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Utf16 { big_endian } => {
+                let mut output = Vec::with_capacity(text.len() * 2);
+                for unit in text.encode_utf16() {
+                    let bytes = if *big_endian {
+                        unit.to_be_bytes()
+                    } else {
+                        unit.to_le_bytes()
+                    };
+                    output.extend_from_slice(&bytes);
+                }
+                Ok(output)
+            }
+            Self::Other { encoding, encoder } => {
+                let mut output = Vec::new();
+                let mut remaining = text;
+                loop {
+                    let needed = encoder
+                        .max_buffer_length_from_utf8_without_replacement(remaining.len())
+                        .ok_or_else(|| "Save chunk is too large to encode".to_string())?;
+                    output.reserve(needed);
+
+                    let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(
+                        remaining,
+                        &mut output,
+                        last,
+                    );
+                    remaining = &remaining[read..];
+
+                    match result {
+                        EncoderResult::InputEmpty => return Ok(output),
+                        EncoderResult::OutputFull => continue,
+                        EncoderResult::Unmappable(character) => {
+                            return Err(format!(
+                                "Character '{character}' (U+{:04X}) cannot be saved as {}",
+                                character as u32,
+                                encoding.name()
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_in_chunks(bytes: &[u8], chunk_size: usize) -> Result<String, String> {
+        let encoding = detect_encoding(bytes, true);
+        let mut decoder = StreamDecoder::new(encoding);
+        let mut text = String::new();
+        for chunk in bytes[bom_length(bytes)..].chunks(chunk_size) {
+            text.push_str(&decoder.decode(chunk, false)?);
+        }
+        text.push_str(&decoder.decode(&[], true)?);
+        Ok(text)
+    }
+
+    #[test]
+    fn decodes_whole_documents() {
+        assert_eq!(
+            decode_document(b"\xEF\xBB\xBF# Notes\n"),
+            Ok("# Notes\n".to_string())
+        );
+        assert_eq!(decode_document(b"\xFF\xFEh\x00i\x00"), Ok("hi".to_string()));
+        assert!(decode_document(b"\xFF\xFEh\x00i").is_err());
+    }
+
+    #[test]
+    fn detects_common_encodings() {
+        assert_eq!(detect_encoding(b"plain ascii\n", true), UTF_8);
+        assert_eq!(detect_encoding("caf\u{e9}\n".as_bytes(), true), UTF_8);
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFhello", true), UTF_8);
+        assert_eq!(detect_encoding(b"\xFF\xFEh\x00i\x00", true), UTF_16LE);
+        assert_eq!(
+            detect_encoding(b"h\x00e\x00l\x00l\x00o\x00", true),
+            UTF_16LE
+        );
+        assert_eq!(
+            detect_encoding(b"\x00h\x00e\x00l\x00l\x00o", true),
+            UTF_16BE
+        );
+
+        let latin = b"Le caf\xE9 est tr\xE8s bon, d\xE9j\xE0 pr\xEAt \xE0 \xEAtre servi.\n";
+        assert_eq!(detect_encoding(latin, true).name(), "windows-1252");
+
+        let (shift_jis, _, _) = encoding_rs::SHIFT_JIS
+            .encode("これは日本語のテキストファイルです。ログの内容を確認してください。");
+        assert_eq!(detect_encoding(&shift_jis, true), encoding_rs::SHIFT_JIS);
+    }
+
+    #[test]
+    fn incomplete_utf8_tail_is_still_utf8_when_sample_is_partial() {
+        let bytes = "naïve".as_bytes();
+        let truncated = &bytes[..3];
+        assert_eq!(detect_encoding(truncated, false), UTF_8);
+    }
+
+    #[test]
+    fn decodes_across_chunk_boundaries() {
+        let text = "grüße, 日本語, emoji 🎉\n".repeat(50);
+
+        assert_eq!(decode_in_chunks(text.as_bytes(), 7).unwrap(), text);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in text.encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(decode_in_chunks(&utf16, 5).unwrap(), text);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut decoder = StreamDecoder::new(UTF_8);
+        assert!(decoder.decode(b"ok \xFF", false).is_err());
+
+        let mut decoder = StreamDecoder::new(UTF_8);
+        assert_eq!(decoder.decode(b"ok \xC3", false).unwrap(), "ok ");
+        assert!(decoder.decode(&[], true).is_err());
+    }
+
+    #[test]
+    fn encodes_back_to_original_bytes() {
+        let latin = b"Le caf\xE9 est pr\xEAt.\n";
+        let windows_1252 = resolve_encoding("windows-1252").unwrap();
+        let text = decode_in_chunks(latin, 4).unwrap();
+        let mut encoder = StreamEncoder::new(windows_1252);
+        assert_eq!(encoder.encode(&text, true).unwrap(), latin);
+
+        let utf16_be = b"\xFE\xFF\x00h\x00i";
+        let text = decode_in_chunks(utf16_be, 3).unwrap();
+        let mut encoder = StreamEncoder::new(UTF_16BE);
+        let mut encoded = bom_bytes(UTF_16BE).to_vec();
+        encoded.extend(encoder.encode(&text, true).unwrap());
+        assert_eq!(encoded, utf16_be);
+    }
+
+    #[test]
+    fn measures_byte_order_marks() {
+        assert_eq!(bom_length(b"\xEF\xBB\xBFabc"), 3);
+        assert_eq!(bom_length(b"\xFF\xFEa\x00"), 2);
+        assert_eq!(bom_length(b"abc"), 0);
+        assert!(bom_bytes(encoding_rs::WINDOWS_1252).is_empty());
+    }
+
+    #[test]
+    fn reports_unmappable_characters() {
+        let mut encoder = StreamEncoder::new(resolve_encoding("latin1").unwrap());
+        let error = encoder.encode("snowman ☃", true).unwrap_err();
+        assert!(error.contains("U+2603"));
+    }
+
+    #[test]
+    fn resolves_labels() {
+        assert_eq!(resolve_encoding("UTF-8").unwrap(), UTF_8);
+        assert_eq!(
+            resolve_encoding(" shift_jis ").unwrap(),
+            encoding_rs::SHIFT_JIS
+        );
+        assert!(resolve_encoding("iso-2022-kr").is_err());
+        assert!(resolve_encoding("klingon").is_err());
+    }
+}
@@ -1,10 +1,11 @@
-import { createEffect, createSignal } from "solid-js";
+import { createEffect, createSignal, on } from "solid-js";
 import { getVersion } from "@tauri-apps/api/app";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { ask, message, open } from "@tauri-apps/plugin-dialog";
 import "./App.css";
 import { AppShell } from "./components/AppShell";
 import { createCommandRegistry } from "./core/commands/commandRegistry";
-import { buildCommands, spellLanguageCommandId } from "./core/commands/buildCommands";
+import { buildCommands, spellLanguageCommandId, spellRemoveCommandId } from "./core/commands/buildCommands";
 import type { DictionaryInfo } from "./core/spellcheck/spellService";
 import { createShortcutRouter } from "./core/commands/shortcutRouter";
 import type { ErrorReporter } from "./core/app/contracts";
@@ -26,9 +27,9 @@ import {
   openTextFile,
   openTextFilePath,
   readTextFileAtPath,
+  saveExportPathAs,
   saveTextFile,
-  saveTextFilePathAs,
-  streamReadTextFileAtPath
+  saveTextFilePathAs
 } from "./core/files/fileService";
 import { createSettingsStore } from "./core/settings/settingsStore";
 import { chooseEditorFont } from "./core/fonts/fontDialog";
@@ -40,16 +41,47 @@ import {
   writeSaveFileChunk
 } from "./core/window/saveStreamService";
 import {
-  cancelLaunchFileStream,
-  closeLaunchFileStream,
-  readLaunchFileChunk,
-  startLaunchFileStream,
+  cancelOpenFileStream,
+  closeOpenFileStream,
+  readOpenFileChunk,
+  startOpenFileStream,
+  startStdinStream
+} from "./core/window/openStreamService";
+import {
+  launchWritesToStdout,
+  onOpenFilesRequested,
+  openPendingLaunchWindows,
+  releaseLaunchWait,
   takeLaunchFileArg,
   type LaunchFileArg
 } from "./core/window/launchArgService";
+import { checkFileOnDisk, onFileChangedOnDisk, unwatchFile } from "./core/window/fileWatchService";
+import { exportDocx, exportHtml, exportOdt, exportPdf } from "./core/window/exportService";
+import { printDocument } from "./core/window/printService";
+import { documentStatsForFile, documentStatsForText, type DocumentStats } from "./core/window/documentStatsService";
+import {
+  onSpellReportProgress,
+  spellReportForFile,
+  spellReportForText,
+  type SpellReport
+} from "./core/spellcheck/spellReportService";
+import {
+  cancelRecoverySnapshot,
+  discardRecoverySnapshot,
+  finishRecoverySnapshot,
+  listRecoverableDocuments,
+  startRecoverySnapshot,
+  writeRecoverySnapshotChunk
+} from "./core/window/recoveryService";
+import { useRecoveryJournal } from "./core/app/useRecoveryJournal";
 
 const MAIN_WINDOW_LABEL = "main";
 const PLATFORM_IS_MAC = navigator.userAgent.toLowerCase().includes("mac");
+// Below this the detector isn't sure enough to change dictionaries for the
+// user; whatlang calls a guess reliable from 0.9.
+const SPELL_DETECT_MIN_CONFIDENCE = 0.9;
+// Misspellings listed in the spelling report; the rest are only counted.
+const SPELL_REPORT_LISTED_WORDS = 25;
 
 type LargeFileDialogState =
   | {
@@ -82,6 +114,7 @@ function App() {
     totalCharacters: 1
   });
   const [spellDictionaries, setSpellDictionaries] = createSignal<DictionaryInfo[]>([]);
+  const [statusActivity, setStatusActivity] = createSignal<string | null>(null);
   const errorModalQueue = useErrorModalQueue();
 
   let editorHostRef: HTMLDivElement | undefined;
@@ -134,6 +167,33 @@ function App() {
       });
     });
 
+  const confirmReloadChangedFile = (filePath: string, hasUnsavedChanges: boolean): Promise<boolean> =>
+    ask(
+      hasUnsavedChanges
+        ? `${filePath} was changed by another program. Reload it and discard your unsaved changes?`
+        : `${filePath} was changed by another program. Reload it?`,
+      { title: "File changed on disk", kind: "warning", okLabel: "Reload", cancelLabel: "Keep Editing" }
+    );
+
+  const confirmOverwriteChangedFile = (filePath: string): Promise<boolean> =>
+    ask(
+      `${filePath} was changed by another program since it was opened. Save anyway and replace those changes?`,
+      { title: "File changed on disk", kind: "warning", okLabel: "Save Anyway", cancelLabel: "Cancel" }
+    );
+
+  const confirmElevatedSave = (filePath: string): Promise<boolean> =>
+    ask(
+      `You don't have permission to write ${filePath}. Save it with administrator rights?`,
+      { title: "Permission required", kind: "warning", okLabel: "Save as Administrator", cancelLabel: "Cancel" }
+    );
+
+  const showFileMissingOnDisk = async (filePath: string, kind: "removed" | "renamed") => {
+    await message(
+      `${filePath} was ${kind === "removed" ? "deleted" : "moved or renamed"} by another program. Save to write it back.`,
+      { title: "File no longer on disk", kind: "warning" }
+    );
+  };
+
   const fileLifecycle = useFileLifecycle({
     editor: editorAdapter,
     document: documentStore,
@@ -141,21 +201,22 @@ function App() {
     fileDialogs: {
       openTextFile,
       openTextFilePath,
-      saveTextFilePathAs
+      saveTextFilePathAs,
+      saveExportPathAs
     },
     fileIo: {
       getFileSize,
       fileExists,
       readTextFile: readTextFileAtPath,
-      streamReadTextFile: streamReadTextFileAtPath,
       saveTextFile,
       getDirectoryFromFilePath
     },
-    launchFileStream: {
-      startLaunchFileStream,
-      readLaunchFileChunk,
-      cancelLaunchFileStream,
-      closeLaunchFileStream
+    openFileStream: {
+      startOpenFileStream,
+      startStdinStream,
+      readOpenFileChunk,
+      cancelOpenFileStream,
+      closeOpenFileStream
     },
     saveFileStream: {
       startSaveFileStream,
@@ -163,14 +224,81 @@ function App() {
       finishSaveFileStream,
       cancelSaveFileStream
     },
+    fileWatch: {
+      checkFileOnDisk,
+      unwatchFile
+    },
+    exporter: {
+      exportHtml,
+      exportPdf,
+      exportOdt,
+      exportDocx
+    },
+    printer: {
+      printDocument
+    },
     fontPicker: {
       chooseEditorFont
     },
     errors,
     confirmOpenLargeFile,
-    showFileTooLarge
+    showFileTooLarge,
+    confirmReloadChangedFile,
+    confirmOverwriteChangedFile,
+    confirmElevatedSave,
+    releaseLaunchWait,
+    showFileMissingOnDisk
+  });
+
+  void launchWritesToStdout().then(fileLifecycle.setUntitledSavesToStdout);
+
+  const recoveryJournal = useRecoveryJournal({
+    editor: editorAdapter,
+    document: documentStore,
+    isBusy: () => fileLifecycle.loadingState.isLoading() || fileLifecycle.savingState.isSaving(),
+    getDocumentFormat: fileLifecycle.getDocumentFormat,
+    recovery: {
+      startRecoverySnapshot,
+      writeRecoverySnapshotChunk,
+      finishRecoverySnapshot,
+      cancelRecoverySnapshot,
+      discardRecoverySnapshot
+    }
   });
 
+  // Only one document can be open, so the first one the user restores wins;
+  // any left undecided are offered again next launch.
+  const offerRecovery = async () => {
+    // Windows opened for further launch files leave recovery to the main one.
+    if (appWindow.label !== MAIN_WINDOW_LABEL) {
+      return;
+    }
+    const entries = await listRecoverableDocuments();
+    for (const entry of entries) {
+      const shouldRestore = await ask(
+        `Unsaved changes to ${entry.filePath ?? "an untitled document"} from ${new Date(entry.savedAtMs).toLocaleString()} were recovered. Restore them?`,
+        { title: "Recover unsaved changes", kind: "info", okLabel: "Restore", cancelLabel: "Not Now" }
+      );
+      if (!shouldRestore) {
+        // Closing either dialog keeps the snapshot for next launch.
+        const shouldDiscard = await ask("Discard these changes for good?", {
+          title: "Recover unsaved changes",
+          kind: "warning",
+          okLabel: "Discard",
+          cancelLabel: "Keep"
+        });
+        if (shouldDiscard) {
+          await discardRecoverySnapshot(entry.documentId);
+        }
+        continue;
+      }
+      if (await fileLifecycle.restoreRecoveredDocument(entry)) {
+        recoveryJournal.adoptSnapshot(entry.documentId);
+      }
+      return;
+    }
+  };
+
   const closeFlow = useCloseFlow({
     isDirty: () => documentStore.state.isDirty,
     closeWindow: () => appWindow.close(),
@@ -222,6 +350,104 @@ function App() {
     }
   };
 
+  const describeDocumentStats = (stats: DocumentStats): string => {
+    const count = (value: number) => value.toLocaleString();
+    const minutes = Math.ceil(stats.readingTimeSeconds / 60);
+    const lines = [
+      `Words: ${count(stats.words)}`,
+      `Characters: ${count(stats.characters)} (${count(stats.charactersExcludingSpaces)} without spaces)`,
+      `Sentences: ${count(stats.sentences)}`,
+      `Paragraphs: ${count(stats.paragraphs)}`,
+      `Lines: ${count(stats.lines)}`,
+      `Reading time: ${count(minutes)} ${minutes === 1 ? "minute" : "minutes"}`
+    ];
+    if (stats.readability !== null) {
+      lines.push(`Readability (Flesch): ${stats.readability.toFixed(1)}`);
+    }
+    if (settingsStore.state.formatViewMode === "formatted") {
+      const headings = stats.headings
+        .map((headingCount, index) => (headingCount > 0 ? `H${index + 1}: ${count(headingCount)}` : ""))
+        .filter(Boolean);
+      lines.push(`Headings: ${headings.length > 0 ? headings.join(", ") : "none"}`);
+    }
+    return lines.join("\n");
+  };
+
+  // A saved document is counted straight from disk; one with unsaved edits
+  // is streamed from the editor.
+  const showDocumentStats = async () => {
+    try {
+      const filePath = documentStore.state.filePath;
+      const stats = filePath && !documentStore.state.isDirty
+        ? await documentStatsForFile(filePath, fileLifecycle.getDocumentFormat()?.encoding)
+        : await documentStatsForText(editorAdapter.getDocLength(), editorAdapter.getTextSlice);
+      await message(describeDocumentStats(stats), { title: "Document Statistics", kind: "info" });
+      editorAdapter.focus();
+    } catch (error) {
+      const appError = toAppError(error, "UNKNOWN", "Unable to count document");
+      errorModalQueue.enqueue({
+        title: "Unable to count document",
+        message: appError.message,
+        code: appError.code,
+        details: appError.details
+      });
+    }
+  };
+
+  const describeSpellReport = (report: SpellReport): string => {
+    const count = (value: number) => value.toLocaleString();
+    if (report.misspellings.length === 0) {
+      return `No misspellings in ${count(report.wordsChecked)} words.`;
+    }
+    const lines = [
+      `${count(report.misspelledCount)} misspellings of ${count(report.misspellings.length)} words `
+        + `in ${count(report.wordsChecked)} words checked.`,
+      ""
+    ];
+    for (const entry of report.misspellings.slice(0, SPELL_REPORT_LISTED_WORDS)) {
+      const first = entry.occurrences[0];
+      const suggestions = entry.suggestions.slice(0, 3).map((suggestion) => suggestion.word).join(", ");
+      lines.push(
+        `${entry.word} ×${count(entry.count)}, first at line ${count(first.line)}, column ${count(first.column)}`
+          + (suggestions ? ` → ${suggestions}` : "")
+      );
+    }
+    const unlisted = report.misspellings.length - SPELL_REPORT_LISTED_WORDS;
+    if (unlisted > 0) {
+      lines.push(`…and ${count(unlisted)} more`);
+    }
+    return lines.join("\n");
+  };
+
+  // Checked from disk or the editor like the statistics, with progress shown
+  // in the status bar for a document large enough to take a while.
+  const showSpellReport = async () => {
+    const stopListening = await onSpellReportProgress(({ stage, done, total }) => {
+      const percent = total > 0 ? Math.floor((done / total) * 100) : 0;
+      setStatusActivity(`${stage === "checking" ? "Checking spelling" : "Finding suggestions"}… ${percent}%`);
+    });
+    try {
+      const filePath = documentStore.state.filePath;
+      const report = filePath && !documentStore.state.isDirty
+        ? await spellReportForFile(filePath, fileLifecycle.getDocumentFormat()?.encoding)
+        : await spellReportForText(editorAdapter.getText());
+      setStatusActivity(null);
+      await message(describeSpellReport(report), { title: "Spelling Report", kind: "info" });
+      editorAdapter.focus();
+    } catch (error) {
+      const appError = toAppError(error, "UNKNOWN", "Unable to check spelling");
+      errorModalQueue.enqueue({
+        title: "Unable to check spelling",
+        message: appError.message,
+        code: appError.code,
+        details: appError.details
+      });
+    } finally {
+      stopListening();
+      setStatusActivity(null);
+    }
+  };
+
   const dismissErrorModalAndRefocus = () => {
     const hadSingleEntry = errorModalQueue.entries().length <= 1;
     errorModalQueue.dismissCurrent();
@@ -257,9 +483,31 @@ function App() {
       }
       return;
     }
-    if (!dictionaries.some((entry) => entry.code === settingsStore.state.spellCheckLanguage)) {
-      await settingsStore.actions.setSpellCheckLanguage(dictionaries[0].code);
+    const installed = settingsStore.state.spellCheckLanguages.filter((language) =>
+      dictionaries.some((entry) => entry.code === language));
+    if (installed.length !== settingsStore.state.spellCheckLanguages.length) {
+      await settingsStore.actions.setSpellCheckLanguages(installed.length > 0 ? installed : [dictionaries[0].code]);
+    }
+  };
+
+  // Adds the dictionary for an opened document's language, unless one for it
+  // is already in use or the detector isn't sure. The languages already
+  // chosen stay in use: a word is correct if any dictionary accepts it.
+  const detectSpellLanguage = async () => {
+    const settings = settingsStore.state;
+    if (!settings.spellCheckEnabled || !settings.spellCheckDetectLanguage || spellDictionaries().length < 2) {
+      return;
+    }
+    const guesses = await editorAdapter.detectSpellLanguage();
+    const best = guesses[0];
+    if (!best || (best.confidence ?? 0) < SPELL_DETECT_MIN_CONFIDENCE) {
+      return;
+    }
+    const sameLanguage = guesses.filter((guess) => guess.language === best.language);
+    if (sameLanguage.some((guess) => settings.spellCheckLanguages.includes(guess.code))) {
+      return;
     }
+    await settingsStore.actions.setSpellCheckLanguages([...settings.spellCheckLanguages, best.code]);
   };
 
   const showSpellInstallHelp = () => {
@@ -268,8 +516,73 @@ function App() {
       message:
         "Install hunspell dictionaries to /usr/share/hunspell. For example, "
         + "“sudo apt install hunspell-en-us” on Debian/Ubuntu, or “sudo dnf install hunspell-en-US” "
-        + "on Fedora."
+        + "on Fedora. Without root, copy a dictionary's .aff and .dic files to ~/.local/share/hunspell "
+        + "or to a directory listed in DICPATH. Install Dictionary... also takes a LibreOffice "
+        + "dictionary extension (.oxt) and copies its dictionaries there. To search other "
+        + "directories first, list their absolute paths under “spellDictionaryDirs” in wisty's "
+        + "settings.json, for example \"spellDictionaryDirs\": [\"/home/me/dictionaries\"]."
+    });
+  };
+
+  // Reloads the dictionaries in use once the files behind them have changed.
+  const refreshSpellDictionaries = async () => {
+    await loadSpellDictionaries();
+    await editorAdapter.configureSpellcheck({
+      enabled: settingsStore.state.spellCheckEnabled,
+      languages: [...settingsStore.state.spellCheckLanguages]
+    });
+  };
+
+  const installSpellDictionary = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        directory: false,
+        filters: [{ name: "Dictionary Packages", extensions: ["oxt", "zip"] }]
+      });
+      if (typeof selected !== "string") {
+        return;
+      }
+      const installed = await editorAdapter.installSpellDictionary(selected);
+      await refreshSpellDictionaries();
+      const labels = installed.map((dictionary) => dictionary.label).join(", ");
+      await message(`Installed ${labels}. Turn it on from View > Spell Check.`, {
+        title: "Install Dictionary",
+        kind: "info"
+      });
+    } catch (error) {
+      const appError = toAppError(error, "UNKNOWN", "Unable to install dictionary");
+      errorModalQueue.enqueue({
+        title: "Unable to install dictionary",
+        message: appError.message,
+        code: appError.code,
+        details: appError.details
+      });
+    }
+  };
+
+  const removeSpellDictionary = async (dictionary: DictionaryInfo) => {
+    const confirmed = await ask(`Remove the ${dictionary.label} dictionary from ${dictionary.directory}?`, {
+      title: "Remove Dictionary",
+      kind: "warning",
+      okLabel: "Remove",
+      cancelLabel: "Cancel"
     });
+    if (!confirmed) {
+      return;
+    }
+    try {
+      await editorAdapter.uninstallSpellDictionary(dictionary.code);
+      await refreshSpellDictionaries();
+    } catch (error) {
+      const appError = toAppError(error, "UNKNOWN", "Unable to remove dictionary");
+      errorModalQueue.enqueue({
+        title: "Unable to remove dictionary",
+        message: appError.message,
+        code: appError.code,
+        details: appError.details
+      });
+    }
   };
 
   const { definitions, sections } = buildCommands({
@@ -281,8 +594,11 @@ function App() {
     spell: {
       dictionaries: spellDictionaries,
       showInstallHelp: showSpellInstallHelp,
-      showAddedWords: () => void openAddedWordsDialog()
+      installDictionary: installSpellDictionary,
+      showAddedWords: () => void openAddedWordsDialog(),
+      showReport: showSpellReport
     },
+    showDocumentStats,
     showAbout: openAboutDialog
   });
 
@@ -296,12 +612,30 @@ function App() {
         refocusEditorOnMenuSelect: true,
         checked: () =>
           settingsStore.state.spellCheckEnabled
-          && settingsStore.state.spellCheckLanguage === dictionary.code,
+          && settingsStore.state.spellCheckLanguages.includes(dictionary.code),
+        // Each language toggles on its own, so a bilingual document can be
+        // checked against both; unticking the last one turns spell check off.
         run: async () => {
-          await settingsStore.actions.setSpellCheckLanguage(dictionary.code);
-          await settingsStore.actions.setSpellCheckEnabled(true);
+          const languages = settingsStore.state.spellCheckLanguages;
+          if (!settingsStore.state.spellCheckEnabled) {
+            await settingsStore.actions.setSpellCheckLanguages([dictionary.code]);
+            await settingsStore.actions.setSpellCheckEnabled(true);
+          } else if (!languages.includes(dictionary.code)) {
+            await settingsStore.actions.setSpellCheckLanguages([...languages, dictionary.code]);
+          } else if (languages.length > 1) {
+            await settingsStore.actions.setSpellCheckLanguages(languages.filter((code) => code !== dictionary.code));
+          } else {
+            await settingsStore.actions.setSpellCheckEnabled(false);
+          }
         }
       });
+      if (dictionary.removable) {
+        commandRegistry.register({
+          id: spellRemoveCommandId(dictionary.code),
+          label: dictionary.label,
+          run: () => removeSpellDictionary(dictionary)
+        });
+      }
     }
   });
   const isInteractionBlocked = () =>
@@ -387,11 +721,27 @@ function App() {
     },
     takeLaunchFileArg,
     openLaunchFileArg: async (launchFile: LaunchFileArg) => {
-      if (launchFile.exists) {
-        await fileLifecycle.openLaunchFileAtPath(launchFile.path, launchFile.fileSizeBytes);
+      settingsStore.actions.overrideViewModes({
+        themeMode: launchFile.themeMode,
+        formatViewMode: launchFile.formatViewMode
+      });
+      const openOptions = {
+        position: launchFile.position,
+        encoding: launchFile.encoding ?? undefined,
+        readOnly: launchFile.readOnly
+      };
+      if (launchFile.stdin) {
+        await fileLifecycle.openLaunchStdin(launchFile.fileSizeBytes, openOptions);
         return;
       }
-      await fileLifecycle.openMissingFileAtPath(launchFile.path);
+      if (launchFile.exists) {
+        await fileLifecycle.openLaunchFileAtPath(launchFile.path, launchFile.fileSizeBytes, openOptions);
+      } else {
+        await fileLifecycle.openMissingFileAtPath(launchFile.path, openOptions.encoding);
+      }
+      if (launchFile.waitId !== null) {
+        fileLifecycle.holdLaunchWait(launchFile.waitId, launchFile.path);
+      }
     },
     onLaunchFileOpenError: async (error) => {
       const appError = toAppError(error, "LAUNCH_OPEN_FAILED", "Unable to open launch file");
@@ -402,11 +752,21 @@ function App() {
         details: appError.details
       });
     },
+    offerRecovery,
     loadVersion: () => getVersion(),
     setAppVersion,
     handleGlobalKeydown,
     registerCloseRequested: (handler) => appWindow.onCloseRequested(handler),
-    handleWindowCloseRequested: closeFlow.handleWindowCloseRequested
+    handleWindowCloseRequested: closeFlow.handleWindowCloseRequested,
+    registerFileChangedOnDisk: onFileChangedOnDisk,
+    handleFileChangedOnDisk: fileLifecycle.handleFileChangedOnDisk,
+    registerOpenFilesRequested: onOpenFilesRequested,
+    canOpenLaunchFileInPlace: () =>
+      !documentStore.state.filePath &&
+      !documentStore.state.isDirty &&
+      !fileLifecycle.loadingState.isLoading() &&
+      editorAdapter.getDocLength() === 0,
+    openPendingLaunchWindows
   });
 
   createEffect(() => {
@@ -424,17 +784,29 @@ function App() {
     editorAdapter.setFormatMode(settingsStore.state.formatViewMode);
   });
 
+  createEffect(() => {
+    editorAdapter.setReadOnly(documentStore.state.readOnly);
+  });
+
   createEffect(() => {
     void editorAdapter.configureSpellcheck({
       enabled: settingsStore.state.spellCheckEnabled,
-      language: settingsStore.state.spellCheckLanguage
+      languages: [...settingsStore.state.spellCheckLanguages]
     });
   });
 
+  createEffect(on(() => documentStore.state.filePath, (filePath) => {
+    if (filePath) {
+      void detectSpellLanguage().catch(() => {
+        // Detection only saves a trip to the menu; the current dictionaries stay.
+      });
+    }
+  }, { defer: true }));
+
   useWindowTitleSync({
     fileName: () => documentStore.state.fileName,
     isDirty: () => documentStore.state.isDirty,
-    windowLabel: MAIN_WINDOW_LABEL
+    windowLabel: appWindow.label
   });
 
   return (
@@ -509,7 +881,8 @@ function App() {
           statusBar={{
             enabled: settingsStore.state.statusBarEnabled,
             ...cursorPosition(),
-            formatViewMode: settingsStore.state.formatViewMode
+            formatViewMode: settingsStore.state.formatViewMode,
+            activity: statusActivity()
           }}
           errorModal={{
             open: errorModalQueue.open(),
@@ -59,6 +59,8 @@ type AppShellProps = {
   statusBar: {
     enabled: boolean;
     formatViewMode: "formatted" | "plain";
+    /** Progress of long background work, such as a spelling report. */
+    activity: string | null;
   } & CursorPositionPayload;
   errorModal: {
     open: boolean;
@@ -86,6 +88,9 @@ export const AppShell = (props: AppShellProps) => {
           <span class="status-bar-character">
             Character {props.statusBar.currentCharacter} of {props.statusBar.totalCharacters}
           </span>
+          <Show when={props.statusBar.activity}>
+            <span class="status-bar-activity">{props.statusBar.activity}</span>
+          </Show>
           <span class="status-bar-mode">
             {props.statusBar.formatViewMode === "formatted" ? "Formatted view" : "Plain text view"}
           </span>
@@ -25,14 +25,10 @@ export type SaveTextFileAsResult =
   | { kind: "cancelled" }
   | { kind: "saved"; filePath: string };
 
-export type TextStreamChunk = {
-  text: string;
-  bytesReadTotal: number;
-  fileSizeBytes?: number;
-};
-
-export type StreamReadTextFileOptions = {
-  chunkSizeBytes?: number;
+/** The file type offered by an export's save dialog. */
+export type ExportFileFilter = {
+  name: string;
+  extensions: string[];
 };
 
 export type FileLoadPhase = "idle" | "loading" | "cancelling" | "error";
@@ -43,37 +39,38 @@ export type FileLoadProgress = {
   totalBytes?: number;
 };
 
-export type LaunchFileStreamStartResult = {
+export type OpenFileStreamStartResult = {
   streamId: string;
   filePath: string;
   fileSizeBytes: number;
+  encoding: string;
+  hasBom: boolean;
+  lineEnding: "lf" | "crlf" | "cr" | "mixed" | "none";
 };
 
-export type LaunchFileStreamChunkResult =
+export type OpenFileStreamChunkResult =
   | { kind: "chunk"; text: string; bytesReadTotal: number; fileSizeBytes: number }
   | { kind: "eof"; bytesReadTotal: number; fileSizeBytes: number };
 
-export type LaunchFileStreamPort = {
-  startLaunchFileStream: (filePath: string) => Promise<LaunchFileStreamStartResult>;
-  readLaunchFileChunk: (streamId: string, maxBytes: number) => Promise<LaunchFileStreamChunkResult>;
-  cancelLaunchFileStream: (streamId: string) => Promise<void>;
-  closeLaunchFileStream: (streamId: string) => Promise<void>;
+export type OpenFileStreamPort = {
+  startOpenFileStream: (filePath: string, encoding?: string) => Promise<OpenFileStreamStartResult>;
+  startStdinStream: (encoding?: string) => Promise<OpenFileStreamStartResult>;
+  readOpenFileChunk: (streamId: string, maxBytes: number) => Promise<OpenFileStreamChunkResult>;
+  cancelOpenFileStream: (streamId: string) => Promise<void>;
+  closeOpenFileStream: (streamId: string) => Promise<void>;
 };
 
 export type FileDialogsPort = {
   openTextFile: (defaultPath?: string) => Promise<OpenTextFileResult>;
   openTextFilePath: (defaultPath?: string) => Promise<OpenTextFilePathResult>;
   saveTextFilePathAs: (defaultPath?: string) => Promise<SaveTextFileAsResult>;
+  saveExportPathAs: (defaultPath: string, filter: ExportFileFilter) => Promise<SaveTextFileAsResult>;
 };
 
 export type FileIoPort = {
   getFileSize: (filePath: string) => Promise<number>;
   fileExists: (filePath: string) => Promise<boolean>;
   readTextFile: (filePath: string) => Promise<string>;
-  streamReadTextFile: (
-    filePath: string,
-    options?: StreamReadTextFileOptions
-  ) => AsyncGenerator<TextStreamChunk, void, void>;
   saveTextFile: (filePath: string, text: string) => Promise<void>;
   getDirectoryFromFilePath: (filePath: string) => string;
 };
@@ -93,11 +90,13 @@ export type EditorPort = {
   getText: () => string;
   getDocLength: () => number;
   getTextSlice: (from: number, to: number) => string;
+  goToPosition: (line: number, column?: number) => void;
   getRevision: () => number;
   setText: (text: string, options?: { emitChange?: boolean }) => void;
   append: (text: string, options?: AppendTextOptions) => void;
   reset: (options?: ResetEditorOptions) => void;
   setLargeLineSafeMode: (enabled: boolean) => void;
+  setReadOnly: (readOnly: boolean) => void;
   setFormatMode: (mode: FormatViewMode) => void;
   getFormatMode: () => FormatViewMode;
   toggleBold: () => void;
@@ -121,11 +120,13 @@ export type DocumentPort = {
     filePath: string;
     fileName: string;
     isDirty: boolean;
+    readOnly: boolean;
   };
   setRevision: (revision: number) => void;
   markCleanAt: (revision: number) => void;
   setFilePath: (filePath: string) => void;
   setUntitled: () => void;
+  setReadOnly: (readOnly: boolean) => void;
 };
 
 export type FontSelection = {
@@ -1,6 +1,7 @@
 import { onCleanup, onMount } from "solid-js";
 import type { CloseRequestEvent, DocumentPort, EditorPort } from "./contracts";
 import type { LaunchFileArg } from "../window/launchArgService";
+import type { FileChangedOnDiskEvent } from "../window/fileWatchService";
 
 type UseAppLifecycleOptions = {
   getEditorHost: () => HTMLDivElement | undefined;
@@ -11,6 +12,7 @@ type UseAppLifecycleOptions = {
   takeLaunchFileArg: () => Promise<LaunchFileArg | null>;
   openLaunchFileArg: (launchFile: LaunchFileArg) => Promise<void>;
   onLaunchFileOpenError: (error: unknown) => Promise<void>;
+  offerRecovery: () => Promise<void>;
   loadVersion: () => Promise<string>;
   setAppVersion: (version: string) => void;
   handleGlobalKeydown: (event: KeyboardEvent) => void;
@@ -18,10 +20,39 @@ type UseAppLifecycleOptions = {
     handler: (event: CloseRequestEvent) => void
   ) => Promise<() => void>;
   handleWindowCloseRequested: (event: CloseRequestEvent) => void;
+  registerFileChangedOnDisk: (
+    handler: (event: FileChangedOnDiskEvent) => void
+  ) => Promise<() => void>;
+  handleFileChangedOnDisk: (event: FileChangedOnDiskEvent) => Promise<void>;
+  registerOpenFilesRequested: (handler: (fileCount: number) => void) => Promise<() => void>;
+  /** Whether this window holds nothing worth keeping and can take a forwarded file. */
+  canOpenLaunchFileInPlace: () => boolean;
+  openPendingLaunchWindows: () => Promise<void>;
 };
 
 export const useAppLifecycle = (options: UseAppLifecycleOptions) => {
   let unlistenCloseRequest: (() => void) | undefined;
+  let unlistenFileChanged: (() => void) | undefined;
+  let unlistenOpenFiles: (() => void) | undefined;
+
+  const openNextLaunchFile = async () => {
+    const launchFile = await options.takeLaunchFileArg();
+    if (!launchFile) {
+      return;
+    }
+    try {
+      await options.openLaunchFileArg(launchFile);
+    } catch (error) {
+      await options.onLaunchFileOpenError(error);
+    }
+  };
+
+  const handleOpenFilesRequested = async () => {
+    if (options.canOpenLaunchFileInPlace()) {
+      await openNextLaunchFile();
+    }
+    await options.openPendingLaunchWindows();
+  };
 
   onMount(() => {
     const editorHost = options.getEditorHost();
@@ -59,19 +90,13 @@ export const useAppLifecycle = (options: UseAppLifecycleOptions) => {
     // recent files and the last directory, and those writes are dropped (and
     // then clobbered) if the settings store isn't ready yet.
     void settingsLoaded
-      .then(() => options.takeLaunchFileArg())
-      .then(async (launchFile) => {
-        if (!launchFile) {
-          return;
-        }
-        try {
-          await options.openLaunchFileArg(launchFile);
-        } catch (error) {
-          await options.onLaunchFileOpenError(error);
-        }
-      })
+      .then(() => openNextLaunchFile())
       .catch(() => {
         // ignore unavailable launch argument path
+      })
+      .then(() => options.offerRecovery())
+      .catch(() => {
+        // ignore an unreadable recovery store; nothing can be offered
       });
 
     void options
@@ -81,6 +106,26 @@ export const useAppLifecycle = (options: UseAppLifecycleOptions) => {
       .then((unlisten) => {
         unlistenCloseRequest = unlisten;
       });
+
+    void options
+      .registerFileChangedOnDisk((event) => {
+        void options.handleFileChangedOnDisk(event);
+      })
+      .then((unlisten) => {
+        unlistenFileChanged = unlisten;
+      });
+
+    void options
+      .registerOpenFilesRequested(() => {
+        void settingsLoaded
+          .then(() => handleOpenFilesRequested())
+          .catch(async (error) => {
+            await options.onLaunchFileOpenError(error);
+          });
+      })
+      .then((unlisten) => {
+        unlistenOpenFiles = unlisten;
+      });
   });
 
   onCleanup(() => {
@@ -89,6 +134,14 @@ export const useAppLifecycle = (options: UseAppLifecycleOptions) => {
       unlistenCloseRequest();
       unlistenCloseRequest = undefined;
     }
+    if (unlistenFileChanged) {
+      unlistenFileChanged();
+      unlistenFileChanged = undefined;
+    }
+    if (unlistenOpenFiles) {
+      unlistenOpenFiles();
+      unlistenOpenFiles = undefined;
+    }
     options.editor.destroy();
   });
 };
@@ -2,37 +2,64 @@ import type {
   DocumentPort,
   EditorPort,
   ErrorReporter,
+  ExportFileFilter,
   FileDialogsPort,
   FileIoPort,
   FontPickerPort,
+  OpenFileStreamPort,
   SettingsPort
 } from "./contracts";
-import { createSignal } from "solid-js";
-import type { LaunchFileStreamChunkResult } from "../window/launchArgService";
+import { createEffect, createSignal, on } from "solid-js";
+import type { SaveFileStreamOptions } from "../window/saveStreamService";
+import type { FileChangedOnDiskEvent, FileDiskStatus } from "../window/fileWatchService";
+import type { RecoveryEntry } from "../window/recoveryService";
+import type { ExportStyle } from "../window/exportService";
+import { STDIO_PATH, type LaunchPosition } from "../window/launchArgService";
 import { toAppError, type AppErrorCode } from "../errors/appError";
 
 type UseFileLifecycleDeps = {
-  editor: Pick<EditorPort, "focus" | "getText" | "getDocLength" | "getTextSlice" | "setText" | "append" | "reset" | "setLargeLineSafeMode" | "getRevision">;
-  document: Pick<DocumentPort, "state" | "setRevision" | "markCleanAt" | "setFilePath" | "setUntitled">;
+  editor: Pick<EditorPort, "focus" | "getText" | "getDocLength" | "getTextSlice" | "setText" | "append" | "reset" | "setLargeLineSafeMode" | "getRevision" | "goToPosition">;
+  document: Pick<DocumentPort, "state" | "setRevision" | "markCleanAt" | "setFilePath" | "setUntitled" | "setReadOnly">;
   settings: Pick<SettingsPort, "state" | "actions">;
   fileDialogs: FileDialogsPort;
   fileIo: FileIoPort;
-  launchFileStream: {
-    startLaunchFileStream: (filePath: string) => Promise<{ streamId: string; filePath: string; fileSizeBytes: number }>;
-    readLaunchFileChunk: (streamId: string, maxBytes: number) => Promise<LaunchFileStreamChunkResult>;
-    cancelLaunchFileStream: (streamId: string) => Promise<void>;
-    closeLaunchFileStream: (streamId: string) => Promise<void>;
-  };
+  openFileStream: OpenFileStreamPort;
   saveFileStream: {
-    startSaveFileStream: (filePath: string) => Promise<{ streamId: string; filePath: string }>;
+    startSaveFileStream: (filePath: string, options?: SaveFileStreamOptions) => Promise<{ streamId: string; filePath: string; requiresElevation: boolean }>;
     writeSaveFileChunk: (streamId: string, textChunk: string) => Promise<{ bytesWrittenTotal: number }>;
     finishSaveFileStream: (streamId: string) => Promise<{ bytesWrittenTotal: number }>;
     cancelSaveFileStream: (streamId: string) => Promise<void>;
   };
+  fileWatch: {
+    checkFileOnDisk: (filePath: string) => Promise<FileDiskStatus>;
+    unwatchFile: (filePath: string) => Promise<void>;
+  };
+  exporter: {
+    exportHtml: (filePath: string, text: string, style: ExportStyle) => Promise<unknown>;
+    exportPdf: (filePath: string, text: string, style: ExportStyle) => Promise<unknown>;
+    exportOdt: (filePath: string, text: string, style: ExportStyle) => Promise<unknown>;
+    exportDocx: (filePath: string, text: string, style: ExportStyle) => Promise<unknown>;
+  };
+  printer: {
+    printDocument: (text: string, style: ExportStyle, title: string) => Promise<boolean>;
+  };
   fontPicker: FontPickerPort;
   errors: ErrorReporter;
   confirmOpenLargeFile: (filePath: string, sizeBytes: number) => Promise<boolean>;
   showFileTooLarge: (filePath: string, sizeBytes: number) => Promise<void>;
+  confirmReloadChangedFile: (filePath: string, hasUnsavedChanges: boolean) => Promise<boolean>;
+  confirmOverwriteChangedFile: (filePath: string) => Promise<boolean>;
+  confirmElevatedSave: (filePath: string) => Promise<boolean>;
+  releaseLaunchWait: (waitId: number) => Promise<void>;
+  showFileMissingOnDisk: (filePath: string, kind: "removed" | "renamed") => Promise<void>;
+};
+
+/** What the command line asked for along with a launch file. */
+type LaunchOpenOptions = {
+  position?: LaunchPosition;
+  /** Replaces encoding detection. */
+  encoding?: string;
+  readOnly?: boolean;
 };
 
 const SOFT_FILE_LIMIT_BYTES = 50 * 1024 * 1024;
@@ -41,7 +68,7 @@ const LOADING_OVERLAY_DELAY_MS = 500;
 const BATCH_NORMAL_BYTES = 1024 * 1024;
 const BATCH_SAFE_MODE_BYTES = 256 * 1024;
 const SAFE_MODE_PROBE_BYTES = 8 * 1024 * 1024;
-const LAUNCH_STREAM_READ_BYTES = 256 * 1024;
+const OPEN_STREAM_READ_BYTES = 256 * 1024;
 const SAVE_STREAM_CHUNK_CHARS = 256 * 1024;
 const SAVING_OVERLAY_DELAY_MS = 500;
 
@@ -93,6 +120,53 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
   let loadingOverlayTimer: ReturnType<typeof setTimeout> | null = null;
   let activeSaveId = 0;
   let savingOverlayTimer: ReturnType<typeof setTimeout> | null = null;
+  // Encoding, byte-order mark and line-ending style reported by the backend
+  // when the current document was opened, so saving writes it back the same
+  // way. Undefined means a plain UTF-8 document with LF line endings.
+  let documentFormat: SaveFileStreamOptions | undefined;
+  let externalChangePromptOpen = false;
+  // Set when launched with `--stdout`: saving an untitled document sends it
+  // to standard output when the app exits instead of asking for a path.
+  let untitledSavesToStdout = false;
+  // The launch document a `wisty --wait` process is blocked on, if this
+  // window shows one.
+  let launchWait: { waitId: number; filePath: string } | undefined;
+
+  const releaseLaunchWait = () => {
+    if (!launchWait) {
+      return;
+    }
+    const { waitId } = launchWait;
+    launchWait = undefined;
+    void deps.releaseLaunchWait(waitId).catch(() => {
+      // closing the window releases it as well
+    });
+  };
+
+  // The backend starts watching a file once it has been fully opened or
+  // saved; stop watching the previous one whenever the document moves on.
+  createEffect(on(() => deps.document.state.filePath, (filePath, previousFilePath) => {
+    if (previousFilePath !== filePath) {
+      deps.document.setReadOnly(false);
+    }
+    if (previousFilePath && previousFilePath !== filePath) {
+      void deps.fileWatch.unwatchFile(previousFilePath).catch(() => {
+        // the watch is dropped with the app at worst
+      });
+      if (launchWait?.filePath === previousFilePath) {
+        releaseLaunchWait();
+      }
+    }
+  }));
+
+  /** Keeps a `--wait` process blocked until this window leaves `filePath`. */
+  const holdLaunchWait = (waitId: number, filePath: string) => {
+    launchWait = { waitId, filePath };
+    if (deps.document.state.filePath !== filePath) {
+      // the launch file failed to open, so there is nothing to wait for
+      releaseLaunchWait();
+    }
+  };
 
   const beginLoadingState = (filePath: string) => {
     activeLoadId += 1;
@@ -197,6 +271,12 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
       if (normalized.includes("save")) {
         return "SAVE_FAILED";
       }
+      if (normalized.includes("export")) {
+        return "EXPORT_FAILED";
+      }
+      if (normalized.includes("print")) {
+        return "PRINT_FAILED";
+      }
       if (normalized.includes("font")) {
         return "FONT_PICK_FAILED";
       }
@@ -225,6 +305,13 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     }
   };
 
+  // Used when the editor holds the only copy of the text: closing then asks
+  // first, and Save writes it out.
+  const markDocumentUnsaved = () => {
+    deps.document.markCleanAt(-1);
+    deps.document.setRevision(deps.editor.getRevision());
+  };
+
   const loadEditorTextAsClean = (text: string) => {
     deps.editor.reset({ emitChange: false, addToHistory: false });
     if (text.length > 0) {
@@ -377,16 +464,22 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     }
   };
 
-  const loadEditorFileAsCleanFromFsStream = async (filePath: string, expectedTotalBytes?: number) => {
-    await loadEditorFileAsCleanFromChunkSource(
-      filePath,
-      deps.fileIo.streamReadTextFile(filePath),
-      expectedTotalBytes
-    );
-  };
-
-  const loadEditorFileAsCleanFromLaunchStream = async (filePath: string, expectedTotalBytes?: number) => {
-    const stream = await deps.launchFileStream.startLaunchFileStream(filePath);
+  const loadEditorFileAsCleanFromOpenStream = async (
+    filePath: string,
+    expectedTotalBytes?: number,
+    errorCode: AppErrorCode = "OPEN_FAILED",
+    startStream = () => deps.openFileStream.startOpenFileStream(filePath)
+  ) => {
+    const stream = await startStream();
+    // The editor always hands back LF-separated text, so CRLF and CR files
+    // need converting on save to round-trip without spurious diffs. A file
+    // with mixed endings can't be written back as it was and is saved as LF.
+    documentFormat = {
+      encoding: stream.encoding,
+      writeBom: stream.hasBom,
+      lineEndingPolicy:
+        stream.lineEnding === "crlf" || stream.lineEnding === "cr" ? stream.lineEnding : "lf"
+    };
     let streamClosed = false;
 
     const closeStream = async () => {
@@ -394,19 +487,19 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
         return;
       }
       streamClosed = true;
-      await deps.launchFileStream.closeLaunchFileStream(stream.streamId);
+      await deps.openFileStream.closeOpenFileStream(stream.streamId);
     };
 
     const chunks = (async function* () {
       while (true) {
         if (cancelRequested()) {
-          await deps.launchFileStream.cancelLaunchFileStream(stream.streamId);
+          await deps.openFileStream.cancelOpenFileStream(stream.streamId);
           throw new FileLoadCancelledError();
         }
 
-        const next = await deps.launchFileStream.readLaunchFileChunk(
+        const next = await deps.openFileStream.readOpenFileChunk(
           stream.streamId,
-          LAUNCH_STREAM_READ_BYTES
+          OPEN_STREAM_READ_BYTES
         );
 
         if (next.kind === "eof") {
@@ -426,13 +519,13 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     } catch (error) {
       if (isFileLoadCancelledError(error)) {
         try {
-          await deps.launchFileStream.cancelLaunchFileStream(stream.streamId);
+          await deps.openFileStream.cancelOpenFileStream(stream.streamId);
         } catch {
           // ignore cancellation errors during teardown
         }
         throw error;
       }
-      throw toAppError(error, "LAUNCH_OPEN_FAILED", "Unable to open launch file", {
+      throw toAppError(error, errorCode, "Unable to open file", {
         filePath
       });
     } finally {
@@ -441,6 +534,7 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
   };
 
   const newFile = async () => {
+    documentFormat = undefined;
     applySafeMode(false);
     loadEditorTextAsClean("");
     deps.document.setUntitled();
@@ -470,7 +564,7 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
         }
       }
 
-      await loadEditorFileAsCleanFromFsStream(selected.filePath, fileSize);
+      await loadEditorFileAsCleanFromOpenStream(selected.filePath, fileSize);
       deps.document.setFilePath(selected.filePath);
       await deps.settings.actions.setLastDirectory(deps.fileIo.getDirectoryFromFilePath(selected.filePath));
       await deps.settings.actions.addRecentFile(selected.filePath);
@@ -481,7 +575,7 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
   const openFileAtPath = async (filePath: string) => {
     await runWithErrorMessage(async () => {
       try {
-        await loadEditorFileAsCleanFromFsStream(filePath);
+        await loadEditorFileAsCleanFromOpenStream(filePath);
         deps.document.setFilePath(filePath);
         await deps.settings.actions.setLastDirectory(deps.fileIo.getDirectoryFromFilePath(filePath));
         await deps.settings.actions.addRecentFile(filePath);
@@ -495,17 +589,46 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     }, "Unable to open file");
   };
 
-  const openLaunchFileAtPath = async (filePath: string, fileSizeBytes?: number) => {
+  const goToLaunchPosition = (position?: LaunchPosition) => {
+    if (position) {
+      deps.editor.goToPosition(position.line, position.column ?? undefined);
+    }
+  };
+
+  const openLaunchFileAtPath = async (filePath: string, fileSizeBytes?: number, options: LaunchOpenOptions = {}) => {
     await runWithErrorMessage(async () => {
-      await loadEditorFileAsCleanFromLaunchStream(filePath, fileSizeBytes);
+      await loadEditorFileAsCleanFromOpenStream(
+        filePath,
+        fileSizeBytes,
+        "LAUNCH_OPEN_FAILED",
+        () => deps.openFileStream.startOpenFileStream(filePath, options.encoding)
+      );
       deps.document.setFilePath(filePath);
+      // after the path, whose change clears it
+      deps.document.setReadOnly(options.readOnly ?? false);
       await deps.settings.actions.setLastDirectory(deps.fileIo.getDirectoryFromFilePath(filePath));
       await deps.settings.actions.addRecentFile(filePath);
+      goToLaunchPosition(options.position);
       deps.editor.focus();
     }, "Unable to open launch file");
   };
 
+  const openLaunchStdin = async (fileSizeBytes?: number, options: LaunchOpenOptions = {}) => {
+    await runWithErrorMessage(async () => {
+      await loadEditorFileAsCleanFromOpenStream(
+        STDIO_PATH,
+        fileSizeBytes,
+        "LAUNCH_OPEN_FAILED",
+        () => deps.openFileStream.startStdinStream(options.encoding)
+      );
+      deps.document.setReadOnly(options.readOnly ?? false);
+      goToLaunchPosition(options.position);
+      deps.editor.focus();
+    }, "Unable to read standard input");
+  };
+
   const openFileFromTextAtPath = async (filePath: string, text: string) => {
+    documentFormat = undefined;
     const useLargeLineSafeMode = text.length >= SAFE_MODE_PROBE_BYTES && !text.includes("\n");
     applySafeMode(useLargeLineSafeMode);
     loadEditorTextAsClean(text);
@@ -514,7 +637,9 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     deps.editor.focus();
   };
 
-  const openMissingFileAtPath = async (filePath: string) => {
+  /** `encoding` is what the new file will be saved as, from `--encoding`. */
+  const openMissingFileAtPath = async (filePath: string, encoding?: string) => {
+    documentFormat = encoding ? { encoding } : undefined;
     applySafeMode(false);
     loadEditorTextAsClean("");
     deps.document.setFilePath(filePath);
@@ -522,7 +647,7 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     deps.editor.focus();
   };
 
-  const saveDocumentToPathViaStream = async (filePath: string) => {
+  const saveDocumentToPathViaStream = async (filePath: string, overwriteExternalChanges = false) => {
     const totalChars = deps.editor.getDocLength();
     const saveId = beginSavingState(filePath, totalChars);
     let streamId: string | undefined;
@@ -530,8 +655,14 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     let charsWritten = 0;
 
     try {
-      const started = await deps.saveFileStream.startSaveFileStream(filePath);
+      const started = await deps.saveFileStream.startSaveFileStream(filePath, {
+        ...documentFormat,
+        overwriteExternalChanges
+      });
       streamId = started.streamId;
+      if (started.requiresElevation && !await deps.confirmElevatedSave(filePath)) {
+        throw new FileSaveCancelledError();
+      }
 
       let from = 0;
       while (from < totalChars) {
@@ -598,7 +729,8 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
         return;
       }
 
-      await saveDocumentToPathViaStream(result.filePath);
+      // The Save As dialog has already confirmed replacing an existing file.
+      await saveDocumentToPathViaStream(result.filePath, true);
       deps.document.setFilePath(result.filePath);
       deps.document.markCleanAt(deps.editor.getRevision());
       await deps.settings.actions.setLastDirectory(deps.fileIo.getDirectoryFromFilePath(result.filePath));
@@ -607,20 +739,156 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     }, "Unable to save file");
   };
 
+  const documentFileName = (): string => {
+    const filePath = deps.document.state.filePath;
+    return filePath ? filePath.replace(/\\/g, "/").split("/").pop() ?? "" : "";
+  };
+
+  // Exports sit next to the document under its name, or in the last
+  // directory for an untitled one.
+  const exportDefaultPath = (extension: string): string => {
+    const filePath = deps.document.state.filePath;
+    const directory = filePath
+      ? deps.fileIo.getDirectoryFromFilePath(filePath)
+      : deps.settings.state.lastDirectory;
+    const stem = documentFileName().replace(/\.[^.]*$/, "") || "Untitled";
+    return directory ? `${directory.replace(/\/$/, "")}/${stem}.${extension}` : `${stem}.${extension}`;
+  };
+
+  const exportStyle = (): ExportStyle => ({
+    fontFamily: deps.settings.state.fontFamily,
+    fontSize: deps.settings.state.fontSize,
+    fontStyle: deps.settings.state.fontStyle,
+    fontWeight: deps.settings.state.fontWeight,
+    themeMode: deps.settings.state.themeMode
+  });
+
+  const exportDocument = async (
+    filter: ExportFileFilter,
+    write: (filePath: string, text: string) => Promise<unknown>
+  ) => {
+    await runWithErrorMessage(async () => {
+      const result = await deps.fileDialogs.saveExportPathAs(exportDefaultPath(filter.extensions[0]), filter);
+      if (result.kind === "cancelled") {
+        deps.editor.focus();
+        return;
+      }
+
+      // The document itself is untouched: it stays dirty or clean as it was.
+      await write(result.filePath, deps.editor.getText());
+      deps.editor.focus();
+    }, "Unable to export file");
+  };
+
+  const exportHtml = () =>
+    exportDocument({ name: "HTML", extensions: ["html"] }, (filePath, text) =>
+      deps.exporter.exportHtml(filePath, text, exportStyle())
+    );
+
+  const exportPdf = () =>
+    exportDocument({ name: "PDF", extensions: ["pdf"] }, (filePath, text) =>
+      deps.exporter.exportPdf(filePath, text, exportStyle())
+    );
+
+  const exportOdt = () =>
+    exportDocument({ name: "OpenDocument Text", extensions: ["odt"] }, (filePath, text) =>
+      deps.exporter.exportOdt(filePath, text, exportStyle())
+    );
+
+  const exportDocx = () =>
+    exportDocument({ name: "Word Document", extensions: ["docx"] }, (filePath, text) =>
+      deps.exporter.exportDocx(filePath, text, exportStyle())
+    );
+
+  const printDocument = async () => {
+    await runWithErrorMessage(async () => {
+      await deps.printer.printDocument(deps.editor.getText(), exportStyle(), documentFileName() || "Untitled");
+      deps.editor.focus();
+    }, "Unable to print file");
+  };
+
+  const saveFileToStdout = async () => {
+    await runWithErrorMessage(async () => {
+      await saveDocumentToPathViaStream(STDIO_PATH);
+      deps.document.markCleanAt(deps.editor.getRevision());
+      deps.editor.focus();
+    }, "Unable to save file");
+  };
+
   const saveFile = async () => {
     if (!deps.document.state.filePath) {
+      if (untitledSavesToStdout) {
+        await saveFileToStdout();
+        return;
+      }
       await saveFileAs();
       return;
     }
 
     await runWithErrorMessage(async () => {
-      await saveDocumentToPathViaStream(deps.document.state.filePath);
+      const filePath = deps.document.state.filePath;
+      const diskStatus = await deps.fileWatch.checkFileOnDisk(filePath);
+      const overwriteExternalChanges = diskStatus === "modified";
+      if (overwriteExternalChanges && !await deps.confirmOverwriteChangedFile(filePath)) {
+        deps.editor.focus();
+        return;
+      }
+
+      await saveDocumentToPathViaStream(filePath, overwriteExternalChanges);
       deps.document.markCleanAt(deps.editor.getRevision());
       await deps.settings.actions.setLastDirectory(deps.fileIo.getDirectoryFromFilePath(deps.document.state.filePath));
       deps.editor.focus();
     }, "Unable to save file");
   };
 
+  const handleFileChangedOnDisk = async (event: FileChangedOnDiskEvent) => {
+    const filePath = deps.document.state.filePath;
+    // An event may still arrive for the file this window just moved on from.
+    if (!filePath || event.path !== filePath || isLoading() || isSaving() || externalChangePromptOpen) {
+      return;
+    }
+
+    externalChangePromptOpen = true;
+    try {
+      if (event.kind === "modified") {
+        const shouldReload = await deps.confirmReloadChangedFile(filePath, deps.document.state.isDirty);
+        if (shouldReload) {
+          await runWithErrorMessage(async () => {
+            await loadEditorFileAsCleanFromOpenStream(filePath);
+            deps.document.setFilePath(filePath);
+          }, "Unable to reload file");
+        }
+        return;
+      }
+
+      markDocumentUnsaved();
+      await deps.showFileMissingOnDisk(filePath, event.kind);
+    } finally {
+      externalChangePromptOpen = false;
+      deps.editor.focus();
+    }
+  };
+
+  const restoreRecoveredDocument = async (entry: RecoveryEntry) => {
+    let restored = false;
+    await runWithErrorMessage(async () => {
+      await loadEditorFileAsCleanFromOpenStream(entry.snapshotPath);
+      // Snapshots are always UTF-8; save in the format the document had.
+      documentFormat = {
+        encoding: entry.encoding ?? undefined,
+        writeBom: entry.writeBom ?? undefined,
+        lineEndingPolicy: entry.lineEndingPolicy ?? undefined
+      };
+      if (entry.filePath) {
+        deps.document.setFilePath(entry.filePath);
+      }
+      markDocumentUnsaved();
+      restored = true;
+      deps.editor.focus();
+    }, "Unable to restore unsaved changes");
+    return restored;
+  };
+
   const chooseEditorFont = async () => {
     await runWithErrorMessage(async () => {
       const selection = await deps.fontPicker.chooseEditorFont({
@@ -648,10 +916,23 @@ export const useFileLifecycle = (deps: UseFileLifecycleDeps) => {
     openFile,
     openFileAtPath,
     openLaunchFileAtPath,
+    openLaunchStdin,
     openFileFromTextAtPath,
     openMissingFileAtPath,
     saveFile,
     saveFileAs,
+    exportHtml,
+    exportPdf,
+    exportOdt,
+    exportDocx,
+    printDocument,
+    handleFileChangedOnDisk,
+    restoreRecoveredDocument,
+    holdLaunchWait,
+    getDocumentFormat: () => documentFormat,
+    setUntitledSavesToStdout: (enabled: boolean) => {
+      untitledSavesToStdout = enabled;
+    },
     chooseEditorFont,
     requestCancelLoading,
     requestCancelSaving,
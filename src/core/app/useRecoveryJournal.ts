@@ -0,0 +1,141 @@
+import { createEffect, on, onCleanup, onMount, type Accessor } from "solid-js";
+import type { DocumentPort, EditorPort } from "./contracts";
+import type { SaveFileStreamOptions } from "../window/saveStreamService";
+import type {
+  RecoveryEntry,
+  RecoverySnapshotStartResult,
+  RecoverySnapshotTarget
+} from "../window/recoveryService";
+
+type UseRecoveryJournalDeps = {
+  editor: Pick<EditorPort, "getDocLength" | "getTextSlice" | "getRevision">;
+  document: Pick<DocumentPort, "state">;
+  isBusy: Accessor<boolean>;
+  getDocumentFormat: () => SaveFileStreamOptions | undefined;
+  recovery: {
+    startRecoverySnapshot: (
+      target: RecoverySnapshotTarget,
+      format?: SaveFileStreamOptions
+    ) => Promise<RecoverySnapshotStartResult>;
+    writeRecoverySnapshotChunk: (streamId: string, textChunk: string) => Promise<void>;
+    finishRecoverySnapshot: (streamId: string) => Promise<RecoveryEntry>;
+    cancelRecoverySnapshot: (streamId: string) => Promise<void>;
+    discardRecoverySnapshot: (documentId: string) => Promise<void>;
+  };
+};
+
+const SNAPSHOT_INTERVAL_MS = 15 * 1000;
+const SNAPSHOT_CHUNK_CHARS = 256 * 1024;
+
+/**
+ * Keeps a snapshot of the unsaved document in the backend's recovery store,
+ * refreshed every few seconds while there are unsaved edits and removed as
+ * soon as the document is saved or discarded.
+ */
+export const useRecoveryJournal = (deps: UseRecoveryJournalDeps) => {
+  // One id per session is enough: only one untitled buffer is open at a time,
+  // and its snapshot is discarded before the next one can exist.
+  const untitledId = crypto.randomUUID();
+  let snapshotDocumentId: string | undefined;
+  let snapshotRevision: number | undefined;
+  let snapshotInFlight = false;
+  let timer: ReturnType<typeof setInterval> | null = null;
+
+  const discardSnapshot = async () => {
+    const documentId = snapshotDocumentId;
+    snapshotDocumentId = undefined;
+    snapshotRevision = undefined;
+    if (documentId) {
+      await deps.recovery.discardRecoverySnapshot(documentId);
+    }
+  };
+
+  const writeSnapshot = async () => {
+    const revision = deps.editor.getRevision();
+    const totalChars = deps.editor.getDocLength();
+    const started = await deps.recovery.startRecoverySnapshot(
+      { filePath: deps.document.state.filePath, untitledId },
+      deps.getDocumentFormat()
+    );
+
+    let finished = false;
+    try {
+      for (let from = 0; from < totalChars;) {
+        let to = Math.min(totalChars, from + SNAPSHOT_CHUNK_CHARS);
+        const lastCharCode = deps.editor.getTextSlice(to - 1, to).charCodeAt(0);
+        if (to < totalChars && lastCharCode >= 0xd800 && lastCharCode <= 0xdbff) {
+          to -= 1;
+        }
+        await deps.recovery.writeRecoverySnapshotChunk(started.streamId, deps.editor.getTextSlice(from, to));
+        from = to;
+      }
+      await deps.recovery.finishRecoverySnapshot(started.streamId);
+      finished = true;
+    } finally {
+      if (!finished) {
+        await deps.recovery.cancelRecoverySnapshot(started.streamId);
+      }
+    }
+
+    // Saving under a new path moves the snapshot to a new id.
+    if (snapshotDocumentId && snapshotDocumentId !== started.documentId) {
+      await deps.recovery.discardRecoverySnapshot(snapshotDocumentId);
+    }
+    snapshotDocumentId = started.documentId;
+    snapshotRevision = revision;
+  };
+
+  const tick = async () => {
+    if (snapshotInFlight || deps.isBusy()) {
+      return;
+    }
+    snapshotInFlight = true;
+    try {
+      if (!deps.document.state.isDirty) {
+        await discardSnapshot();
+        return;
+      }
+      if (snapshotRevision === deps.editor.getRevision()) {
+        return;
+      }
+      await writeSnapshot();
+    } catch {
+      // the next tick retries; recovery must never interrupt editing
+    } finally {
+      snapshotInFlight = false;
+    }
+  };
+
+  /**
+   * Takes over a snapshot restored from an earlier session, so it is
+   * replaced or discarded like one written by this session.
+   */
+  const adoptSnapshot = (documentId: string) => {
+    snapshotDocumentId = documentId;
+    snapshotRevision = undefined;
+    void tick();
+  };
+
+  createEffect(on(() => deps.document.state.isDirty, (isDirty) => {
+    if (!isDirty) {
+      void tick();
+    }
+  }, { defer: true }));
+
+  onMount(() => {
+    timer = setInterval(() => {
+      void tick();
+    }, SNAPSHOT_INTERVAL_MS);
+  });
+
+  onCleanup(() => {
+    if (timer !== null) {
+      clearInterval(timer);
+      timer = null;
+    }
+  });
+
+  return {
+    adoptSnapshot
+  };
+};
@@ -1,6 +1,7 @@
 import { describe, expect, it, vi } from "vitest";
-import { buildCommands } from "./buildCommands";
-import type { FormatViewMode } from "../settings/settingsTypes";
+import { buildCommands, spellRemoveCommandId } from "./buildCommands";
+import type { BackupMode, FormatViewMode } from "../settings/settingsTypes";
+import type { DictionaryInfo } from "../spellcheck/spellService";
 
 const createDeps = (overrides: { formatViewMode?: FormatViewMode; activeLineHighlightEnabled?: boolean } = {}) => {
   const settingsState = {
@@ -10,7 +11,9 @@ const createDeps = (overrides: { formatViewMode?: FormatViewMode; activeLineHigh
     formatViewMode: overrides.formatViewMode ?? "plain",
     statusBarEnabled: true,
     spellCheckEnabled: false,
-    spellCheckLanguage: "en_US",
+    spellCheckLanguages: ["en_US"],
+    spellCheckDetectLanguage: true,
+    backupMode: "off" as BackupMode,
     recentFiles: [] as string[]
   };
 
@@ -26,6 +29,11 @@ const createDeps = (overrides: { formatViewMode?: FormatViewMode; activeLineHigh
       openFileAtPath: vi.fn(async () => {}),
       saveFile: vi.fn(async () => {}),
       saveFileAs: vi.fn(async () => {}),
+      exportHtml: vi.fn(async () => {}),
+      exportPdf: vi.fn(async () => {}),
+      exportOdt: vi.fn(async () => {}),
+      exportDocx: vi.fn(async () => {}),
+      printDocument: vi.fn(async () => {}),
       chooseEditorFont: vi.fn(async () => {}),
       safeModeActive: () => false
     },
@@ -53,14 +61,21 @@ const createDeps = (overrides: { formatViewMode?: FormatViewMode; activeLineHigh
         }),
         setStatusBarEnabled: vi.fn(async () => {}),
         setSpellCheckEnabled: vi.fn(async () => {}),
-        setSpellCheckLanguage: vi.fn(async () => {})
+        setSpellCheckLanguages: vi.fn(async () => {}),
+        setSpellCheckDetectLanguage: vi.fn(async () => {}),
+        setBackupMode: vi.fn(async (mode: BackupMode) => {
+          settingsState.backupMode = mode;
+        })
       }
     },
     spell: {
-      dictionaries: () => [],
+      dictionaries: (): DictionaryInfo[] => [],
       showInstallHelp: vi.fn(),
-      showAddedWords: vi.fn()
+      installDictionary: vi.fn(async () => {}),
+      showAddedWords: vi.fn(),
+      showReport: vi.fn(async () => {})
     },
+    showDocumentStats: vi.fn(async () => {}),
     showAbout: vi.fn(async () => {})
   };
 
@@ -202,3 +217,87 @@ describe("view.activeLineHighlight command", () => {
     expect(ids).toContain("view.activeLineHighlight");
   });
 });
+
+describe("file.backup commands", () => {
+  it("select the backup mode and check only the active one", async () => {
+    const deps = createDeps();
+    const { definitions } = buildCommands(deps);
+
+    expect(findCommand(definitions, "file.backup.off").checked!()).toBe(true);
+
+    await findCommand(definitions, "file.backup.numbered").run();
+
+    expect(deps.settings.actions.setBackupMode).toHaveBeenCalledWith("numbered");
+    expect(findCommand(definitions, "file.backup.numbered").checked!()).toBe(true);
+    expect(findCommand(definitions, "file.backup.off").checked!()).toBe(false);
+  });
+
+  it("are grouped in a File submenu", () => {
+    const deps = createDeps();
+    const { sections } = buildCommands(deps);
+    const fileSection = sections.find((section) => section.id === "file");
+    const submenu = fileSection!.items.find((item) => item.type === "submenu" && item.id === "file.backup");
+    expect(submenu).toBeDefined();
+  });
+});
+
+describe("file.print command", () => {
+  it("is bound to Ctrl+P, listed in the File menu and prints through the file lifecycle", () => {
+    const deps = createDeps();
+    const { definitions, sections } = buildCommands(deps);
+
+    const print = findCommand(definitions, "file.print");
+    expect(print.shortcut).toBe("Ctrl+P");
+    print.run();
+    expect(deps.fileLifecycle.printDocument).toHaveBeenCalledOnce();
+
+    const fileSection = sections.find((section) => section.id === "file");
+    expect(fileSection!.items).toContainEqual({ type: "command", commandId: "file.print" });
+  });
+});
+
+describe("view.spellCheck submenu", () => {
+  const spellSubmenu = (deps: ReturnType<typeof createDeps>) => {
+    const viewSection = buildCommands(deps).sections.find((section) => section.id === "view");
+    const submenu = viewSection!.items.find((item) => item.type === "submenu" && item.id === "view.spellCheck");
+    return submenu!.type === "submenu" ? submenu!.items() : [];
+  };
+
+  it("offers to install a dictionary when none are installed", () => {
+    const deps = createDeps();
+    expect(spellSubmenu(deps)).toContainEqual({ type: "command", commandId: "view.spellCheck.install" });
+  });
+
+  it("lists only installed packages under Remove Dictionary", () => {
+    const deps = createDeps();
+    const dictionaries: DictionaryInfo[] = [
+      { code: "en_US", label: "English (United States)", directory: "/usr/share/hunspell", removable: false },
+      { code: "de_DE", label: "German (Germany)", directory: "/home/me/.local/share/hunspell", removable: true }
+    ];
+    deps.spell.dictionaries = () => dictionaries;
+
+    const remove = spellSubmenu(deps).find((item) => item.type === "submenu" && item.id === "view.spellCheck.remove");
+    expect(remove!.visible!()).toBe(true);
+    expect(remove!.type === "submenu" && remove!.items()).toEqual([
+      { type: "command", commandId: spellRemoveCommandId("de_DE") }
+    ]);
+
+    dictionaries[1].removable = false;
+    expect(remove!.visible!()).toBe(false);
+  });
+});
+
+describe("view.spellCheck.report command", () => {
+  it("is only enabled while spell check is on", async () => {
+    const deps = createDeps();
+    const { definitions } = buildCommands(deps);
+    const report = findCommand(definitions, "view.spellCheck.report");
+
+    expect(report.enabled!()).toBe(false);
+    deps.settings.state.spellCheckEnabled = true;
+    expect(report.enabled!()).toBe(true);
+
+    await report.run();
+    expect(deps.spell.showReport).toHaveBeenCalledOnce();
+  });
+});
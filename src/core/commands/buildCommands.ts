@@ -1,11 +1,14 @@
 import type { CommandDefinition, MenuItem, MenuSection } from "./commandRegistry";
 import type { Accessor } from "solid-js";
 import type { DictionaryInfo } from "../spellcheck/spellService";
-import type { FormatViewMode } from "../settings/settingsTypes";
+import type { BackupMode, FormatViewMode } from "../settings/settingsTypes";
 
 /** Stable command id for selecting a given spell-check dictionary. */
 export const spellLanguageCommandId = (code: string) => `view.spellCheck.lang.${code}`;
 
+/** Stable command id for removing a given installed spell-check dictionary. */
+export const spellRemoveCommandId = (code: string) => `view.spellCheck.remove.${code}`;
+
 const fileNameFromPath = (filePath: string): string => {
   const normalized = filePath.replace(/\\/g, "/");
   const segments = normalized.split("/");
@@ -26,6 +29,11 @@ type BuildCommandsDeps = {
     openFileAtPath: (filePath: string) => Promise<void>;
     saveFile: () => Promise<void>;
     saveFileAs: () => Promise<void>;
+    exportHtml: () => Promise<void>;
+    exportPdf: () => Promise<void>;
+    exportOdt: () => Promise<void>;
+    exportDocx: () => Promise<void>;
+    printDocument: () => Promise<void>;
     chooseEditorFont: () => Promise<void>;
     safeModeActive: Accessor<boolean>;
   };
@@ -51,7 +59,9 @@ type BuildCommandsDeps = {
       formatViewMode: FormatViewMode;
       statusBarEnabled: boolean;
       spellCheckEnabled: boolean;
-      spellCheckLanguage: string;
+      spellCheckLanguages: string[];
+      spellCheckDetectLanguage: boolean;
+      backupMode: BackupMode;
       recentFiles: string[];
     };
     actions: {
@@ -60,14 +70,19 @@ type BuildCommandsDeps = {
       setActiveLineHighlightEnabled: (enabled: boolean) => Promise<void>;
       setStatusBarEnabled: (enabled: boolean) => Promise<void>;
       setSpellCheckEnabled: (enabled: boolean) => Promise<void>;
-      setSpellCheckLanguage: (language: string) => Promise<void>;
+      setSpellCheckLanguages: (languages: string[]) => Promise<void>;
+      setSpellCheckDetectLanguage: (enabled: boolean) => Promise<void>;
+      setBackupMode: (mode: BackupMode) => Promise<void>;
     };
   };
   spell: {
     dictionaries: Accessor<DictionaryInfo[]>;
     showInstallHelp: () => void;
+    installDictionary: () => Promise<void>;
     showAddedWords: () => void;
+    showReport: () => Promise<void>;
   };
+  showDocumentStats: () => Promise<void>;
   showAbout: () => Promise<void>;
 };
 
@@ -99,6 +114,44 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
       shortcut: commandShortcut(deps.platform.isMac, "S", true),
       run: deps.fileLifecycle.saveFileAs
     },
+    {
+      id: "file.export.html",
+      label: "HTML...",
+      run: deps.fileLifecycle.exportHtml
+    },
+    {
+      id: "file.export.pdf",
+      label: "PDF...",
+      run: deps.fileLifecycle.exportPdf
+    },
+    {
+      id: "file.export.odt",
+      label: "OpenDocument Text...",
+      run: deps.fileLifecycle.exportOdt
+    },
+    {
+      id: "file.export.docx",
+      label: "Word Document...",
+      run: deps.fileLifecycle.exportDocx
+    },
+    {
+      id: "file.print",
+      label: "Print...",
+      shortcut: commandShortcut(deps.platform.isMac, "P"),
+      run: deps.fileLifecycle.printDocument
+    },
+    ...([
+      ["off", "Off"],
+      ["single", "Single Backup (file~)"],
+      ["numbered", "Numbered Backups (file.~1~)"],
+      ["timestamped", "Timestamped Backups"]
+    ] as const).map(([mode, label]): CommandDefinition => ({
+      id: `file.backup.${mode}`,
+      label,
+      refocusEditorOnMenuSelect: true,
+      run: () => deps.settings.actions.setBackupMode(mode),
+      checked: () => deps.settings.state.backupMode === mode
+    })),
     {
       id: "file.quit",
       label: "Quit",
@@ -291,6 +344,11 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
       run: () => deps.settings.actions.setStatusBarEnabled(!deps.settings.state.statusBarEnabled),
       checked: () => deps.settings.state.statusBarEnabled
     },
+    {
+      id: "view.documentStats",
+      label: "Document Statistics...",
+      run: deps.showDocumentStats
+    },
     {
       id: "view.spellCheck.off",
       label: "Off",
@@ -309,11 +367,29 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
       label: "How to Install Dictionaries...",
       run: () => deps.spell.showInstallHelp()
     },
+    {
+      id: "view.spellCheck.install",
+      label: "Install Dictionary...",
+      run: deps.spell.installDictionary
+    },
+    {
+      id: "view.spellCheck.detectLanguage",
+      label: "Detect Language on Open",
+      refocusEditorOnMenuSelect: true,
+      run: () => deps.settings.actions.setSpellCheckDetectLanguage(!deps.settings.state.spellCheckDetectLanguage),
+      checked: () => deps.settings.state.spellCheckDetectLanguage
+    },
     {
       id: "view.spellCheck.addedWords",
       label: "Added Words...",
       run: () => deps.spell.showAddedWords()
     },
+    {
+      id: "view.spellCheck.report",
+      label: "Spelling Report...",
+      enabled: () => deps.settings.state.spellCheckEnabled,
+      run: deps.spell.showReport
+    },
     {
       id: "view.font.browser",
       label: "Font...",
@@ -337,6 +413,30 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
         { type: "separator" },
         { type: "command", commandId: "file.save" },
         { type: "command", commandId: "file.saveAs" },
+        {
+          type: "submenu",
+          id: "file.export",
+          label: "Export As",
+          items: (): MenuItem[] => [
+            { type: "command", commandId: "file.export.html" },
+            { type: "command", commandId: "file.export.pdf" },
+            { type: "command", commandId: "file.export.odt" },
+            { type: "command", commandId: "file.export.docx" }
+          ]
+        },
+        { type: "command", commandId: "file.print" },
+        {
+          type: "submenu",
+          id: "file.backup",
+          label: "Backups on Save",
+          items: (): MenuItem[] => [
+            { type: "command", commandId: "file.backup.off" },
+            { type: "separator" },
+            { type: "command", commandId: "file.backup.single" },
+            { type: "command", commandId: "file.backup.numbered" },
+            { type: "command", commandId: "file.backup.timestamped" }
+          ]
+        },
         { type: "separator", visible: () => deps.settings.state.recentFiles.length > 0 },
         { type: "command", commandId: "file.recent.1", visible: () => deps.settings.state.recentFiles.length >= 1 },
         { type: "command", commandId: "file.recent.2", visible: () => deps.settings.state.recentFiles.length >= 2 },
@@ -395,6 +495,7 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
         { type: "command", commandId: "view.formatMode" },
         { type: "command", commandId: "view.activeLineHighlight" },
         { type: "command", commandId: "view.statusBar" },
+        { type: "command", commandId: "view.documentStats" },
         {
           type: "submenu",
           id: "view.spellCheck",
@@ -405,6 +506,7 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
               return [
                 { type: "command", commandId: "view.spellCheck.none" },
                 { type: "command", commandId: "view.spellCheck.help" },
+                { type: "command", commandId: "view.spellCheck.install" },
                 { type: "command", commandId: "view.spellCheck.addedWords" }
               ];
             }
@@ -416,7 +518,20 @@ export const buildCommands = (deps: BuildCommandsDeps): { definitions: CommandDe
                 commandId: spellLanguageCommandId(dictionary.code)
               })),
               { type: "separator" },
-              { type: "command", commandId: "view.spellCheck.addedWords" }
+              { type: "command", commandId: "view.spellCheck.detectLanguage" },
+              { type: "command", commandId: "view.spellCheck.addedWords" },
+              { type: "command", commandId: "view.spellCheck.report" },
+              { type: "separator" },
+              { type: "command", commandId: "view.spellCheck.install" },
+              {
+                type: "submenu",
+                id: "view.spellCheck.remove",
+                label: "Remove Dictionary",
+                visible: () => deps.spell.dictionaries().some((dictionary) => dictionary.removable),
+                items: () => deps.spell.dictionaries()
+                  .filter((dictionary) => dictionary.removable)
+                  .map((dictionary): MenuItem => ({ type: "command", commandId: spellRemoveCommandId(dictionary.code) }))
+              }
             ];
           }
         },
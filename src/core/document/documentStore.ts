@@ -4,6 +4,8 @@ export type DocumentMetaState = {
   filePath: string;
   fileName: string;
   isDirty: boolean;
+  /** Opened with `--readonly`; cleared when the window moves on to another document. */
+  readOnly: boolean;
   currentRevision: number;
   baselineRevision: number;
 };
@@ -24,6 +26,7 @@ export const createDocumentStore = () => {
     filePath: "",
     fileName: UNTITLED,
     isDirty: false,
+    readOnly: false,
     currentRevision: 0,
     baselineRevision: 0
   });
@@ -54,11 +57,16 @@ export const createDocumentStore = () => {
     setState({ filePath: "", fileName: UNTITLED });
   };
 
+  const setReadOnly = (readOnly: boolean) => {
+    setState({ readOnly });
+  };
+
   return {
     state,
     setRevision,
     markCleanAt,
     setFilePath,
-    setUntitled
+    setUntitled,
+    setReadOnly
   };
 };
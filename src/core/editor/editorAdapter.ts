@@ -45,6 +45,9 @@ type AppendTextOptions = {
   addToHistory?: boolean;
 };
 
+// A few paragraphs are enough to tell which language a document is in.
+const SPELL_DETECT_SAMPLE_CHARS = 8 * 1024;
+
 type ResetEditorOptions = {
   emitChange?: boolean;
   addToHistory?: boolean;
@@ -62,13 +65,17 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
   const spellService = createSpellService();
   const spellExtension = createSpellcheckExtension(spellService);
   let spellEnabled = false;
-  let spellLoadedLanguage: string | undefined;
+  // The languages last asked for, and those of them that were found.
+  let spellRequestedLanguages: string[] = [];
+  let spellLoadedLanguages: string[] = [];
   let spellDictionaryDirty = false;
 
   const wrapCompartment = new Compartment();
   const activeLineCompartment = new Compartment();
   const styleCompartment = new Compartment();
   const spellCompartment = new Compartment();
+  const readOnlyCompartment = new Compartment();
+  let readOnlyEnabled = false;
 
   const formatting = createFormatting(() => options.getSettings().formatViewMode);
 
@@ -246,6 +253,7 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
         wrapCompartment.of(!largeLineSafeModeEnabled && settings.textWrapEnabled ? EditorView.lineWrapping : []),
         activeLineCompartment.of(settings.activeLineHighlightEnabled ? highlightActiveLine() : []),
         spellCompartment.of(spellEnabled ? spellExtension : []),
+        readOnlyCompartment.of(EditorState.readOnly.of(readOnlyEnabled)),
         styleCompartment.of(createStyleExtension()),
         formatting.extension,
         EditorView.updateListener.of((update) => {
@@ -351,6 +359,21 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
 
   const getDocLength = () => editorView?.state.doc.length ?? 0;
 
+  /** Moves the cursor to a 1-based line and column, clamped to the document, and scrolls it into view. */
+  const goToPosition = (line: number, column = 1) => {
+    const view = editorView;
+    if (!view) {
+      return;
+    }
+    const doc = view.state.doc;
+    const target = doc.line(Math.min(Math.max(line, 1), doc.lines));
+    const anchor = target.from + Math.min(Math.max(column, 1) - 1, target.length);
+    view.dispatch({
+      selection: { anchor },
+      effects: EditorView.scrollIntoView(anchor, { y: "center" })
+    });
+  };
+
   const getTextSlice = (from: number, to: number) => {
     if (!editorView) {
       return "";
@@ -440,15 +463,17 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
 
   const listSpellDictionaries = () => spellService.listDictionaries();
 
+  const detectSpellLanguage = () => spellService.detectLanguage(getTextSlice(0, SPELL_DETECT_SAMPLE_CHARS));
+
   const listAddedWords = () => spellService.listAddedWords();
 
   const removeAddedWord = async (word: string) => {
     await spellService.removeWord(word);
-    if (!spellLoadedLanguage) {
+    if (spellLoadedLanguages.length === 0) {
       return;
     }
     if (spellEnabled) {
-      await spellService.loadDictionary(spellLoadedLanguage);
+      await spellService.loadDictionaries(spellLoadedLanguages);
       if (editorView) {
         editorView.dispatch({ effects: requestSpellRescan.of(null) });
       }
@@ -459,14 +484,28 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
     }
   };
 
-  const configureSpellcheck = async ({ enabled, language }: { enabled: boolean; language: string }) => {
-    if (enabled && language && (language !== spellLoadedLanguage || spellDictionaryDirty)) {
-      const loaded = await spellService.loadDictionary(language);
-      spellLoadedLanguage = loaded ? language : undefined;
+  // Installing or removing files can change what a loaded code resolves to,
+  // so the next configureSpellcheck reloads even if the codes are the same.
+  const installSpellDictionary = async (packagePath: string) => {
+    const installed = await spellService.installDictionary(packagePath);
+    spellDictionaryDirty = true;
+    return installed;
+  };
+
+  const uninstallSpellDictionary = async (code: string) => {
+    await spellService.uninstallDictionary(code);
+    spellDictionaryDirty = true;
+  };
+
+  const configureSpellcheck = async ({ enabled, languages }: { enabled: boolean; languages: string[] }) => {
+    const changed = languages.join("\n") !== spellRequestedLanguages.join("\n");
+    if (enabled && languages.length > 0 && (changed || spellDictionaryDirty)) {
+      spellRequestedLanguages = [...languages];
+      spellLoadedLanguages = await spellService.loadDictionaries(languages);
       spellDictionaryDirty = false;
     }
 
-    spellEnabled = enabled && spellLoadedLanguage !== undefined;
+    spellEnabled = enabled && spellLoadedLanguages.length > 0;
 
     if (!editorView) {
       return;
@@ -487,6 +526,18 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
     applySettings();
   };
 
+  // Blocks typing and the editing commands; text loaded by the app still
+  // goes through.
+  const setReadOnly = (readOnly: boolean) => {
+    if (readOnlyEnabled === readOnly) {
+      return;
+    }
+    readOnlyEnabled = readOnly;
+    editorView?.dispatch({
+      effects: readOnlyCompartment.reconfigure(EditorState.readOnly.of(readOnly))
+    });
+  };
+
   const openOrFocusFindPanel = () => {
     if (!editorView) {
       return false;
@@ -524,7 +575,7 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
 
   const cutSelection = async () => {
     const view = editorView;
-    if (!view) {
+    if (!view || readOnlyEnabled) {
       return false;
     }
     const copied = await copySelection();
@@ -544,7 +595,7 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
 
   const pasteSelection = async () => {
     const view = editorView;
-    if (!view) {
+    if (!view || readOnlyEnabled) {
       return false;
     }
     const text = await readText();
@@ -570,19 +621,19 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
     editorView ? editorView.state.field(formatting.modeField) : options.getSettings().formatViewMode;
 
   const toggleBoldFormat = () => {
-    if (editorView) {
+    if (editorView && !readOnlyEnabled) {
       toggleBold(editorView);
     }
   };
 
   const toggleItalicFormat = () => {
-    if (editorView) {
+    if (editorView && !readOnlyEnabled) {
       toggleItalic(editorView);
     }
   };
 
   const applyHeadingLevel = (level: number) => {
-    if (editorView) {
+    if (editorView && !readOnlyEnabled) {
       setHeadingLevel(editorView, level);
     }
   };
@@ -611,13 +662,18 @@ export const createEditorAdapter = (options: EditorAdapterOptions) => {
     getText,
     getDocLength,
     getTextSlice,
+    goToPosition,
     setText,
     append,
     reset,
     setLargeLineSafeMode,
+    setReadOnly,
     listSpellDictionaries,
+    detectSpellLanguage,
     listAddedWords,
     removeAddedWord,
+    installSpellDictionary,
+    uninstallSpellDictionary,
     configureSpellcheck,
     setFormatMode,
     getFormatMode,
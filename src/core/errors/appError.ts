@@ -1,6 +1,8 @@
 export type AppErrorCode =
   | "OPEN_FAILED"
   | "SAVE_FAILED"
+  | "EXPORT_FAILED"
+  | "PRINT_FAILED"
   | "LAUNCH_OPEN_FAILED"
   | "SETTINGS_LOAD_FAILED"
   | "FONT_PICK_FAILED"
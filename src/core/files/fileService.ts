@@ -1,5 +1,6 @@
-import { open as openDialog, save } from "@tauri-apps/plugin-dialog";
-import { open as openFile, readTextFile, stat, writeTextFile } from "@tauri-apps/plugin-fs";
+import { save } from "@tauri-apps/plugin-dialog";
+import { readTextFile, stat, writeTextFile } from "@tauri-apps/plugin-fs";
+import { pickOpenFile } from "../window/openStreamService";
 
 export type OpenFileResult =
   | { kind: "cancelled" }
@@ -13,16 +14,6 @@ export type SaveAsResult =
   | { kind: "cancelled" }
   | { kind: "saved"; filePath: string };
 
-const normalizeDialogPath = (value: string | string[] | null): string | null => {
-  if (!value) {
-    return null;
-  }
-  if (Array.isArray(value)) {
-    return value[0] ?? null;
-  }
-  return value;
-};
-
 const directoryFromPath = (filePath: string): string => {
   const normalized = filePath.replace(/\\/g, "/");
   const lastSlash = normalized.lastIndexOf("/");
@@ -35,20 +26,6 @@ const directoryFromPath = (filePath: string): string => {
   return normalized.slice(0, lastSlash);
 };
 
-const DEFAULT_STREAM_CHUNK_BYTES = 256 * 1024;
-const MIN_STREAM_CHUNK_BYTES = 4 * 1024;
-
-const normalizeChunkSizeBytes = (chunkSizeBytes?: number): number => {
-  if (typeof chunkSizeBytes !== "number" || !Number.isFinite(chunkSizeBytes)) {
-    return DEFAULT_STREAM_CHUNK_BYTES;
-  }
-  const normalized = Math.floor(chunkSizeBytes);
-  if (normalized < MIN_STREAM_CHUNK_BYTES) {
-    return MIN_STREAM_CHUNK_BYTES;
-  }
-  return normalized;
-};
-
 export const openTextFile = async (defaultPath?: string): Promise<OpenFileResult> => {
   const selectedPath = await openTextFilePath(defaultPath);
   if (selectedPath.kind === "cancelled") {
@@ -64,10 +41,7 @@ export const openTextFile = async (defaultPath?: string): Promise<OpenFileResult
 };
 
 export const openTextFilePath = async (defaultPath?: string): Promise<OpenFilePathResult> => {
-  const selected = normalizeDialogPath(await openDialog({
-    multiple: false,
-    defaultPath: defaultPath || undefined
-  }));
+  const selected = await pickOpenFile(defaultPath || undefined);
 
   if (!selected) {
     return { kind: "cancelled" };
@@ -99,6 +73,20 @@ export const saveTextFilePathAs = async (defaultPath?: string): Promise<SaveAsRe
   };
 };
 
+export const saveExportPathAs = async (
+  defaultPath: string,
+  filter: { name: string; extensions: string[] }
+): Promise<SaveAsResult> => {
+  const selected = await save({ defaultPath, filters: [filter] });
+  if (!selected) {
+    return { kind: "cancelled" };
+  }
+  return {
+    kind: "saved",
+    filePath: selected
+  };
+};
+
 export const saveTextFile = async (filePath: string, text: string): Promise<void> => {
   await writeTextFile(filePath, text);
 };
@@ -107,64 +95,6 @@ export const readTextFileAtPath = async (filePath: string): Promise<string> => {
   return readTextFile(filePath);
 };
 
-export const streamReadTextFileAtPath = async function* (
-  filePath: string,
-  options?: { chunkSizeBytes?: number }
-): AsyncGenerator<{ text: string; bytesReadTotal: number; fileSizeBytes?: number }, void, void> {
-  const fileInfo = await stat(filePath);
-  const fileSizeBytes = fileInfo.size;
-  const chunkSizeBytes = normalizeChunkSizeBytes(options?.chunkSizeBytes);
-  const buffer = new Uint8Array(chunkSizeBytes);
-  // Fatal mode refuses non-UTF-8 input instead of silently substituting
-  // replacement characters, which would corrupt the file on the next save.
-  const decoder = new TextDecoder("utf-8", { fatal: true });
-  const invalidUtf8Error = () =>
-    new Error(`File is not valid UTF-8 text: '${filePath}'`);
-
-  const handle = await openFile(filePath, { read: true });
-  let bytesReadTotal = 0;
-
-  try {
-    while (true) {
-      const readCount = await handle.read(buffer);
-      if (readCount === null || readCount <= 0) {
-        break;
-      }
-
-      bytesReadTotal += readCount;
-      let text: string;
-      try {
-        text = decoder.decode(buffer.subarray(0, readCount), { stream: true });
-      } catch {
-        throw invalidUtf8Error();
-      }
-      if (text) {
-        yield {
-          text,
-          bytesReadTotal,
-          fileSizeBytes
-        };
-      }
-    }
-
-    let trailingText: string;
-    try {
-      trailingText = decoder.decode();
-    } catch {
-      throw invalidUtf8Error();
-    }
-    if (trailingText) {
-      yield {
-        text: trailingText,
-        bytesReadTotal,
-        fileSizeBytes
-      };
-    }
-  } finally {
-    await handle.close();
-  }
-};
-
 export const getFileSize = async (filePath: string): Promise<number> => {
   const metadata = await stat(filePath);
   return metadata.size;
@@ -65,6 +65,42 @@ describe("createSettingsStore load", () => {
     await store.load();
     expect(store.state.fontSize).toBe(40);
   });
+
+  it("rejects an unknown persisted backupMode and falls back to the default", async () => {
+    backing.set("backupMode", "hourly");
+    const store = createSettingsStore();
+    await store.load();
+    expect(store.state.backupMode).toBe(DEFAULT_SETTINGS.backupMode);
+  });
+
+  it("loads a valid persisted backupMode", async () => {
+    backing.set("backupMode", "timestamped");
+    const store = createSettingsStore();
+    await store.load();
+    expect(store.state.backupMode).toBe("timestamped");
+  });
+
+  it("carries a single persisted spellCheckLanguage over to spellCheckLanguages", async () => {
+    backing.set("spellCheckLanguage", "de_DE");
+    const store = createSettingsStore();
+    await store.load();
+    expect(store.state.spellCheckLanguages).toEqual(["de_DE"]);
+  });
+
+  it("prefers persisted spellCheckLanguages over the older single language", async () => {
+    backing.set("spellCheckLanguage", "de_DE");
+    backing.set("spellCheckLanguages", ["en_GB", "de_DE"]);
+    const store = createSettingsStore();
+    await store.load();
+    expect(store.state.spellCheckLanguages).toEqual(["en_GB", "de_DE"]);
+  });
+
+  it("rejects an empty persisted spellCheckLanguages", async () => {
+    backing.set("spellCheckLanguages", []);
+    const store = createSettingsStore();
+    await store.load();
+    expect(store.state.spellCheckLanguages).toEqual(DEFAULT_SETTINGS.spellCheckLanguages);
+  });
 });
 
 describe("createSettingsStore actions", () => {
@@ -76,6 +112,15 @@ describe("createSettingsStore actions", () => {
     expect(backing.get("formatViewMode")).toBe("formatted");
   });
 
+  it("overrideViewModes updates state without persisting", async () => {
+    const store = createSettingsStore();
+    await store.load();
+    store.actions.overrideViewModes({ themeMode: "dark", formatViewMode: "not-a-real-mode" });
+    expect(store.state.themeMode).toBe("dark");
+    expect(store.state.formatViewMode).toBe(DEFAULT_SETTINGS.formatViewMode);
+    expect(backing.has("themeMode")).toBe(false);
+  });
+
   it("setActiveLineHighlightEnabled updates state and persists", async () => {
     const store = createSettingsStore();
     await store.load();
@@ -1,7 +1,7 @@
 import { createStore } from "solid-js/store";
 import { createSignal } from "solid-js";
 import { Store } from "@tauri-apps/plugin-store";
-import { AppSettings, DEFAULT_SETTINGS, FontStyle, FormatViewMode, ThemeMode } from "./settingsTypes";
+import { AppSettings, BackupMode, DEFAULT_SETTINGS, FontStyle, FormatViewMode, ThemeMode } from "./settingsTypes";
 
 const SETTINGS_FILE = "settings.json";
 
@@ -13,6 +13,14 @@ const isFontStyle = (value: unknown): value is FontStyle => value === "normal" |
 
 const isFormatViewMode = (value: unknown): value is FormatViewMode => value === "formatted" || value === "plain";
 
+const isBackupMode = (value: unknown): value is BackupMode =>
+  value === "off" || value === "single" || value === "numbered" || value === "timestamped";
+
+const isLanguageList = (value: unknown): value is string[] =>
+  Array.isArray(value)
+  && value.length > 0
+  && value.every((language) => typeof language === "string" && language.trim().length > 0);
+
 type SettingKey = keyof AppSettings;
 
 export const createSettingsStore = () => {
@@ -70,6 +78,19 @@ export const createSettingsStore = () => {
     await saveSetting("formatViewMode", formatViewMode);
   };
 
+  /**
+   * Applies `--theme` / `--format` from the command line to this window only;
+   * the saved settings are left alone. Unknown values are ignored.
+   */
+  const overrideViewModes = (overrides: { themeMode?: string | null; formatViewMode?: string | null }) => {
+    if (isThemeMode(overrides.themeMode)) {
+      setState({ themeMode: overrides.themeMode });
+    }
+    if (isFormatViewMode(overrides.formatViewMode)) {
+      setState({ formatViewMode: overrides.formatViewMode });
+    }
+  };
+
   const setStatusBarEnabled = async (enabled: boolean) => {
     setState({ statusBarEnabled: enabled });
     await saveSetting("statusBarEnabled", enabled);
@@ -80,9 +101,19 @@ export const createSettingsStore = () => {
     await saveSetting("spellCheckEnabled", enabled);
   };
 
-  const setSpellCheckLanguage = async (language: string) => {
-    setState({ spellCheckLanguage: language });
-    await saveSetting("spellCheckLanguage", language);
+  const setSpellCheckLanguages = async (languages: string[]) => {
+    setState({ spellCheckLanguages: [...languages] });
+    await saveSetting("spellCheckLanguages", [...languages]);
+  };
+
+  const setSpellCheckDetectLanguage = async (enabled: boolean) => {
+    setState({ spellCheckDetectLanguage: enabled });
+    await saveSetting("spellCheckDetectLanguage", enabled);
+  };
+
+  const setBackupMode = async (backupMode: BackupMode) => {
+    setState({ backupMode });
+    await saveSetting("backupMode", backupMode);
   };
 
   const setLastDirectory = async (lastDirectory: string) => {
@@ -125,7 +156,12 @@ export const createSettingsStore = () => {
     const loadedFormatViewMode = await backingStore.get("formatViewMode");
     const loadedStatusBarEnabled = await backingStore.get("statusBarEnabled");
     const loadedSpellCheckEnabled = await backingStore.get("spellCheckEnabled");
-    const loadedSpellCheckLanguage = await backingStore.get("spellCheckLanguage");
+    const loadedSpellCheckLanguages = await backingStore.get("spellCheckLanguages");
+    // Saved before more than one language could be checked at a time.
+    const legacySpellCheckLanguage = await backingStore.get("spellCheckLanguage");
+    const loadedSpellCheckDetectLanguage = await backingStore.get("spellCheckDetectLanguage");
+    const loadedSpellDictionaryDirs = await backingStore.get("spellDictionaryDirs");
+    const loadedBackupMode = await backingStore.get("backupMode");
     const loadedLastDirectory = await backingStore.get("lastDirectory");
     const loadedRecentFiles = await backingStore.get("recentFiles");
 
@@ -150,9 +186,19 @@ export const createSettingsStore = () => {
       spellCheckEnabled: typeof loadedSpellCheckEnabled === "boolean"
         ? loadedSpellCheckEnabled
         : DEFAULT_SETTINGS.spellCheckEnabled,
-      spellCheckLanguage: typeof loadedSpellCheckLanguage === "string" && loadedSpellCheckLanguage.trim().length > 0
-        ? loadedSpellCheckLanguage
-        : DEFAULT_SETTINGS.spellCheckLanguage,
+      spellCheckLanguages: isLanguageList(loadedSpellCheckLanguages)
+        ? loadedSpellCheckLanguages
+        : typeof legacySpellCheckLanguage === "string" && legacySpellCheckLanguage.trim().length > 0
+          ? [legacySpellCheckLanguage]
+          : DEFAULT_SETTINGS.spellCheckLanguages,
+      spellCheckDetectLanguage: typeof loadedSpellCheckDetectLanguage === "boolean"
+        ? loadedSpellCheckDetectLanguage
+        : DEFAULT_SETTINGS.spellCheckDetectLanguage,
+      spellDictionaryDirs: Array.isArray(loadedSpellDictionaryDirs)
+        && loadedSpellDictionaryDirs.every((dir) => typeof dir === "string")
+        ? (loadedSpellDictionaryDirs as string[])
+        : DEFAULT_SETTINGS.spellDictionaryDirs,
+      backupMode: isBackupMode(loadedBackupMode) ? loadedBackupMode : DEFAULT_SETTINGS.backupMode,
       lastDirectory: typeof loadedLastDirectory === "string" ? loadedLastDirectory : DEFAULT_SETTINGS.lastDirectory,
       recentFiles: Array.isArray(loadedRecentFiles) && loadedRecentFiles.every((f) => typeof f === "string")
         ? (loadedRecentFiles as string[]).slice(0, 3)
@@ -175,9 +221,12 @@ export const createSettingsStore = () => {
       setTextWrapEnabled,
       setActiveLineHighlightEnabled,
       setFormatViewMode,
+      overrideViewModes,
       setStatusBarEnabled,
       setSpellCheckEnabled,
-      setSpellCheckLanguage,
+      setSpellCheckLanguages,
+      setSpellCheckDetectLanguage,
+      setBackupMode,
       setLastDirectory,
       addRecentFile,
       setRecentFiles,
@@ -5,6 +5,13 @@ export type FontStyle = "normal" | "italic" | "oblique";
 /** Live rendering mode for Markdown-style formatting. */
 export type FormatViewMode = "formatted" | "plain";
 
+/**
+ * What saving keeps of the previous version: nothing, a single `file~`,
+ * numbered `file.~N~` copies, or time-stamped copies in a central directory.
+ * Read by the backend when a save finishes.
+ */
+export type BackupMode = "off" | "single" | "numbered" | "timestamped";
+
 export const FONT_PRESETS = {
   sans: "Noto Sans, Liberation Sans, sans-serif",
   serif: "Noto Serif, Liberation Serif, serif",
@@ -22,7 +29,16 @@ export type AppSettings = {
   formatViewMode: FormatViewMode;
   statusBarEnabled: boolean;
   spellCheckEnabled: boolean;
-  spellCheckLanguage: string;
+  /** Dictionary codes checked together; a word passes if any accepts it. */
+  spellCheckLanguages: string[];
+  /** Switch to the dictionary for an opened document's language. */
+  spellCheckDetectLanguage: boolean;
+  /**
+   * Absolute paths searched for dictionaries before DICPATH and the standard
+   * directories. Read by the backend; edited in settings.json.
+   */
+  spellDictionaryDirs: string[];
+  backupMode: BackupMode;
   lastDirectory: string;
   recentFiles: string[];
 };
@@ -38,7 +54,10 @@ export const DEFAULT_SETTINGS: AppSettings = {
   formatViewMode: "plain",
   statusBarEnabled: true,
   spellCheckEnabled: false,
-  spellCheckLanguage: "en_US",
+  spellCheckLanguages: ["en_US"],
+  spellCheckDetectLanguage: true,
+  spellDictionaryDirs: [],
+  backupMode: "off",
   lastDirectory: "",
   recentFiles: []
 };
@@ -0,0 +1,61 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import type { SpellSuggestion } from "./spellService";
+
+/**
+ * Lines and columns count from 1, offsets from 0; "\r\n" and a lone "\r"
+ * end a line as "\n" does. `byteOffset` is into the text as UTF-8, which for
+ * a file is the decoded text rather than the file's own bytes. `column` and
+ * `charOffset` count Unicode scalar values, not the UTF-16 units of a
+ * JavaScript string, with "\r\n" counted once as the editor shows it.
+ */
+export type SpellReportPosition = {
+  byteOffset: number;
+  charOffset: number;
+  line: number;
+  column: number;
+};
+
+export type SpellReportEntry = {
+  word: string;
+  count: number;
+  /** Only the most frequent misspellings are looked up. */
+  suggestions: SpellSuggestion[];
+  /** The first hundred occurrences, in document order. */
+  occurrences: SpellReportPosition[];
+};
+
+export type SpellReport = {
+  wordsChecked: number;
+  misspelledCount: number;
+  /** Most frequent first. */
+  misspellings: SpellReportEntry[];
+};
+
+export type SpellReportProgress = {
+  /** Bytes read while checking, then misspellings looked up. */
+  stage: "checking" | "suggesting";
+  done: number;
+  total: number;
+};
+
+const SPELL_REPORT_PROGRESS_EVENT = "spell-report-progress";
+
+/** Checks a file on disk, read and decoded by the backend. */
+export const spellReportForFile = async (filePath: string, encoding?: string): Promise<SpellReport> => {
+  return invoke<SpellReport>("spell_report_for_file", { filePath, encoding });
+};
+
+/** Checks text held by the editor, for a document with unsaved changes. */
+export const spellReportForText = async (text: string): Promise<SpellReport> => {
+  return invoke<SpellReport>("spell_report_for_text", { text });
+};
+
+/** Progress of a report on a large document, sent to this window only. */
+export const onSpellReportProgress = async (
+  handler: (progress: SpellReportProgress) => void
+): Promise<() => void> => {
+  return getCurrentWindow().listen<SpellReportProgress>(SPELL_REPORT_PROGRESS_EVENT, (event) => {
+    handler(event.payload);
+  });
+};
@@ -3,6 +3,26 @@ import { invoke } from "@tauri-apps/api/core";
 export type DictionaryInfo = {
   code: string;
   label: string;
+  /** Where its files were found; the first directory searched wins a tie. */
+  directory: string;
+  /** Installed from a package into the user directory, so it can be removed. */
+  removable: boolean;
+};
+
+/** An installed dictionary the text may be in, from language detection. */
+export type LanguageGuess = {
+  code: string;
+  label: string;
+  /** ISO 639-3 code shared by every dictionary for the same language. */
+  language: string;
+  /** From 0 to 1; null for the last guess, which had nothing to beat. */
+  confidence: number | null;
+};
+
+/** A suggested correction and the code of the dictionary that offered it. */
+export type SpellSuggestion = {
+  word: string;
+  dictionary: string;
 };
 
 /**
@@ -16,8 +36,16 @@ export const createSpellService = () => {
   const listDictionaries = (): Promise<DictionaryInfo[]> =>
     invoke<DictionaryInfo[]>("spell_list_dictionaries");
 
-  const loadDictionary = async (code: string): Promise<boolean> => {
-    const loaded = await invoke<boolean>("spell_load_dictionary", { code });
+  /** Installed dictionaries ranked by how likely `text` is in their language. */
+  const detectLanguage = (text: string): Promise<LanguageGuess[]> =>
+    invoke<LanguageGuess[]>("spell_detect_language", { text });
+
+  /**
+   * Replaces the loaded dictionaries with `codes`. A word is then correct if
+   * any of them accepts it. Resolves to the codes that were found.
+   */
+  const loadDictionaries = async (codes: string[]): Promise<string[]> => {
+    const loaded = await invoke<string[]>("spell_load_dictionary", { codes });
     correctnessCache.clear();
     return loaded;
   };
@@ -42,8 +70,8 @@ export const createSpellService = () => {
     return misspelled;
   };
 
-  const suggest = (word: string): Promise<string[]> =>
-    invoke<string[]>("spell_suggest", { word });
+  const suggest = (word: string): Promise<SpellSuggestion[]> =>
+    invoke<SpellSuggestion[]>("spell_suggest", { word });
 
   const addWord = async (word: string): Promise<void> => {
     await invoke("spell_add_word", { word });
@@ -63,17 +91,31 @@ export const createSpellService = () => {
     correctnessCache.clear();
   };
 
+  /**
+   * Installs the dictionaries in a LibreOffice extension (.oxt) or zip into
+   * the user dictionary directory. Resolves to the dictionaries it added.
+   */
+  const installDictionary = (packagePath: string): Promise<DictionaryInfo[]> =>
+    invoke<DictionaryInfo[]>("spell_install_dictionary", { packagePath });
+
+  /** Deletes an installed dictionary's files; reload to stop using it. */
+  const uninstallDictionary = (code: string): Promise<void> =>
+    invoke("spell_uninstall_dictionary", { code });
+
   const clearCache = () => correctnessCache.clear();
 
   return {
     listDictionaries,
-    loadDictionary,
+    detectLanguage,
+    loadDictionaries,
     findMisspelled,
     suggest,
     addWord,
     ignoreWord,
     listAddedWords,
     removeWord,
+    installDictionary,
+    uninstallDictionary,
     clearCache
   };
 };
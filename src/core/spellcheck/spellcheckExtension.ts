@@ -153,11 +153,17 @@ const createContextMenuHandler = (spellService: SpellService) => {
     menu.style.left = `${clientX}px`;
     menu.style.top = `${clientY}px`;
 
-    const addItem = (label: string, action: () => void, extraClass = "") => {
+    const addItem = (label: string, action: () => void, extraClass = "", detail = "") => {
       const item = document.createElement("button");
       item.type = "button";
       item.className = `cm-spell-menu-item${extraClass ? ` ${extraClass}` : ""}`;
       item.textContent = label;
+      if (detail) {
+        const source = document.createElement("span");
+        source.className = "cm-spell-menu-source";
+        source.textContent = detail;
+        item.appendChild(source);
+      }
       item.addEventListener("click", () => {
         closeMenu();
         action();
@@ -196,13 +202,15 @@ const createContextMenuHandler = (spellService: SpellService) => {
       note.textContent = "No suggestions";
       menu.appendChild(note);
     } else {
+      // Only name the dictionary when the suggestions come from more than one.
+      const mixed = new Set(suggestions.map((suggestion) => suggestion.dictionary)).size > 1;
       for (const suggestion of suggestions.slice(0, 8)) {
-        addItem(suggestion, () => {
+        addItem(suggestion.word, () => {
           view.dispatch({
-            changes: { from: target.from, to: target.to, insert: suggestion },
+            changes: { from: target.from, to: target.to, insert: suggestion.word },
             userEvent: "input.replace"
           });
-        }, "cm-spell-menu-suggestion");
+        }, "cm-spell-menu-suggestion", mixed ? suggestion.dictionary : "");
       }
     }
 
@@ -0,0 +1,48 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type DocumentStats = {
+  words: number;
+  characters: number;
+  charactersExcludingSpaces: number;
+  sentences: number;
+  paragraphs: number;
+  lines: number;
+  readingTimeSeconds: number;
+  /** Flesch reading ease, or null for a document without words. */
+  readability: number | null;
+  /** ATX heading counts for levels 1 to 6. */
+  headings: number[];
+};
+
+const STATS_CHUNK_CHARS = 256 * 1024;
+
+/** Counts a file on disk, read and decoded by the backend. */
+export const documentStatsForFile = async (filePath: string, encoding?: string): Promise<DocumentStats> => {
+  return invoke<DocumentStats>("document_stats", { filePath, encoding });
+};
+
+/**
+ * Counts text held by the editor, streamed to the backend in chunks that
+ * never split a surrogate pair.
+ */
+export const documentStatsForText = async (
+  totalChars: number,
+  getTextSlice: (from: number, to: number) => string
+): Promise<DocumentStats> => {
+  const streamId = await invoke<string>("start_document_stats_stream");
+  try {
+    for (let from = 0; from < totalChars;) {
+      let to = Math.min(totalChars, from + STATS_CHUNK_CHARS);
+      const lastCharCode = getTextSlice(to - 1, to).charCodeAt(0);
+      if (to < totalChars && lastCharCode >= 0xd800 && lastCharCode <= 0xdbff) {
+        to -= 1;
+      }
+      await invoke<void>("write_document_stats_chunk", { streamId, textChunk: getTextSlice(from, to) });
+      from = to;
+    }
+  } catch (error) {
+    await invoke<void>("cancel_document_stats_stream", { streamId });
+    throw error;
+  }
+  return invoke<DocumentStats>("finish_document_stats_stream", { streamId });
+};
@@ -0,0 +1,43 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { FontStyle, ThemeMode } from "../settings/settingsTypes";
+
+/** The look the Formatted view had when the document was exported. */
+export type ExportStyle = {
+  fontFamily: string;
+  fontSize: number;
+  fontStyle: FontStyle;
+  fontWeight: number;
+  themeMode: ThemeMode;
+};
+
+/** Paper size and margins of a PDF export, in points. A4 when omitted. */
+export type PageSetup = {
+  width: number;
+  height: number;
+  margin: number;
+};
+
+export type ExportResult = {
+  bytesWrittenTotal: number;
+};
+
+export const exportHtml = async (filePath: string, text: string, style: ExportStyle): Promise<ExportResult> => {
+  return invoke<ExportResult>("export_html", { filePath, text, style });
+};
+
+export const exportPdf = async (
+  filePath: string,
+  text: string,
+  style: ExportStyle,
+  page?: PageSetup
+): Promise<ExportResult> => {
+  return invoke<ExportResult>("export_pdf", { filePath, text, style, page });
+};
+
+export const exportOdt = async (filePath: string, text: string, style: ExportStyle): Promise<ExportResult> => {
+  return invoke<ExportResult>("export_odt", { filePath, text, style });
+};
+
+export const exportDocx = async (filePath: string, text: string, style: ExportStyle): Promise<ExportResult> => {
+  return invoke<ExportResult>("export_docx", { filePath, text, style });
+};
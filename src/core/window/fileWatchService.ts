@@ -0,0 +1,33 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+
+export type FileDiskStatus = "untracked" | "unchanged" | "modified" | "missing";
+
+export type FileChangedOnDiskEvent = {
+  path: string;
+  kind: "modified" | "removed" | "renamed";
+  newPath: string | null;
+};
+
+const FILE_CHANGED_EVENT = "file-changed-on-disk";
+
+/**
+ * Files are tracked by the backend once they are fully opened or saved;
+ * this reports changes made by other programs to the file open in this
+ * window, under the path the window opened it by.
+ */
+export const onFileChangedOnDisk = async (
+  handler: (event: FileChangedOnDiskEvent) => void
+): Promise<() => void> => {
+  return getCurrentWindow().listen<FileChangedOnDiskEvent>(FILE_CHANGED_EVENT, (event) => {
+    handler(event.payload);
+  });
+};
+
+export const checkFileOnDisk = async (filePath: string): Promise<FileDiskStatus> => {
+  return invoke<FileDiskStatus>("check_file_on_disk", { filePath });
+};
+
+export const unwatchFile = async (filePath: string): Promise<void> => {
+  await invoke<void>("unwatch_file", { filePath });
+};
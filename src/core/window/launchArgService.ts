@@ -1,21 +1,35 @@
 import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+
+/** Launch argument for standard input, and the save path for standard output. */
+export const STDIO_PATH = "-";
+
+const OPEN_FILES_EVENT = "open-files";
+
+/** 1-based, from `+N` or a `path:line[:col]` argument. */
+export type LaunchPosition = {
+  line: number;
+  column: number | null;
+};
 
 export type LaunchFileArg = {
   path: string;
   exists: boolean;
   fileSizeBytes?: number;
+  /** Launched as `wisty -`: open with `startStdinStream`. */
+  stdin: boolean;
+  position?: LaunchPosition;
+  /** `--readonly`. */
+  readOnly: boolean;
+  /** `--encoding`: a label that replaces encoding detection. */
+  encoding: string | null;
+  /** `--theme` and `--format`: this window's view, not saved to settings. */
+  themeMode: string | null;
+  formatViewMode: string | null;
+  /** Set when a `wisty --wait` process is blocked until this document closes. */
+  waitId: number | null;
 };
 
-export type LaunchFileStreamStartResult = {
-  streamId: string;
-  filePath: string;
-  fileSizeBytes: number;
-};
-
-export type LaunchFileStreamChunkResult =
-  | { kind: "chunk"; text: string; bytesReadTotal: number; fileSizeBytes: number }
-  | { kind: "eof"; bytesReadTotal: number; fileSizeBytes: number };
-
 export const takeLaunchFileArg = async (): Promise<LaunchFileArg | null> => {
   try {
     const value = await invoke<LaunchFileArg | null>("take_launch_file_arg");
@@ -25,21 +39,36 @@ export const takeLaunchFileArg = async (): Promise<LaunchFileArg | null> => {
   }
 };
 
-export const startLaunchFileStream = async (filePath: string): Promise<LaunchFileStreamStartResult> => {
-  return invoke<LaunchFileStreamStartResult>("start_launch_file_stream", { filePath });
+/**
+ * Tells a `wisty --wait` process its document was closed because the window
+ * moved on to another one. Closing the window releases it by itself.
+ */
+export const releaseLaunchWait = async (waitId: number): Promise<void> => {
+  await invoke<void>("release_launch_wait", { waitId });
 };
 
-export const readLaunchFileChunk = async (
-  streamId: string,
-  maxBytes: number
-): Promise<LaunchFileStreamChunkResult> => {
-  return invoke<LaunchFileStreamChunkResult>("read_launch_file_chunk", { streamId, maxBytes });
+/** Whether wisty was launched with `--stdout`. */
+export const launchWritesToStdout = async (): Promise<boolean> => {
+  try {
+    return await invoke<boolean>("launch_writes_to_stdout");
+  } catch {
+    return false;
+  }
 };
 
-export const cancelLaunchFileStream = async (streamId: string): Promise<void> => {
-  await invoke<void>("cancel_launch_file_stream", { streamId });
+/**
+ * Another `wisty` invocation forwarded files to this instance; they are
+ * queued for `takeLaunchFileArg`. The payload is how many were queued.
+ */
+export const onOpenFilesRequested = async (
+  handler: (fileCount: number) => void
+): Promise<() => void> => {
+  return getCurrentWindow().listen<number>(OPEN_FILES_EVENT, (event) => {
+    handler(event.payload);
+  });
 };
 
-export const closeLaunchFileStream = async (streamId: string): Promise<void> => {
-  await invoke<void>("close_launch_file_stream", { streamId });
+/** Opens a window for each launch file still queued. */
+export const openPendingLaunchWindows = async (): Promise<void> => {
+  await invoke<void>("open_pending_launch_windows");
 };
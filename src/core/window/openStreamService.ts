@@ -0,0 +1,53 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type LineEnding = "lf" | "crlf" | "cr" | "mixed" | "none";
+
+export type OpenFileStreamStartResult = {
+  streamId: string;
+  filePath: string;
+  fileSizeBytes: number;
+  encoding: string;
+  hasBom: boolean;
+  lineEnding: LineEnding;
+};
+
+export type OpenFileStreamChunkResult =
+  | { kind: "chunk"; text: string; bytesReadTotal: number; fileSizeBytes: number }
+  | { kind: "eof"; bytesReadTotal: number; fileSizeBytes: number };
+
+/**
+ * Shows the native Open dialog from the backend, which approves the chosen
+ * path for `startOpenFileStream`. Returns null when the dialog is cancelled.
+ */
+export const pickOpenFile = async (defaultDirectory?: string): Promise<string | null> => {
+  const value = await invoke<string | null>("pick_open_file", { defaultDirectory });
+  return value ?? null;
+};
+
+/** `encoding` is a label that replaces detection, as given to `--encoding`. */
+export const startOpenFileStream = async (
+  filePath: string,
+  encoding?: string
+): Promise<OpenFileStreamStartResult> => {
+  return invoke<OpenFileStreamStartResult>("start_open_file_stream", { filePath, encoding });
+};
+
+/** Streams the standard input wisty was launched with (`wisty -`). */
+export const startStdinStream = async (encoding?: string): Promise<OpenFileStreamStartResult> => {
+  return invoke<OpenFileStreamStartResult>("start_stdin_stream", { encoding });
+};
+
+export const readOpenFileChunk = async (
+  streamId: string,
+  maxBytes: number
+): Promise<OpenFileStreamChunkResult> => {
+  return invoke<OpenFileStreamChunkResult>("read_open_file_chunk", { streamId, maxBytes });
+};
+
+export const cancelOpenFileStream = async (streamId: string): Promise<void> => {
+  await invoke<void>("cancel_open_file_stream", { streamId });
+};
+
+export const closeOpenFileStream = async (streamId: string): Promise<void> => {
+  await invoke<void>("close_open_file_stream", { streamId });
+};
@@ -0,0 +1,7 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { ExportStyle } from "./exportService";
+
+/** Shows the print dialog. Resolves to whether the document was printed. */
+export const printDocument = async (text: string, style: ExportStyle, title: string): Promise<boolean> => {
+  return invoke<boolean>("print_document", { text, style, title });
+};
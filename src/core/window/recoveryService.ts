@@ -0,0 +1,58 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { LineEndingPolicy, SaveFileStreamOptions } from "./saveStreamService";
+
+export type RecoveryEntry = {
+  documentId: string;
+  filePath: string | null;
+  snapshotPath: string;
+  savedAtMs: number;
+  sizeBytes: number;
+  encoding: string | null;
+  writeBom: boolean | null;
+  lineEndingPolicy: LineEndingPolicy | null;
+};
+
+export type RecoverySnapshotTarget = {
+  /** Path of the document, or empty for an untitled buffer. */
+  filePath: string;
+  /** Identifies an untitled buffer; ignored when `filePath` is set. */
+  untitledId: string;
+};
+
+export type RecoverySnapshotStartResult = {
+  streamId: string;
+  documentId: string;
+};
+
+export const startRecoverySnapshot = async (
+  target: RecoverySnapshotTarget,
+  format?: SaveFileStreamOptions
+): Promise<RecoverySnapshotStartResult> => {
+  return invoke<RecoverySnapshotStartResult>("start_recovery_snapshot", {
+    filePath: target.filePath || undefined,
+    untitledId: target.untitledId,
+    encoding: format?.encoding,
+    writeBom: format?.writeBom,
+    lineEndingPolicy: format?.lineEndingPolicy
+  });
+};
+
+export const writeRecoverySnapshotChunk = async (streamId: string, textChunk: string): Promise<void> => {
+  await invoke<void>("write_recovery_snapshot_chunk", { streamId, textChunk });
+};
+
+export const finishRecoverySnapshot = async (streamId: string): Promise<RecoveryEntry> => {
+  return invoke<RecoveryEntry>("finish_recovery_snapshot", { streamId });
+};
+
+export const cancelRecoverySnapshot = async (streamId: string): Promise<void> => {
+  await invoke<void>("cancel_recovery_snapshot", { streamId });
+};
+
+export const listRecoverableDocuments = async (): Promise<RecoveryEntry[]> => {
+  return invoke<RecoveryEntry[]>("list_recoverable_documents");
+};
+
+export const discardRecoverySnapshot = async (documentId: string): Promise<void> => {
+  await invoke<void>("discard_recovery_snapshot", { documentId });
+};
@@ -1,8 +1,23 @@
 import { invoke } from "@tauri-apps/api/core";
 
+/** The editor's text always uses "\n"; "crlf" and "cr" rewrite it on save. */
+export type LineEndingPolicy = "lf" | "crlf" | "cr";
+
+export type SaveFileStreamOptions = {
+  encoding?: string;
+  writeBom?: boolean;
+  lineEndingPolicy?: LineEndingPolicy;
+  /** Save even if the file changed on disk since it was opened. */
+  overwriteExternalChanges?: boolean;
+};
+
 export type SaveFileStreamStartResult = {
   streamId: string;
   filePath: string;
+  encoding: string;
+  hasBom: boolean;
+  /** The user can't write the file; finishing hands it to pkexec or sudo. */
+  requiresElevation: boolean;
 };
 
 export type SaveFileStreamWriteResult = {
@@ -13,8 +28,17 @@ export type SaveFileStreamFinishResult = {
   bytesWrittenTotal: number;
 };
 
-export const startSaveFileStream = async (filePath: string): Promise<SaveFileStreamStartResult> => {
-  return invoke<SaveFileStreamStartResult>("start_save_file_stream", { filePath });
+export const startSaveFileStream = async (
+  filePath: string,
+  options?: SaveFileStreamOptions
+): Promise<SaveFileStreamStartResult> => {
+  return invoke<SaveFileStreamStartResult>("start_save_file_stream", {
+    filePath,
+    encoding: options?.encoding,
+    writeBom: options?.writeBom,
+    lineEndingPolicy: options?.lineEndingPolicy,
+    overwriteExternalChanges: options?.overwriteExternalChanges
+  });
 };
 
 export const writeSaveFileChunk = async (